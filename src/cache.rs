@@ -0,0 +1,532 @@
+use gix::bstr::BString;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::context::Context;
+use crate::logger;
+use crate::utils::{self, CommandOutput};
+
+/// Output of a version command, valid as long as the binary is unchanged.
+#[derive(Serialize, Deserialize, Debug)]
+struct VersionEntry {
+    binary: PathBuf,
+    args: Vec<String>,
+    dir: PathBuf,
+    modified: SystemTime,
+    size: u64,
+    output: CommandOutput,
+}
+
+/// Untracked files of a worktree, valid as long as `key` is unchanged.
+#[derive(Serialize, Deserialize, Debug)]
+struct UntrackedEntry {
+    key: String,
+    untracked: Vec<Vec<u8>>,
+}
+
+/// Environment variables that version managers use to override the selected version.
+const VERSION_ENV_VARS: &[&str] = &[
+    "RUSTUP_TOOLCHAIN",
+    "PYENV_VERSION",
+    "NODENV_VERSION",
+    "RBENV_VERSION",
+    "GOENV_VERSION",
+    "ASDF_DEFAULT_TOOL_VERSIONS_FILENAME",
+    "MISE_ENV",
+];
+
+/// Files that version managers read from the current directory or its ancestors.
+const VERSION_PIN_FILES: &[&str] = &[
+    "rust-toolchain.toml",
+    "rust-toolchain",
+    ".python-version",
+    ".tool-versions",
+    ".node-version",
+    ".nvmrc",
+    ".ruby-version",
+    ".go-version",
+    "mise.toml",
+    ".mise.toml",
+];
+
+/// Entries are removed once they are older than this, or when there are too many of them.
+const MAX_ENTRY_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+const MAX_ENTRIES: usize = 512;
+
+/// Returns the directory starship caches data in across prompts.
+pub fn cache_dir(context: &Context) -> PathBuf {
+    context
+        .get_env_os("STARSHIP_CACHE")
+        .map_or_else(logger::get_log_dir, PathBuf::from)
+}
+
+fn versions_dir(context: &Context) -> PathBuf {
+    cache_dir(context).join("versions")
+}
+
+fn untracked_dir(context: &Context) -> PathBuf {
+    cache_dir(context).join("untracked")
+}
+
+fn untracked_file(context: &Context, workdir: &Path) -> PathBuf {
+    let mut hasher = Sha1::new();
+    hasher.update(workdir.as_os_str().as_encoded_bytes());
+    untracked_dir(context).join(format!("{}.json", utils::encode_to_hex(&hasher.finalize())))
+}
+
+/// Returns the untracked files last stored for `workdir`, if they were stored with `key`.
+pub fn untracked_files(context: &Context, workdir: &Path, key: &str) -> Option<Vec<BString>> {
+    let content = utils::read_file(untracked_file(context, workdir)).ok()?;
+    let entry: UntrackedEntry = serde_json::from_str(&content).ok()?;
+    (entry.key == key).then(|| entry.untracked.into_iter().map(BString::from).collect())
+}
+
+/// Stores the untracked files of `workdir`, replacing the ones stored before.
+pub fn store_untracked_files(context: &Context, workdir: &Path, key: &str, untracked: &[BString]) {
+    let file = untracked_file(context, workdir);
+    let entry = UntrackedEntry {
+        key: key.to_string(),
+        untracked: untracked.iter().map(|path| path.to_vec()).collect(),
+    };
+    if let Err(e) = write_entry(&file, &entry) {
+        log::debug!("Unable to cache untracked files in {file:?}: {e}");
+    }
+    if let Err(e) = evict_entries(&untracked_dir(context), SystemTime::now()) {
+        log::debug!("Unable to evict cached untracked files: {e}");
+    }
+}
+
+/// Returns the cached output of running `binary` with `args` in the current directory,
+/// or runs `exec` and caches its output if the binary was modified since it was cached.
+pub fn version_output<U: AsRef<OsStr>>(
+    context: &Context,
+    binary: &Path,
+    args: &[U],
+    exec: impl FnOnce() -> Option<CommandOutput>,
+) -> Option<CommandOutput> {
+    let Ok(metadata) = fs::metadata(binary) else {
+        return exec();
+    };
+    let (Ok(modified), size) = (metadata.modified(), metadata.len()) else {
+        return exec();
+    };
+
+    let args: Vec<String> = args
+        .iter()
+        .map(|arg| arg.as_ref().to_string_lossy().into_owned())
+        .collect();
+    let file = versions_dir(context).join(format!(
+        "{}.json",
+        entry_key(
+            binary,
+            &args,
+            &context.current_dir,
+            &version_selectors(context)
+        )
+    ));
+
+    let cached = utils::read_file(&file)
+        .ok()
+        .and_then(|content| serde_json::from_str::<VersionEntry>(&content).ok())
+        .filter(|entry| entry.modified == modified && entry.size == size);
+    if let Some(entry) = cached {
+        log::trace!("Using cached output of {binary:?} {args:?}");
+        return Some(entry.output);
+    }
+
+    let output = exec()?;
+    let entry = VersionEntry {
+        binary: binary.to_owned(),
+        args,
+        dir: context.current_dir.clone(),
+        modified,
+        size,
+        output,
+    };
+    if let Err(e) = write_entry(&file, &entry) {
+        log::debug!("Unable to cache output of {binary:?} in {file:?}: {e}");
+    }
+    if let Err(e) = evict_entries(&versions_dir(context), SystemTime::now()) {
+        log::debug!("Unable to evict cached versions: {e}");
+    }
+    Some(entry.output)
+}
+
+/// Collects the inputs shims like `rustup`, `pyenv`, `asdf` or `mise` use to select a version:
+/// the overriding environment variables and the modification times of the nearest pin files
+/// and of the files selecting the global version.
+fn version_selectors(context: &Context) -> Vec<u8> {
+    let mut selectors = Vec::new();
+    for name in VERSION_ENV_VARS {
+        if let Some(value) = context.get_env_os(name) {
+            selectors.extend_from_slice(name.as_bytes());
+            selectors.push(b'=');
+            selectors.extend_from_slice(value.as_encoded_bytes());
+            selectors.push(0);
+        }
+    }
+
+    let mut remaining: Vec<&str> = VERSION_PIN_FILES.to_vec();
+    for dir in context.current_dir.ancestors() {
+        remaining.retain(|name| !push_modified(&mut selectors, &dir.join(name)));
+        if remaining.is_empty() {
+            break;
+        }
+    }
+
+    for path in global_version_files(context) {
+        push_modified(&mut selectors, &path);
+    }
+    selectors
+}
+
+/// Adds the path and modification time of the file to the selectors.
+/// Returns false if the file doesn't exist.
+fn push_modified(selectors: &mut Vec<u8>, path: &Path) -> bool {
+    let Ok(modified) = fs::metadata(path).and_then(|metadata| metadata.modified()) else {
+        return false;
+    };
+    let since_epoch = modified
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    selectors.extend_from_slice(path.as_os_str().as_encoded_bytes());
+    selectors.push(b'@');
+    selectors.extend_from_slice(&since_epoch.as_nanos().to_le_bytes());
+    selectors.push(0);
+    true
+}
+
+/// Files that version managers fall back to when no pin file selects a version.
+fn global_version_files(context: &Context) -> Vec<PathBuf> {
+    let home = context.get_home();
+    let root = |var: &str, default: &str| {
+        context
+            .get_env_os(var)
+            .map(PathBuf::from)
+            .or_else(|| Some(home.as_ref()?.join(default)))
+    };
+    let mise_config_dir = context
+        .get_env_os("MISE_CONFIG_DIR")
+        .map(PathBuf::from)
+        .or_else(|| {
+            let config_dir = context
+                .get_env_os("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|| Some(home.as_ref()?.join(".config")))?;
+            Some(config_dir.join("mise"))
+        });
+
+    let mut files = vec![
+        root("RUSTUP_HOME", ".rustup").map(|dir| dir.join("settings.toml")),
+        root("PYENV_ROOT", ".pyenv").map(|dir| dir.join("version")),
+        root("RBENV_ROOT", ".rbenv").map(|dir| dir.join("version")),
+        root("NODENV_ROOT", ".nodenv").map(|dir| dir.join("version")),
+        root("GOENV_ROOT", ".goenv").map(|dir| dir.join("version")),
+        root("NVM_DIR", ".nvm").map(|dir| dir.join("alias").join("default")),
+        mise_config_dir.map(|dir| dir.join("config.toml")),
+    ];
+    // Pin files in the home directory are already found in the ancestors of the current one
+    if let Some(home) = home.filter(|home| !context.current_dir.starts_with(home)) {
+        files.push(Some(home.join(".tool-versions")));
+    }
+    files.into_iter().flatten().collect()
+}
+
+/// Tools like `rustup` or `pyenv` select a version based on the directory they run in,
+/// so outputs are cached per directory and per version selector.
+fn entry_key(binary: &Path, args: &[String], dir: &Path, selectors: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(binary.as_os_str().as_encoded_bytes());
+    for arg in args {
+        hasher.update([0]);
+        hasher.update(arg.as_bytes());
+    }
+    hasher.update([0]);
+    hasher.update(dir.as_os_str().as_encoded_bytes());
+    hasher.update([0]);
+    hasher.update(selectors);
+    utils::encode_to_hex(&hasher.finalize())
+}
+
+/// Removes entries older than `MAX_ENTRY_AGE`, then the oldest entries beyond `MAX_ENTRIES`.
+pub fn evict_entries(dir: &Path, now: SystemTime) -> io::Result<()> {
+    let mut entries: Vec<(SystemTime, PathBuf)> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension() == Some(OsStr::new("json")))
+        .filter_map(|entry| {
+            let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect();
+    entries.sort_unstable_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+
+    for (index, (modified, path)) in entries.iter().enumerate() {
+        let expired = now
+            .duration_since(*modified)
+            .is_ok_and(|age| age > MAX_ENTRY_AGE);
+        if expired || index >= MAX_ENTRIES {
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_entry(file: &Path, entry: &impl Serialize) -> io::Result<()> {
+    let dir = file.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dir)?;
+
+    // Write to a temporary file first, so concurrent prompts never read a partial entry
+    let content = serde_json::to_string(entry)?;
+    let tmp = file.with_extension(format!("{}.tmp", std::process::id()));
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, file).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        e
+    })
+}
+
+fn read_entries(context: &Context) -> Vec<VersionEntry> {
+    let Ok(dir) = fs::read_dir(versions_dir(context)) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<VersionEntry> = dir
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension() == Some(OsStr::new("json")))
+        .filter_map(|entry| utils::read_file(entry.path()).ok())
+        .filter_map(|content| serde_json::from_str(&content).ok())
+        .collect();
+    entries.sort_by(|a, b| (&a.binary, &a.dir).cmp(&(&b.binary, &b.dir)));
+    entries
+}
+
+fn format_entries(entries: &[VersionEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| {
+            let output = if entry.output.stdout.trim().is_empty() {
+                &entry.output.stderr
+            } else {
+                &entry.output.stdout
+            };
+            format!(
+                "{} {} (in {}): {}\n",
+                entry.binary.display(),
+                entry.args.join(" "),
+                entry.dir.display(),
+                output.lines().next().unwrap_or_default().trim()
+            )
+        })
+        .collect()
+}
+
+/// Prints the cached tool versions
+pub fn show(context: &Context) {
+    let entries = read_entries(context);
+    if entries.is_empty() {
+        println!("No cached versions in {}", versions_dir(context).display());
+        return;
+    }
+    print!("{}", format_entries(&entries));
+}
+
+/// Removes all cached tool versions, untracked files and asynchronously rendered modules
+pub fn clear(context: &Context) -> io::Result<()> {
+    let cache_dir = cache_dir(context);
+    for name in ["versions", "untracked", "async"] {
+        match fs::remove_dir_all(cache_dir.join(name)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::default_context;
+    use std::cell::Cell;
+
+    fn output(stdout: &str) -> Option<CommandOutput> {
+        Some(CommandOutput {
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        })
+    }
+
+    fn context_with_cache(dir: &Path) -> Context<'static> {
+        let mut context = default_context();
+        context
+            .env
+            .insert("STARSHIP_CACHE", dir.to_string_lossy().to_string());
+        context
+    }
+
+    #[test]
+    fn reuses_output_until_binary_changes() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let context = context_with_cache(dir.path());
+        let binary = dir.path().join("node");
+        fs::write(&binary, "v1")?;
+        let runs = Cell::new(0);
+        let exec = |stdout| {
+            runs.set(runs.get() + 1);
+            output(stdout)
+        };
+
+        let first = version_output(&context, &binary, &["--version"], || exec("v20.0.0\n"));
+        let second = version_output(&context, &binary, &["--version"], || exec("v21.0.0\n"));
+        assert_eq!(first, output("v20.0.0\n"));
+        assert_eq!(second, output("v20.0.0\n"));
+        assert_eq!(runs.get(), 1);
+
+        fs::write(&binary, "v22")?;
+        let third = version_output(&context, &binary, &["--version"], || exec("v22.0.0\n"));
+        assert_eq!(third, output("v22.0.0\n"));
+        assert_eq!(runs.get(), 2);
+        dir.close()
+    }
+
+    #[test]
+    fn caches_per_arguments_and_directory() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut context = context_with_cache(dir.path());
+        let binary = dir.path().join("rustc");
+        fs::write(&binary, "")?;
+
+        version_output(&context, &binary, &["--version"], || output("rustc 1.80.0"));
+        let verbose = version_output(&context, &binary, &["-vV"], || output("rustc 1.80.0\nhost"));
+        assert_eq!(verbose, output("rustc 1.80.0\nhost"));
+
+        context.current_dir = dir.path().join("project");
+        let other_dir =
+            version_output(&context, &binary, &["--version"], || output("rustc 1.81.0"));
+        assert_eq!(other_dir, output("rustc 1.81.0"));
+        dir.close()
+    }
+
+    #[test]
+    fn caches_per_version_selector() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut context = context_with_cache(dir.path());
+        context.current_dir = dir.path().join("project/src");
+        fs::create_dir_all(&context.current_dir)?;
+        let binary = dir.path().join("python");
+        fs::write(&binary, "")?;
+
+        version_output(&context, &binary, &["--version"], || {
+            output("Python 3.12.0")
+        });
+
+        context.env.insert("PYENV_VERSION", "3.11.0".to_string());
+        let from_env = version_output(&context, &binary, &["--version"], || {
+            output("Python 3.11.0")
+        });
+        assert_eq!(from_env, output("Python 3.11.0"));
+
+        context.env = context_with_cache(dir.path()).env;
+        fs::write(dir.path().join("project/.python-version"), "3.10.0")?;
+        let from_pin = version_output(&context, &binary, &["--version"], || {
+            output("Python 3.10.0")
+        });
+        assert_eq!(from_pin, output("Python 3.10.0"));
+        dir.close()
+    }
+
+    #[test]
+    fn caches_per_global_version_file() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let home = tempfile::tempdir()?;
+        let mut context = context_with_cache(dir.path());
+        context
+            .env
+            .insert("HOME", home.path().to_string_lossy().to_string());
+        let binary = dir.path().join("python");
+        fs::write(&binary, "")?;
+
+        version_output(&context, &binary, &["--version"], || {
+            output("Python 3.12.0")
+        });
+
+        fs::create_dir(home.path().join(".pyenv"))?;
+        fs::write(home.path().join(".pyenv/version"), "3.11.0")?;
+        let from_pyenv = version_output(&context, &binary, &["--version"], || {
+            output("Python 3.11.0")
+        });
+        assert_eq!(from_pyenv, output("Python 3.11.0"));
+
+        fs::write(home.path().join(".tool-versions"), "python 3.10.0")?;
+        let from_asdf = version_output(&context, &binary, &["--version"], || {
+            output("Python 3.10.0")
+        });
+        assert_eq!(from_asdf, output("Python 3.10.0"));
+        home.close()?;
+        dir.close()
+    }
+
+    #[test]
+    fn evicts_old_and_excess_entries() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        for i in 0..=MAX_ENTRIES {
+            fs::write(dir.path().join(format!("{i}.json")), "{}")?;
+        }
+        fs::write(dir.path().join("unrelated"), "")?;
+        let count = || fs::read_dir(dir.path()).map(Iterator::count);
+
+        evict_entries(dir.path(), SystemTime::now())?;
+        assert_eq!(count()?, MAX_ENTRIES + 1);
+
+        let later = SystemTime::now() + MAX_ENTRY_AGE + Duration::from_secs(60);
+        evict_entries(dir.path(), later)?;
+        assert_eq!(count()?, 1);
+        dir.close()
+    }
+
+    #[test]
+    fn does_not_cache_failures() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let context = context_with_cache(dir.path());
+        let binary = dir.path().join("go");
+        fs::write(&binary, "")?;
+
+        assert_eq!(
+            version_output(&context, &binary, &["version"], || None),
+            None
+        );
+        let actual = version_output(&context, &binary, &["version"], || output("go1.23.0"));
+        assert_eq!(actual, output("go1.23.0"));
+        dir.close()
+    }
+
+    #[test]
+    fn show_and_clear() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut context = context_with_cache(dir.path());
+        context.current_dir = PathBuf::from("/project");
+        let binary = dir.path().join("java");
+        fs::write(&binary, "")?;
+        fs::create_dir_all(dir.path().join("async"))?;
+
+        version_output(&context, &binary, &["-Xinternalversion"], || {
+            output("OpenJDK 64-Bit Server VM (21.0.2+13)\nmore")
+        });
+        let expected = format!(
+            "{} -Xinternalversion (in /project): OpenJDK 64-Bit Server VM (21.0.2+13)\n",
+            binary.display()
+        );
+        assert_eq!(format_entries(&read_entries(&context)), expected);
+
+        clear(&context)?;
+        assert!(read_entries(&context).is_empty());
+        assert!(!dir.path().join("async").exists());
+        dir.close()
+    }
+}
@@ -10,9 +10,11 @@ use serde::{
 
 use std::borrow::Cow;
 use std::clone::Clone;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::OsString;
+use std::fmt;
 use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
 
 use toml::Value;
 
@@ -121,6 +123,122 @@ pub struct StarshipConfig {
     pub config: Option<toml::Table>,
 }
 
+/// The key listing the files a configuration file is based on
+const INCLUDE_KEY: &str = "include";
+
+/// A configuration file merged with the files it includes
+#[derive(Debug, Default)]
+pub struct LayeredConfig {
+    pub table: toml::Table,
+    /// Every file the configuration was read from, or tried to be read from
+    pub files: Vec<PathBuf>,
+    /// The file each value was set in, by its dotted key
+    pub origins: BTreeMap<String, PathBuf>,
+}
+
+impl LayeredConfig {
+    /// Parses `content`, read from `path`, and merges the files it includes into it
+    fn load(
+        &mut self,
+        path: &Path,
+        content: &str,
+        stack: &mut Vec<PathBuf>,
+    ) -> Option<toml::Table> {
+        let mut table: toml::Table = match toml::from_str(content) {
+            Ok(table) => table,
+            Err(error) => {
+                log::error!("Unable to parse the config file {path:?}: {error}");
+                return None;
+            }
+        };
+
+        stack.push(dunce::canonicalize(path).unwrap_or_else(|_| path.to_owned()));
+        let mut merged = toml::Table::new();
+        for include in include_paths(path, table.remove(INCLUDE_KEY)) {
+            self.files.push(include.clone());
+            let canonical = dunce::canonicalize(&include).unwrap_or_else(|_| include.clone());
+            if let Some(start) = stack.iter().position(|file| *file == canonical) {
+                let chain = stack[start..]
+                    .iter()
+                    .chain([&canonical])
+                    .map(|file| file.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                log::error!("Ignoring cyclic include of config file {include:?}: {chain}");
+                continue;
+            }
+            let content = match utils::read_file(&include) {
+                Ok(content) => content,
+                Err(e) => {
+                    log::error!(
+                        "Unable to read config file {include:?} included from {path:?}: {e}"
+                    );
+                    continue;
+                }
+            };
+            if let Some(included) = self.load(&include, &content, stack) {
+                merge_tables(&mut merged, included);
+            }
+        }
+        stack.pop();
+
+        self.set_origins("", &table, path);
+        merge_tables(&mut merged, table);
+        Some(merged)
+    }
+
+    fn set_origins(&mut self, prefix: &str, table: &toml::Table, path: &Path) {
+        for (key, value) in table {
+            let key = format!("{prefix}{key}");
+            match value {
+                Value::Table(table) => self.set_origins(&format!("{key}."), table, path),
+                _ => {
+                    // A value replaces any table an included file set for the same key
+                    let nested = format!("{key}.");
+                    self.origins
+                        .retain(|origin, _| !origin.starts_with(&nested));
+                    self.origins.insert(key, path.to_owned());
+                }
+            }
+        }
+    }
+}
+
+/// Returns the paths in an `include` value, relative to the file they are included from
+fn include_paths(path: &Path, include: Option<Value>) -> Vec<PathBuf> {
+    let includes = match include {
+        None => return Vec::new(),
+        Some(Value::String(include)) => vec![include],
+        Some(Value::Array(includes)) if includes.iter().all(Value::is_str) => includes
+            .into_iter()
+            .filter_map(|include| include.as_str().map(ToOwned::to_owned))
+            .collect(),
+        Some(include) => {
+            log::error!(
+                "Invalid `{INCLUDE_KEY}` in config file {path:?}, expected a list of paths but got {include}"
+            );
+            return Vec::new();
+        }
+    };
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    includes
+        .into_iter()
+        .map(|include| dir.join(Context::expand_tilde(PathBuf::from(include))))
+        .collect()
+}
+
+/// Merges `overrides` into `base`, replacing everything but tables, which are merged recursively
+pub fn merge_tables(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overrides)) => merge_tables(base, overrides),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
 impl StarshipConfig {
     /// Initialize the Config struct
     pub fn initialize(config_file_path: &Option<OsString>) -> Self {
@@ -133,18 +251,21 @@ impl StarshipConfig {
 
     /// Create a config from a starship configuration file
     fn config_from_file(config_file_path: &Option<OsString>) -> Option<toml::Table> {
+        Self::load_layered(config_file_path).map(|config| config.table)
+    }
+
+    /// Load a starship configuration file and the files it includes
+    pub fn load_layered(config_file_path: &Option<OsString>) -> Option<LayeredConfig> {
         let toml_content = Self::read_config_content_as_str(config_file_path)?;
+        let path = PathBuf::from(config_file_path.as_ref()?);
 
-        match toml::from_str(&toml_content) {
-            Ok(parsed) => {
-                log::debug!("Config parsed: {:?}", &parsed);
-                Some(parsed)
-            }
-            Err(error) => {
-                log::error!("Unable to parse the config file: {}", error);
-                None
-            }
-        }
+        let mut config = LayeredConfig {
+            files: vec![path.clone()],
+            ..Default::default()
+        };
+        config.table = config.load(&path, &toml_content, &mut Vec::new())?;
+        log::debug!("Config parsed: {:?}", &config.table);
+        Some(config)
     }
 
     pub fn read_config_content_as_str(config_file_path: &Option<OsString>) -> Option<String> {
@@ -309,6 +430,45 @@ impl Style {
         current
     }
 
+    /// Takes the colors that aren't set in this style from `defaults`
+    pub fn with_defaults(&self, defaults: &Self) -> Self {
+        let mut style = *self;
+        if self.style.foreground.is_none() && self.fg.is_none() {
+            style.style.foreground = defaults.style.foreground;
+            style.fg = defaults.fg;
+        }
+        if self.style.background.is_none() && self.bg.is_none() {
+            style.style.background = defaults.style.background;
+            style.bg = defaults.bg;
+        }
+        style
+    }
+
+    /// The style of a powerline transition glyph: drawn in the previous background color,
+    /// on the background of `next` (or the terminal background if there is no `next`).
+    pub fn transition_to(next: Option<&Self>) -> Self {
+        Self {
+            style: nu_ansi_term::Style {
+                background: next.and_then(|next| next.style.background),
+                ..Default::default()
+            },
+            bg: next.and_then(|next| next.bg),
+            fg: Some(PrevColor::Bg),
+        }
+    }
+
+    /// The style of a glyph drawn in the background color of `self` on the terminal background
+    pub fn background_as_foreground(&self) -> Self {
+        Self {
+            style: nu_ansi_term::Style {
+                foreground: self.style.background,
+                ..Default::default()
+            },
+            bg: None,
+            fg: self.bg,
+        }
+    }
+
     fn map_style<F>(&self, f: F) -> Self
     where
         F: FnOnce(&nu_ansi_term::Style) -> nu_ansi_term::Style,
@@ -334,6 +494,61 @@ impl Style {
     }
 }
 
+/// Formats the style as a style string that `parse_style_string` parses back into the same style.
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let style = &self.style;
+        let mut tokens: Vec<Cow<str>> = [
+            (style.is_bold, "bold"),
+            (style.is_dimmed, "dimmed"),
+            (style.is_italic, "italic"),
+            (style.is_underline, "underline"),
+            (style.is_blink, "blink"),
+            (style.is_reverse, "inverted"),
+            (style.is_hidden, "hidden"),
+            (style.is_strikethrough, "strikethrough"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, token)| enabled.then_some(Cow::Borrowed(token)))
+        .collect();
+
+        // The colors are kept as a fallback for when there is no previous style
+        for (prefix, color, prev) in [
+            ("fg", style.foreground, self.fg),
+            ("bg", style.background, self.bg),
+        ] {
+            if let Some(color) = color.and_then(color_to_string) {
+                tokens.push(format!("{prefix}:{color}").into());
+            }
+            match prev {
+                Some(PrevColor::Fg) => tokens.push(format!("{prefix}:prev_fg").into()),
+                Some(PrevColor::Bg) => tokens.push(format!("{prefix}:prev_bg").into()),
+                None => {}
+            }
+        }
+
+        write!(f, "{}", tokens.join(" "))
+    }
+}
+
+impl Serialize for Style {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Style {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_style(deserializer)
+    }
+}
+
 impl From<nu_ansi_term::Style> for Style {
     fn from(value: nu_ansi_term::Style) -> Self {
         Self {
@@ -508,6 +723,33 @@ fn parse_color_string(
     predefined_color
 }
 
+/// Returns the color string that `parse_color_string` parses back into `color`.
+/// The terminal default color has no color string.
+pub(crate) fn color_to_string(color: Color) -> Option<String> {
+    let name = match color {
+        Color::Black => "black",
+        Color::Red => "red",
+        Color::Green => "green",
+        Color::Yellow => "yellow",
+        Color::Blue => "blue",
+        Color::Purple | Color::Magenta => "purple",
+        Color::Cyan => "cyan",
+        Color::White => "white",
+        Color::DarkGray => "bright-black",
+        Color::LightRed => "bright-red",
+        Color::LightGreen => "bright-green",
+        Color::LightYellow => "bright-yellow",
+        Color::LightBlue => "bright-blue",
+        Color::LightPurple | Color::LightMagenta => "bright-purple",
+        Color::LightCyan => "bright-cyan",
+        Color::LightGray => "bright-white",
+        Color::Fixed(n) => return Some(n.to_string()),
+        Color::Rgb(r, g, b) => return Some(format!("#{r:02x}{g:02x}{b:02x}")),
+        Color::Default => return None,
+    };
+    Some(name.to_string())
+}
+
 fn get_palette<'a>(
     palettes: &'a HashMap<String, Palette>,
     palette_name: Option<&str>,
@@ -972,6 +1214,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn style_to_string_round_trips() {
+        for style_string in [
+            "",
+            "bold",
+            "bold dimmed italic underline blink inverted hidden strikethrough",
+            "fg:red bg:bright-blue",
+            "underline fg:120 bg:#050505",
+            "fg:black fg:prev_bg bg:prev_fg",
+            "bg:prev_bg",
+        ] {
+            let style = parse_style_string(style_string, None).unwrap();
+            assert_eq!(style.to_string(), style_string);
+            assert_eq!(parse_style_string(&style.to_string(), None), Some(style));
+        }
+    }
+
+    #[test]
+    fn style_to_string_normalizes_tokens() {
+        let style = parse_style_string("Underline 125 BOLD bg:none purple", None).unwrap();
+        assert_eq!(style.to_string(), "bold underline fg:purple");
+    }
+
     #[test]
     fn table_get_styles_ordered() {
         // Test a background style with inverted order (also test hex + ANSI)
@@ -1074,4 +1339,101 @@ mod tests {
             "if the platform doesn't have utils::home_dir(), it should return None"
         );
     }
+
+    fn load_layered(path: &Path) -> Option<LayeredConfig> {
+        StarshipConfig::load_layered(&Some(path.into()))
+    }
+
+    #[test]
+    fn config_includes_are_merged() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let base = dir.path().join("base.toml");
+        let machine = dir.path().join("machine.toml");
+        let config = dir.path().join("starship.toml");
+        utils::write_file(
+            &base,
+            "format = '$all'\nadd_newline = false\n[character]\nsuccess_symbol = 'b'\nerror_symbol = 'e'\n[git_branch]\nignore_branches = ['main']",
+        )?;
+        utils::write_file(&machine, "[character]\nsuccess_symbol = 'm'")?;
+        utils::write_file(
+            &config,
+            "include = ['base.toml', 'machine.toml']\n[git_branch]\nignore_branches = ['dev']",
+        )?;
+
+        let layered = load_layered(&config).unwrap();
+        let expected = toml::toml! {
+            format = "$all"
+            add_newline = false
+            [character]
+            success_symbol = "m"
+            error_symbol = "e"
+            [git_branch]
+            ignore_branches = ["dev"]
+        };
+        assert_eq!(layered.table, expected);
+        assert_eq!(
+            layered.files,
+            vec![config.clone(), base.clone(), machine.clone()]
+        );
+
+        let origins: Vec<(&str, &Path)> = layered
+            .origins
+            .iter()
+            .map(|(key, file)| (key.as_str(), file.as_path()))
+            .collect();
+        assert_eq!(
+            origins,
+            vec![
+                ("add_newline", base.as_path()),
+                ("character.error_symbol", &base),
+                ("character.success_symbol", &machine),
+                ("format", &base),
+                ("git_branch.ignore_branches", &config),
+            ]
+        );
+        dir.close()
+    }
+
+    #[test]
+    fn config_include_cycles_and_missing_files_are_skipped() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = dir.path().join("starship.toml");
+        let other = dir.path().join("other.toml");
+        utils::write_file(
+            &config,
+            "include = ['missing.toml', 'other.toml']\nformat = 'main'",
+        )?;
+        utils::write_file(
+            &other,
+            "include = 'starship.toml'\nformat = 'other'\nadd_newline = false",
+        )?;
+
+        let layered = load_layered(&config).unwrap();
+        let expected = toml::toml! {
+            format = "main"
+            add_newline = false
+        };
+        assert_eq!(layered.table, expected);
+        assert_eq!(
+            layered.files,
+            vec![
+                config.clone(),
+                dir.path().join("missing.toml"),
+                other,
+                config
+            ]
+        );
+        dir.close()
+    }
+
+    #[test]
+    fn config_invalid_include() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = dir.path().join("starship.toml");
+        utils::write_file(&config, "include = 42\nformat = 'main'")?;
+
+        let layered = load_layered(&config).unwrap();
+        assert_eq!(layered.table, toml::toml! { format = "main" });
+        dir.close()
+    }
 }
@@ -30,6 +30,10 @@ use std::collections::HashMap;
 ///
 /// When using [aws-sso-cli](https://github.com/synfinatic/aws-sso-cli) the profile
 /// is read from the `AWS_SSO_PROFILE` env var.
+///
+/// For profiles that sign in with IAM Identity Center (SSO), directly or through a
+/// `source_profile`, the expiration date is read from the access token the AWS CLI
+/// caches in `~/.aws/sso/cache` after `aws sso login`.
 pub struct AwsConfig<'a> {
     /// The format for the module.
     pub format: &'a str,
@@ -1,3 +1,4 @@
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -15,7 +16,12 @@ pub struct GitBranchConfig<'a> {
     pub truncation_symbol: &'a str,
     pub only_attached: bool,
     pub always_show_remote: bool,
+    pub gone: &'a str,
+    pub fork: &'a str,
+    pub upstream_remote: &'a str,
+    pub remote_styles: IndexMap<String, &'a str>,
     pub ignore_branches: Vec<&'a str>,
+    pub disabled_in_jj: bool,
     pub disabled: bool,
 }
 
@@ -29,7 +35,12 @@ impl<'a> Default for GitBranchConfig<'a> {
             truncation_symbol: "…",
             only_attached: false,
             always_show_remote: false,
+            gone: "[gone]",
+            fork: "⑂",
+            upstream_remote: "upstream",
+            remote_styles: IndexMap::new(),
             ignore_branches: vec![],
+            disabled_in_jj: false,
             disabled: false,
         }
     }
@@ -12,6 +12,7 @@ pub struct GitCommitConfig<'a> {
     pub format: &'a str,
     pub style: &'a str,
     pub only_detached: bool,
+    pub disabled_in_jj: bool,
     pub disabled: bool,
     pub tag_symbol: &'a str,
     pub tag_disabled: bool,
@@ -26,6 +27,7 @@ impl<'a> Default for GitCommitConfig<'a> {
             format: "[\\($hash$tag\\)]($style) ",
             style: "green bold",
             only_detached: true,
+            disabled_in_jj: false,
             disabled: false,
             tag_symbol: " 🏷  ",
             tag_disabled: true,
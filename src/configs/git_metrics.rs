@@ -12,8 +12,11 @@ pub struct GitMetricsConfig<'a> {
     pub deleted_style: &'a str,
     pub only_nonzero_diffs: bool,
     pub format: &'a str,
+    pub disabled_in_jj: bool,
     pub disabled: bool,
     pub ignore_submodules: bool,
+    pub fsmonitor: bool,
+    pub include_untracked: bool,
 }
 
 impl<'a> Default for GitMetricsConfig<'a> {
@@ -23,8 +26,11 @@ impl<'a> Default for GitMetricsConfig<'a> {
             deleted_style: "bold red",
             only_nonzero_diffs: true,
             format: "([+$added]($added_style) )([-$deleted]($deleted_style) )",
+            disabled_in_jj: false,
             disabled: true,
             ignore_submodules: false,
+            fsmonitor: false,
+            include_untracked: false,
         }
     }
 }
@@ -17,6 +17,7 @@ pub struct GitStateConfig<'a> {
     pub am_or_rebase: &'a str,
     pub style: &'a str,
     pub format: &'a str,
+    pub disabled_in_jj: bool,
     pub disabled: bool,
 }
 
@@ -32,6 +33,7 @@ impl<'a> Default for GitStateConfig<'a> {
             am_or_rebase: "AM/REBASE",
             style: "bold yellow",
             format: "\\([$state( $progress_current/$progress_total)]($style)\\) ",
+            disabled_in_jj: false,
             disabled: false,
         }
     }
@@ -1,3 +1,6 @@
+use crate::config::VecOr;
+
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -22,7 +25,14 @@ pub struct GitStatusConfig<'a> {
     pub staged: &'a str,
     pub untracked: &'a str,
     pub typechanged: &'a str,
+    pub status_partial: &'a str,
+    pub categories: IndexMap<String, VecOr<&'a str>>,
+    pub category_format: &'a str,
+    pub category_separator: &'a str,
+    pub ignore_paths: Vec<&'a str>,
     pub ignore_submodules: bool,
+    pub fsmonitor: bool,
+    pub disabled_in_jj: bool,
     pub disabled: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub windows_starship: Option<&'a str>,
@@ -45,7 +55,14 @@ impl<'a> Default for GitStatusConfig<'a> {
             staged: "+",
             untracked: "?",
             typechanged: "",
+            status_partial: "…",
+            categories: IndexMap::new(),
+            category_format: "$name:$count",
+            category_separator: " ",
+            ignore_paths: vec![],
             ignore_submodules: false,
+            fsmonitor: false,
+            disabled_in_jj: false,
             disabled: false,
             windows_starship: None,
         }
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Deserialize, Serialize)]
+#[cfg_attr(
+    feature = "config-schema",
+    derive(schemars::JsonSchema),
+    schemars(deny_unknown_fields)
+)]
+#[serde(default)]
+pub struct GitWorktreeConfig<'a> {
+    pub format: &'a str,
+    pub worktree_symbol: &'a str,
+    pub submodule_symbol: &'a str,
+    pub style: &'a str,
+    pub disabled: bool,
+}
+
+impl<'a> Default for GitWorktreeConfig<'a> {
+    fn default() -> Self {
+        GitWorktreeConfig {
+            format: "([$worktree_symbol$worktree]($style) )([$submodule_symbol$superproject/$submodule]($style) )",
+            worktree_symbol: "🌲 ",
+            submodule_symbol: "⊂ ",
+            style: "bold green",
+            disabled: false,
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Deserialize, Serialize)]
+#[cfg_attr(
+    feature = "config-schema",
+    derive(schemars::JsonSchema),
+    schemars(deny_unknown_fields)
+)]
+#[serde(default)]
+pub struct HgStatusConfig<'a> {
+    pub format: &'a str,
+    pub style: &'a str,
+    pub unresolved: &'a str,
+    pub missing: &'a str,
+    pub removed: &'a str,
+    pub modified: &'a str,
+    pub added: &'a str,
+    pub unknown: &'a str,
+    pub status_partial: &'a str,
+    pub disabled: bool,
+}
+
+impl<'a> Default for HgStatusConfig<'a> {
+    fn default() -> Self {
+        HgStatusConfig {
+            format: "([\\[$all_status\\]]($style) )",
+            style: "red bold",
+            unresolved: "=",
+            missing: "⊘",
+            removed: "✘",
+            modified: "!",
+            added: "+",
+            unknown: "?",
+            status_partial: "…",
+            disabled: true,
+        }
+    }
+}
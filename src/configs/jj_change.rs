@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Deserialize, Serialize)]
+#[cfg_attr(
+    feature = "config-schema",
+    derive(schemars::JsonSchema),
+    schemars(deny_unknown_fields)
+)]
+#[serde(default)]
+pub struct JjChangeConfig<'a> {
+    pub format: &'a str,
+    pub symbol: &'a str,
+    pub style: &'a str,
+    pub change_id_length: usize,
+    pub commit_id_length: usize,
+    pub truncation_length: i64,
+    pub truncation_symbol: &'a str,
+    pub ignore_working_copy: bool,
+    pub disabled: bool,
+}
+
+impl<'a> Default for JjChangeConfig<'a> {
+    fn default() -> Self {
+        JjChangeConfig {
+            format: "on [$symbol$change_id( $bookmarks)]($style) ",
+            symbol: "@ ",
+            style: "bold purple",
+            change_id_length: 8,
+            commit_id_length: 8,
+            truncation_length: i64::MAX,
+            truncation_symbol: "…",
+            ignore_working_copy: true,
+            disabled: true,
+        }
+    }
+}
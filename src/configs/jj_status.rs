@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Deserialize, Serialize)]
+#[cfg_attr(
+    feature = "config-schema",
+    derive(schemars::JsonSchema),
+    schemars(deny_unknown_fields)
+)]
+#[serde(default)]
+pub struct JjStatusConfig<'a> {
+    pub format: &'a str,
+    pub style: &'a str,
+    pub conflicted: &'a str,
+    pub divergent: &'a str,
+    pub empty: &'a str,
+    pub ignore_working_copy: bool,
+    pub disabled: bool,
+}
+
+impl<'a> Default for JjStatusConfig<'a> {
+    fn default() -> Self {
+        JjStatusConfig {
+            format: "([\\[$conflicted$divergent$empty\\]]($style) )",
+            style: "bold red",
+            conflicted: "=",
+            divergent: "⇕",
+            empty: "∅",
+            ignore_working_copy: true,
+            disabled: true,
+        }
+    }
+}
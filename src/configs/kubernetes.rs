@@ -48,8 +48,9 @@ impl<'a> Default for KubernetesConfig<'a> {
 )]
 #[serde(default)]
 pub struct KubernetesContextConfig<'a> {
-    pub context_pattern: &'a str,
+    pub context_pattern: Option<&'a str>,
     pub user_pattern: Option<&'a str>,
+    pub namespace_pattern: Option<&'a str>,
     pub symbol: Option<&'a str>,
     pub style: Option<&'a str>,
     pub context_alias: Option<&'a str>,
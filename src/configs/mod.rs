@@ -36,6 +36,7 @@ pub mod git_commit;
 pub mod git_metrics;
 pub mod git_state;
 pub mod git_status;
+pub mod git_worktree;
 pub mod gleam;
 pub mod go;
 pub mod gradle;
@@ -44,8 +45,11 @@ pub mod haskell;
 pub mod haxe;
 pub mod helm;
 pub mod hg_branch;
+pub mod hg_status;
 pub mod hostname;
 pub mod java;
+pub mod jj_change;
+pub mod jj_status;
 pub mod jobs;
 pub mod julia;
 pub mod kotlin;
@@ -182,6 +186,8 @@ pub struct FullConfig<'a> {
     #[serde(borrow)]
     git_status: git_status::GitStatusConfig<'a>,
     #[serde(borrow)]
+    git_worktree: git_worktree::GitWorktreeConfig<'a>,
+    #[serde(borrow)]
     gleam: gleam::GleamConfig<'a>,
     #[serde(borrow)]
     golang: go::GoConfig<'a>,
@@ -198,10 +204,16 @@ pub struct FullConfig<'a> {
     #[serde(borrow)]
     hg_branch: hg_branch::HgBranchConfig<'a>,
     #[serde(borrow)]
+    hg_status: hg_status::HgStatusConfig<'a>,
+    #[serde(borrow)]
     hostname: hostname::HostnameConfig<'a>,
     #[serde(borrow)]
     java: java::JavaConfig<'a>,
     #[serde(borrow)]
+    jj_change: jj_change::JjChangeConfig<'a>,
+    #[serde(borrow)]
+    jj_status: jj_status::JjStatusConfig<'a>,
+    #[serde(borrow)]
     jobs: jobs::JobsConfig<'a>,
     #[serde(borrow)]
     julia: julia::JuliaConfig<'a>,
@@ -14,6 +14,8 @@ pub struct NodejsConfig<'a> {
     pub style: &'a str,
     pub disabled: bool,
     pub not_capable_style: &'a str,
+    pub resolve_version: bool,
+    pub mismatch_symbol: &'a str,
     pub detect_extensions: Vec<&'a str>,
     pub detect_files: Vec<&'a str>,
     pub detect_folders: Vec<&'a str>,
@@ -28,6 +30,8 @@ impl<'a> Default for NodejsConfig<'a> {
             style: "bold green",
             disabled: false,
             not_capable_style: "bold red",
+            resolve_version: false,
+            mismatch_symbol: "≠",
             detect_extensions: vec!["js", "mjs", "cjs", "ts", "mts", "cts"],
             detect_files: vec!["package.json", ".node-version", ".nvmrc"],
             detect_folders: vec!["node_modules"],
@@ -12,6 +12,7 @@ pub struct PackageConfig<'a> {
     pub symbol: &'a str,
     pub style: &'a str,
     pub display_private: bool,
+    pub search_upwards: bool,
     pub disabled: bool,
     pub version_format: &'a str,
 }
@@ -19,10 +20,11 @@ pub struct PackageConfig<'a> {
 impl<'a> Default for PackageConfig<'a> {
     fn default() -> Self {
         PackageConfig {
-            format: "is [$symbol$version]($style) ",
+            format: "(is [$symbol$version]($style) )",
             symbol: "📦 ",
             style: "208 bold",
             display_private: false,
+            search_upwards: false,
             disabled: false,
             version_format: "v${raw}",
         }
@@ -12,17 +12,60 @@ use std::collections::HashMap;
 pub struct StarshipRootConfig {
     #[serde(rename = "$schema")]
     schema: String,
+    /// Merged into the config when it is loaded, see `LayeredConfig`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    include: Vec<String>,
     pub format: String,
     pub right_format: String,
     pub continuation_prompt: String,
     pub scan_timeout: u64,
     pub command_timeout: u64,
+    pub cache_versions: bool,
+    pub async_placeholder: String,
     pub add_newline: bool,
     pub follow_symlinks: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub palette: Option<String>,
     pub palettes: HashMap<String, Palette>,
     pub profiles: IndexMap<String, String>,
+    pub powerline: PowerlineConfig,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[cfg_attr(
+    feature = "config-schema",
+    derive(schemars::JsonSchema),
+    schemars(deny_unknown_fields)
+)]
+#[serde(default)]
+pub struct PowerlineConfig {
+    pub separator: String,
+    pub start_symbol: String,
+    pub end_symbol: String,
+    pub groups: Vec<PowerlineGroup>,
+}
+
+impl Default for PowerlineConfig {
+    fn default() -> Self {
+        Self {
+            separator: "\u{e0b0}".to_string(),
+            start_symbol: String::new(),
+            end_symbol: "\u{e0b0}".to_string(),
+            groups: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[cfg_attr(
+    feature = "config-schema",
+    derive(schemars::JsonSchema),
+    schemars(deny_unknown_fields)
+)]
+#[serde(default)]
+pub struct PowerlineGroup {
+    pub modules: Vec<String>,
+    pub style: String,
 }
 
 pub type Palette = HashMap<String, String>;
@@ -42,12 +85,16 @@ pub const PROMPT_ORDER: &[&str] = &[
     "vcsh",
     "fossil_branch",
     "fossil_metrics",
+    "git_worktree",
     "git_branch",
     "git_commit",
     "git_state",
     "git_metrics",
     "git_status",
     "hg_branch",
+    "hg_status",
+    "jj_change",
+    "jj_status",
     "pijul_channel",
     "docker_context",
     "package",
@@ -134,16 +181,20 @@ impl Default for StarshipRootConfig {
     fn default() -> Self {
         Self {
             schema: "https://starship.rs/config-schema.json".to_string(),
+            include: Vec::new(),
             format: "$all".to_string(),
             right_format: String::new(),
             continuation_prompt: "[∙](bright-black) ".to_string(),
             profiles: Default::default(),
             scan_timeout: 30,
             command_timeout: 500,
+            cache_versions: false,
+            async_placeholder: String::new(),
             add_newline: true,
             follow_symlinks: true,
             palette: None,
             palettes: HashMap::default(),
+            powerline: PowerlineConfig::default(),
         }
     }
 }
@@ -1,4 +1,6 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Write as _;
+use std::path::PathBuf;
 use std::process;
 use std::process::Stdio;
 use std::str::FromStr;
@@ -75,6 +77,7 @@ fn handle_update_configuration(
 }
 
 pub fn print_configuration(context: &Context, use_default: bool, paths: &[String]) -> String {
+    let mut origins = BTreeMap::new();
     let config = if use_default {
         // Get default config
         let default_config = crate::configs::FullConfig::default();
@@ -82,7 +85,14 @@ pub fn print_configuration(context: &Context, use_default: bool, paths: &[String
         toml::value::Value::try_from(default_config).unwrap()
     } else {
         // Get config as toml::Value
-        let user_config = get_configuration(context);
+        let layered = StarshipConfig::load_layered(&context.get_config_path_os());
+        let user_config = match layered {
+            Some(layered) => {
+                origins = layered.origins;
+                layered.table
+            }
+            None => toml::Table::new(),
+        };
         // Convert into FullConfig and fill in default values
         let user_config = crate::configs::FullConfig::load(&user_config);
         // Convert back to Value because toml can't serialize FullConfig directly
@@ -126,12 +136,38 @@ pub fn print_configuration(context: &Context, use_default: bool, paths: &[String
         extract_toml_paths(config, paths)
     };
 
-    let string_config = toml::to_string_pretty(&print_config).unwrap();
+    let mut string_config = toml::to_string_pretty(&print_config).unwrap();
+
+    // Only point out where values come from if the config is spread over several files
+    let files: BTreeSet<_> = origins.values().collect();
+    if files.len() > 1 {
+        println!("# Values set in the config files are followed by the file they were set in\n");
+        string_config = annotate_origins(&string_config, &origins);
+    }
 
     println!("{string_config}");
     string_config
 }
 
+/// Adds a comment with the file it was set in after each value in `config`
+fn annotate_origins(config: &str, origins: &BTreeMap<String, PathBuf>) -> String {
+    let Ok(mut doc) = config.parse::<DocumentMut>() else {
+        return config.to_owned();
+    };
+
+    for (key, file) in origins {
+        let item = key.split('.').try_fold(doc.as_item_mut(), |item, segment| {
+            item.as_table_like_mut()?.get_mut(segment)
+        });
+        if let Some(value) = item.and_then(toml_edit::Item::as_value_mut) {
+            value
+                .decor_mut()
+                .set_suffix(format!(" # {}", file.display()));
+        }
+    }
+    doc.to_string()
+}
+
 fn extract_toml_paths(mut config: toml::Value, paths: &[String]) -> toml::Value {
     // Extract all the requested sections into a new configuration.
     let mut subset = toml::value::Table::new();
@@ -598,6 +634,23 @@ mod tests {
     const PRINT_CONFIG_HOME: &str = "[custom.home]";
     const PRINT_CONFIG_ENV: &str = "[custom.STARSHIP_CONFIG]";
 
+    #[test]
+    fn annotates_origins() {
+        let config =
+            "format = \"$all\"\n\n[character]\nerror_symbol = \"e\"\nsuccess_symbol = \"m\"\n";
+        let origins = BTreeMap::from([
+            ("format".to_string(), PathBuf::from("base.toml")),
+            (
+                "character.success_symbol".to_string(),
+                PathBuf::from("machine.toml"),
+            ),
+            ("missing.key".to_string(), PathBuf::from("machine.toml")),
+        ]);
+
+        let expected = "format = \"$all\" # base.toml\n\n[character]\nerror_symbol = \"e\"\nsuccess_symbol = \"m\" # machine.toml\n";
+        assert_eq!(annotate_origins(config, &origins), expected);
+    }
+
     #[test]
     fn print_configuration_scenarios() -> io::Result<()> {
         run_print_configuration_test(
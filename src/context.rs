@@ -1,10 +1,13 @@
-use crate::config::{ModuleConfig, StarshipConfig};
+use crate::cache;
+use crate::config::{self, ModuleConfig, StarshipConfig};
 use crate::configs::StarshipRootConfig;
 use crate::context_env::Env;
 use crate::module::Module;
+use crate::project_config;
 use crate::utils::{create_command, exec_timeout, read_file, CommandOutput, PathExt};
 
 use crate::modules;
+use crate::modules::utils::jj;
 use crate::utils;
 use clap::Parser;
 use gix::{
@@ -23,6 +26,7 @@ use std::fs;
 use std::marker::PhantomData;
 use std::num::ParseIntError;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::str::FromStr;
 use std::string::String;
 use std::time::{Duration, Instant};
@@ -52,6 +56,10 @@ pub struct Context<'a> {
     /// Private field to store Git information for modules who need it
     repo: OnceCell<Repo>,
 
+    /// The working-copy commit of a Jujutsu repository, read without and with
+    /// `--ignore-working-copy`, for the `jj_*` modules that need it
+    jj_working_copy: [OnceCell<Option<jj::WorkingCopy>>; 2],
+
     /// The shell the user is assumed to be running
     pub shell: Shell,
 
@@ -87,26 +95,8 @@ impl<'a> Context<'a> {
     /// for it. "logical-path" is used when a shell allows the "current working directory"
     /// to be something other than a file system path (like powershell provider specific paths).
     pub fn new(arguments: Properties, target: Target) -> Self {
-        let shell = Context::get_shell();
-
-        // Retrieve the "current directory".
-        // If the path argument is not set fall back to the OS current directory.
-        let path = arguments
-            .path
-            .clone()
-            .or_else(|| env::current_dir().ok())
-            .or_else(|| env::var("PWD").map(PathBuf::from).ok())
-            .or_else(|| arguments.logical_path.clone())
-            .unwrap_or_default();
-
-        // Retrieve the "logical directory".
-        // If the path argument is not set fall back to the PWD env variable set by many shells
-        // or to the other path.
-        let logical_path = arguments
-            .logical_path
-            .clone()
-            .or_else(|| env::var("PWD").map(PathBuf::from).ok())
-            .unwrap_or_else(|| path.clone());
+        let shell = Context::get_shell(&Env::default());
+        let (path, logical_path) = Context::get_paths(&arguments, &Env::default());
 
         Context::new_with_shell_and_path(
             arguments,
@@ -118,9 +108,32 @@ impl<'a> Context<'a> {
         )
     }
 
+    /// Identify the current working directory and create an instance of Context
+    /// for it, using an already loaded configuration and the given environment instead of
+    /// reading them from the process.
+    pub fn new_with_config(
+        arguments: Properties,
+        target: Target,
+        config: StarshipConfig,
+        env: Env<'a>,
+    ) -> Self {
+        let shell = Context::get_shell(&env);
+        let (path, logical_path) = Context::get_paths(&arguments, &env);
+
+        Context::new_with_shell_path_and_config(
+            arguments,
+            shell,
+            target,
+            path,
+            logical_path,
+            env,
+            config,
+        )
+    }
+
     /// Create a new instance of Context for the provided directory
     pub fn new_with_shell_and_path(
-        mut properties: Properties,
+        properties: Properties,
         shell: Shell,
         target: Target,
         path: PathBuf,
@@ -129,6 +142,26 @@ impl<'a> Context<'a> {
     ) -> Self {
         let config = StarshipConfig::initialize(&get_config_path_os(&env));
 
+        Context::new_with_shell_path_and_config(
+            properties,
+            shell,
+            target,
+            path,
+            logical_path,
+            env,
+            config,
+        )
+    }
+
+    fn new_with_shell_path_and_config(
+        mut properties: Properties,
+        shell: Shell,
+        target: Target,
+        path: PathBuf,
+        logical_path: PathBuf,
+        env: Env<'a>,
+        config: StarshipConfig,
+    ) -> Self {
         // If the vector is zero-length, we should pretend that we didn't get a
         // pipestatus at all (since this is the input `--pipestatus=""`)
         if properties
@@ -161,13 +194,22 @@ impl<'a> Context<'a> {
 
         let width = properties.terminal_width;
 
-        Context {
+        // tmux status lines and JSON output are not read by the shell, so the text must not be
+        // escaped for it
+        let shell = match target {
+            Target::Tmux(_) => Shell::Unknown,
+            _ if properties.output == OutputFormat::Json => Shell::Unknown,
+            _ => shell,
+        };
+
+        let mut context = Context {
             config,
             properties,
             current_dir,
             logical_dir,
             dir_contents: OnceCell::new(),
             repo: OnceCell::new(),
+            jj_working_copy: Default::default(),
             shell,
             target,
             width,
@@ -180,7 +222,22 @@ impl<'a> Context<'a> {
             battery_info_provider: &crate::modules::BatteryInfoProviderImpl,
             root_config,
             _marker: PhantomData,
-        }
+        };
+        context.load_project_config();
+        context
+    }
+
+    /// Overlays the user config with the trusted project config of the current directory
+    fn load_project_config(&mut self) {
+        let Some(project_config) = project_config::load(self) else {
+            return;
+        };
+        let mut config = self.config.config.take().unwrap_or_default();
+        config::merge_tables(&mut config, project_config);
+        self.root_config = StarshipRootConfig::load(&config);
+        self.config = StarshipConfig {
+            config: Some(config),
+        };
     }
 
     /// Sets the context config, overwriting the existing config
@@ -361,6 +418,29 @@ impl<'a> Context<'a> {
             })
     }
 
+    /// Reuses the directory contents and git repository found for an earlier prompt
+    pub fn with_scans(self, dir_contents: Option<DirContents>, repo: Option<Repo>) -> Self {
+        if let Some(dir_contents) = dir_contents {
+            let _ = self.dir_contents.set(dir_contents);
+        }
+        if let Some(repo) = repo {
+            let _ = self.repo.set(repo);
+        }
+        self
+    }
+
+    /// Takes the directory contents and git repository found while rendering the prompt
+    pub fn take_scans(&mut self) -> (Option<DirContents>, Option<Repo>) {
+        (self.dir_contents.take(), self.repo.take())
+    }
+
+    /// Reads the working-copy commit of the Jujutsu repository with `jj log`, once per prompt
+    pub fn get_jj_working_copy(&self, ignore_working_copy: bool) -> Option<&jj::WorkingCopy> {
+        self.jj_working_copy[usize::from(ignore_working_copy)]
+            .get_or_init(|| jj::get_working_copy(self, ignore_working_copy))
+            .as_ref()
+    }
+
     pub fn dir_contents(&self) -> Result<&DirContents, std::io::Error> {
         self.dir_contents.get_or_try_init(|| {
             let timeout = self.root_config.scan_timeout;
@@ -372,8 +452,33 @@ impl<'a> Context<'a> {
         })
     }
 
-    fn get_shell() -> Shell {
-        let shell = env::var("STARSHIP_SHELL").unwrap_or_default();
+    /// Retrieve the current and logical directories from the arguments,
+    /// falling back to the environment.
+    fn get_paths(arguments: &Properties, env: &Env) -> (PathBuf, PathBuf) {
+        // Retrieve the "current directory".
+        // If the path argument is not set fall back to the OS current directory.
+        let path = arguments
+            .path
+            .clone()
+            .or_else(|| env::current_dir().ok())
+            .or_else(|| env.get_env_os("PWD").map(PathBuf::from))
+            .or_else(|| arguments.logical_path.clone())
+            .unwrap_or_default();
+
+        // Retrieve the "logical directory".
+        // If the path argument is not set fall back to the PWD env variable set by many shells
+        // or to the other path.
+        let logical_path = arguments
+            .logical_path
+            .clone()
+            .or_else(|| env.get_env_os("PWD").map(PathBuf::from))
+            .unwrap_or_else(|| path.clone());
+
+        (path, logical_path)
+    }
+
+    fn get_shell(env: &Env) -> Shell {
+        let shell = env.get_env("STARSHIP_SHELL").unwrap_or_default();
         match shell.as_str() {
             "bash" => Shell::Bash,
             "fish" => Shell::Fish,
@@ -398,6 +503,18 @@ impl<'a> Context<'a> {
             .and_then(|cd| cd.parse::<u128>().ok())
     }
 
+    /// Creates a command that runs with the environment the prompt is rendered for, which is
+    /// the environment of the client when rendered by the daemon
+    pub fn create_command<T: AsRef<OsStr>>(&self, binary_name: T) -> std::io::Result<Command> {
+        let Some(vars) = self.env.vars() else {
+            return create_command(binary_name);
+        };
+        let path = vars.get(OsStr::new("PATH")).map(OsString::as_os_str);
+        let mut command = utils::create_command_in(binary_name, path, &self.current_dir)?;
+        command.env_clear().envs(vars);
+        Ok(command)
+    }
+
     /// Execute a command and return the output on stdout and stderr if successful
     #[inline]
     pub fn exec_cmd<T: AsRef<OsStr> + Debug, U: AsRef<OsStr> + Debug>(
@@ -422,7 +539,7 @@ impl<'a> Context<'a> {
                 return output;
             }
         }
-        let mut cmd = create_command(cmd).ok()?;
+        let mut cmd = self.create_command(cmd).ok()?;
         cmd.args(args).current_dir(&self.current_dir);
         exec_timeout(
             &mut cmd,
@@ -430,6 +547,30 @@ impl<'a> Context<'a> {
         )
     }
 
+    /// Execute a command that prints the version of a tool, see `exec_cmd`.
+    /// With `cache_versions` enabled, the output is reused across prompts until the binary changes.
+    pub fn exec_version_cmd<T: AsRef<OsStr> + Debug, U: AsRef<OsStr> + Debug>(
+        &self,
+        cmd: T,
+        args: &[U],
+    ) -> Option<CommandOutput> {
+        if !self.root_config.cache_versions {
+            return self.exec_cmd(cmd, args);
+        }
+        let binary = match self.env.vars() {
+            Some(vars) => which::which_in(
+                cmd.as_ref(),
+                vars.get(OsStr::new("PATH")),
+                &self.current_dir,
+            ),
+            None => which::which(cmd.as_ref()),
+        };
+        let Ok(binary) = binary else {
+            return self.exec_cmd(cmd, args);
+        };
+        cache::version_output(self, &binary, args, || self.exec_cmd(&binary, args))
+    }
+
     /// Attempt to execute several commands with `exec_cmd`, return the results of the first that works
     pub fn exec_cmds_return_first(&self, commands: Vec<Vec<&str>>) -> Option<CommandOutput> {
         commands
@@ -461,7 +602,7 @@ impl Default for Context<'_> {
 }
 
 fn home_dir(env: &Env) -> Option<PathBuf> {
-    if cfg!(test) {
+    if cfg!(test) || env.vars().is_some() {
         if let Some(home) = env.get_env("HOME") {
             return Some(PathBuf::from(home));
         }
@@ -469,7 +610,7 @@ fn home_dir(env: &Env) -> Option<PathBuf> {
     utils::home_dir()
 }
 
-fn get_config_path_os(env: &Env) -> Option<OsString> {
+pub(crate) fn get_config_path_os(env: &Env) -> Option<OsString> {
     if let Some(config_path) = env.get_env_os("STARSHIP_CONFIG") {
         return Some(config_path);
     }
@@ -668,7 +809,7 @@ impl Repo {
         context: &Context,
         git_args: &[T],
     ) -> Option<CommandOutput> {
-        let mut command = create_command("git").ok()?;
+        let mut command = context.create_command("git").ok()?;
 
         // A value of `true` should not execute external commands.
         let fsm_config_value = if self.fs_monitor_value_is_true {
@@ -705,6 +846,46 @@ impl Repo {
 pub struct Remote {
     pub branch: Option<String>,
     pub name: Option<String>,
+    pub url: Option<String>,
+}
+
+impl Remote {
+    /// Parses the repository the remote points to from its fetch URL
+    pub fn repository(&self) -> Option<RemoteRepository> {
+        parse_remote_url(self.url.as_deref()?)
+    }
+}
+
+/// A repository on a hosting service, e.g. `starship/starship` on `github.com`
+#[derive(Debug, PartialEq, Eq)]
+pub struct RemoteRepository {
+    pub host: String,
+    /// The user or organization, or the group including its subgroups on GitLab
+    pub owner: String,
+    pub name: String,
+}
+
+/// Parses the host, owner and name of a repository from an SSH, scp-like, HTTP(S) or git URL
+/// like `git@github.com:starship/starship.git`. URLs without an owner, such as local paths,
+/// are ignored.
+fn parse_remote_url(url: &str) -> Option<RemoteRepository> {
+    use gix::bstr::ByteSlice;
+
+    let url = gix::url::parse(url.into()).ok()?;
+    let host = url.host()?.to_string();
+    let path = url.path.to_str().ok()?.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let (owner, name) = path.rsplit_once('/')?;
+    // Home directory paths like `ssh://example.com/~user/repo.git`
+    let owner = owner.trim_start_matches('~');
+    if owner.is_empty() || name.is_empty() {
+        return None;
+    }
+    Some(RemoteRepository {
+        host,
+        owner: owner.to_string(),
+        name: name.to_string(),
+    })
 }
 
 // A struct of Criteria which will be used to verify current PathBuf is
@@ -772,6 +953,13 @@ impl<'a> ScanAncestors<'a> {
         self
     }
 
+    /// Continues the scan from another path, e.g. the parent of a directory that was found
+    #[must_use]
+    pub const fn set_path(mut self, path: &'a Path) -> Self {
+        self.path = path;
+        self
+    }
+
     /// Scans upwards starting from the initial path until a directory containing one of the given
     /// files or folders is found.
     ///
@@ -809,8 +997,16 @@ fn get_remote_repository_info(
     let name = repository
         .branch_remote_name(branch_name.shorten(), gix::remote::Direction::Fetch)
         .map(|n| n.as_bstr().to_string());
+    let url = name
+        .as_deref()
+        .and_then(|name| repository.find_remote(name).ok())
+        .and_then(|remote| {
+            remote
+                .url(gix::remote::Direction::Fetch)
+                .map(|url| url.to_bstring().to_string())
+        });
 
-    Some(Remote { branch, name })
+    Some(Remote { branch, name, url })
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -836,6 +1032,40 @@ pub enum Target {
     Right,
     Continuation,
     Profile(String),
+    /// A tmux status line or window title, rendering the main prompt or the given profile
+    Tmux(Option<String>),
+}
+
+/// Arguments of the `starship prompt` command
+#[derive(Parser, Debug)]
+pub struct PromptArgs {
+    /// Print the right prompt (instead of the standard left prompt)
+    #[clap(long)]
+    pub right: bool,
+    /// Print the prompt with the specified profile name (instead of the standard left prompt)
+    #[clap(long, conflicts_with = "right")]
+    pub profile: Option<String>,
+    /// Print the continuation prompt (instead of the standard left prompt)
+    #[clap(long, conflicts_with = "right", conflicts_with = "profile")]
+    pub continuation: bool,
+    /// Print the prompt, or the profile given with `--profile`, with tmux style markup
+    #[clap(long, conflicts_with = "right", conflicts_with = "continuation")]
+    pub tmux: bool,
+    #[clap(flatten)]
+    pub properties: Properties,
+}
+
+impl PromptArgs {
+    /// Which prompt the arguments ask for
+    pub fn target(&self) -> Target {
+        match (self.right, &self.profile, self.continuation, self.tmux) {
+            (_, profile_name, _, true) => Target::Tmux(profile_name.clone()),
+            (true, _, _, _) => Target::Right,
+            (_, Some(profile_name), _, _) => Target::Profile(profile_name.clone()),
+            (_, _, true, _) => Target::Continuation,
+            (_, _, _, _) => Target::Main,
+        }
+    }
 }
 
 /// Properties as passed on from the shell as arguments
@@ -852,7 +1082,7 @@ pub struct Properties {
     terminal_width: usize,
     /// The path that the prompt should render for.
     #[clap(short, long)]
-    path: Option<PathBuf>,
+    pub(crate) path: Option<PathBuf>,
     /// The logical path that the prompt should render for.
     /// This path should be a virtual/logical representation of the PATH argument.
     #[clap(short = 'P', long)]
@@ -866,6 +1096,22 @@ pub struct Properties {
     /// The number of currently running jobs
     #[clap(short, long, default_value_t, value_parser=parse_jobs)]
     pub jobs: i64,
+    /// The format to print the output in
+    #[clap(long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+    /// The process ID of the shell, which is signaled to redraw the prompt once asynchronous modules have been refreshed
+    #[clap(long)]
+    pub shell_pid: Option<i32>,
+}
+
+/// Output formats of the commands that render modules
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Text with ANSI escape sequences for styles
+    #[default]
+    Text,
+    /// JSON describing the modules, their segments and styles
+    Json,
 }
 
 impl Default for Properties {
@@ -879,6 +1125,8 @@ impl Default for Properties {
             cmd_duration: None,
             keymap: "viins".to_string(),
             jobs: 0,
+            output: OutputFormat::default(),
+            shell_pid: None,
         }
     }
 }
@@ -922,6 +1170,46 @@ mod tests {
         Ok(dir)
     }
 
+    #[test]
+    fn parses_remote_urls() {
+        let github = |owner: &str, name: &str| RemoteRepository {
+            host: String::from("github.com"),
+            owner: owner.to_string(),
+            name: name.to_string(),
+        };
+        for url in [
+            "git@github.com:starship/starship.git",
+            "github.com:starship/starship",
+            "ssh://git@github.com/starship/starship.git",
+            "ssh://git@github.com:22/starship/starship",
+            "https://github.com/starship/starship.git",
+            "https://user@github.com/starship/starship/",
+            "git://github.com/starship/starship.git",
+        ] {
+            assert_eq!(
+                parse_remote_url(url),
+                Some(github("starship", "starship")),
+                "{url}"
+            );
+        }
+
+        assert_eq!(
+            parse_remote_url("https://gitlab.com/group/subgroup/project.git"),
+            Some(RemoteRepository {
+                host: String::from("gitlab.com"),
+                owner: String::from("group/subgroup"),
+                name: String::from("project"),
+            })
+        );
+        assert_eq!(
+            parse_remote_url("ssh://example.com/~user/repo.git").map(|repo| repo.owner),
+            Some(String::from("user"))
+        );
+        assert_eq!(parse_remote_url("/srv/git/repo.git"), None);
+        assert_eq!(parse_remote_url("file:///srv/git/repo.git"), None);
+        assert_eq!(parse_remote_url("https://example.com/repo.git"), None);
+    }
+
     #[test]
     fn test_scan_dir_no_symlinks() -> Result<(), Box<dyn std::error::Error>> {
         #[cfg(not(target_os = "windows"))]
@@ -1163,6 +1451,38 @@ mod tests {
         assert_ne!(context.config.config, mod_context.config.config);
     }
 
+    #[test]
+    fn jj_working_copy_is_read_once() {
+        let command = utils::display_command("jj", &jj::log_args(true));
+        let mut context = Context::new_with_shell_and_path(
+            Default::default(),
+            Shell::Unknown,
+            Target::Main,
+            PathBuf::new(),
+            PathBuf::new(),
+            Default::default(),
+        );
+        context.cmd.insert(
+            &command,
+            Some(CommandOutput {
+                stdout: String::from("kxqzwmpl\nk\n3f7d1c0a\n3f\n\n\n\n"),
+                stderr: String::default(),
+            }),
+        );
+
+        let first = context
+            .get_jj_working_copy(true)
+            .map(|commit| commit.change_id(0));
+        assert_eq!(first, Some("k"));
+
+        // Another module asking again reuses the first result instead of running `jj` again
+        context.cmd.clear();
+        let second = context
+            .get_jj_working_copy(true)
+            .map(|commit| commit.change_id(0));
+        assert_eq!(second, Some("k"));
+    }
+
     #[cfg(windows)]
     #[test]
     fn strip_extended_path_prefix() {
@@ -1,8 +1,7 @@
-#[cfg(test)]
 use std::collections::HashMap;
 #[cfg(not(test))]
 use std::env;
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
 
 #[derive(Default)]
 pub struct Env<'a> {
@@ -10,14 +9,36 @@ pub struct Env<'a> {
     #[cfg(test)]
     pub env: HashMap<&'a str, String>,
 
+    /// The environment of a daemon client, read instead of the environment of the process
+    vars: Option<HashMap<OsString, OsString>>,
+
     #[cfg(not(test))]
     _marker: std::marker::PhantomData<&'a ()>,
 }
 
 impl<'a> Env<'a> {
+    /// An environment made of the given variables instead of the environment of the process
+    pub fn from_vars(vars: impl IntoIterator<Item = (OsString, OsString)>) -> Self {
+        Self {
+            vars: Some(vars.into_iter().collect()),
+            ..Default::default()
+        }
+    }
+
+    /// The variables given to `from_vars`, if any
+    pub fn vars(&self) -> Option<&HashMap<OsString, OsString>> {
+        self.vars.as_ref()
+    }
+
     // Retrieves a environment variable from the os or from a table if in testing mode
     #[cfg(test)]
     pub fn get_env<K: AsRef<str>>(&self, key: K) -> Option<String> {
+        if let Some(vars) = &self.vars {
+            return vars
+                .get(OsStr::new(key.as_ref()))?
+                .to_str()
+                .map(String::from);
+        }
         self.env
             .get(key.as_ref())
             .map(std::string::ToString::to_string)
@@ -26,18 +47,30 @@ impl<'a> Env<'a> {
     #[cfg(not(test))]
     #[inline]
     pub fn get_env<K: AsRef<str>>(&self, key: K) -> Option<String> {
+        if let Some(vars) = &self.vars {
+            return vars
+                .get(OsStr::new(key.as_ref()))?
+                .to_str()
+                .map(String::from);
+        }
         env::var(key.as_ref()).ok()
     }
 
     // Retrieves a environment variable from the os or from a table if in testing mode (os version)
     #[cfg(test)]
     pub fn get_env_os<K: AsRef<str>>(&self, key: K) -> Option<OsString> {
+        if let Some(vars) = &self.vars {
+            return vars.get(OsStr::new(key.as_ref())).cloned();
+        }
         self.env.get(key.as_ref()).map(OsString::from)
     }
 
     #[cfg(not(test))]
     #[inline]
     pub fn get_env_os<K: AsRef<str>>(&self, key: K) -> Option<OsString> {
+        if let Some(vars) = &self.vars {
+            return vars.get(OsStr::new(key.as_ref())).cloned();
+        }
         env::var_os(key.as_ref())
     }
 
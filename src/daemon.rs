@@ -0,0 +1,607 @@
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::StarshipConfig;
+use crate::context::{get_config_path_os, Context, DirContents, PromptArgs, Repo};
+use crate::context_env::Env;
+use crate::logger;
+use crate::print;
+
+/// How long a client waits for the daemon to render a prompt before falling back
+/// to rendering it in-process.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A prompt request sent from `starship prompt` to the daemon.
+#[derive(Serialize, Deserialize, Debug)]
+struct Request {
+    /// The arguments passed to `starship prompt`
+    args: Vec<OsString>,
+    /// The working directory of the client
+    cwd: OsString,
+    /// The configuration file the client would have read
+    config_path: Option<OsString>,
+    /// The environment of the client, `None` keeps the environment of the daemon
+    env: Option<Vec<(OsString, OsString)>>,
+}
+
+/// Returns the path of the socket the daemon of the current session listens on.
+pub fn socket_path() -> Option<PathBuf> {
+    let session_key = env::var("STARSHIP_SESSION_KEY").ok()?;
+    if session_key.is_empty() {
+        return None;
+    }
+    Some(logger::get_log_dir().join(format!("daemon_{session_key}.sock")))
+}
+
+/// Asks the daemon of the current session to render the prompt.
+/// Returns `None` if there is no daemon or it failed to answer,
+/// in which case the prompt should be rendered in-process.
+pub fn request_prompt() -> Option<String> {
+    let socket = socket_path()?;
+    // Repositories are discovered and configured with the `GIT_*` variables of the process
+    if let Some((key, _)) =
+        env::vars_os().find(|(key, _)| key.as_encoded_bytes().starts_with(b"GIT_"))
+    {
+        log::debug!("Not using the daemon because {key:?} is set");
+        return None;
+    }
+    // Only collect the request once a daemon is known to be listening
+    let stream = match UnixStream::connect(&socket) {
+        Ok(stream) => stream,
+        Err(e) => {
+            log::debug!("No daemon listening at {socket:?}: {e}");
+            return None;
+        }
+    };
+    let request = Request {
+        args: env::args_os().skip(2).collect(),
+        cwd: env::current_dir().ok()?.into(),
+        config_path: get_config_path_os(&Env::default()),
+        env: Some(env::vars_os().collect()),
+    };
+    match send_request(stream, &request) {
+        Ok(prompt) => Some(prompt),
+        Err(e) => {
+            log::debug!("Unable to render prompt with daemon at {socket:?}: {e}");
+            None
+        }
+    }
+}
+
+fn send_request(mut stream: UnixStream, request: &Request) -> io::Result<String> {
+    stream.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+    stream.set_write_timeout(Some(RESPONSE_TIMEOUT))?;
+
+    let mut payload = serde_json::to_vec(request)?;
+    payload.push(b'\n');
+    stream.write_all(&payload)?;
+
+    let mut prompt = String::new();
+    stream.read_to_string(&mut prompt)?;
+    if prompt.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "daemon returned an empty response",
+        ));
+    }
+    Ok(prompt)
+}
+
+/// Caches the parsed configuration until one of the configuration files changes.
+#[derive(Default)]
+struct ConfigCache {
+    path: Option<OsString>,
+    /// The modification time and size of every file the configuration was read from,
+    /// missing files are checked again on every request
+    stamps: Vec<(PathBuf, Option<(SystemTime, u64)>)>,
+    config: Option<toml::Table>,
+}
+
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+impl ConfigCache {
+    fn get(&mut self, path: Option<OsString>) -> StarshipConfig {
+        let changed = self.path != path
+            || self.stamps.is_empty()
+            || self
+                .stamps
+                .iter()
+                .any(|(file, stamp)| stamp.is_none() || file_stamp(file) != *stamp);
+
+        if changed {
+            log::debug!("Loading configuration from {path:?}");
+            let layered = StarshipConfig::load_layered(&path);
+            let files = match &layered {
+                Some(layered) => layered.files.clone(),
+                None => path.iter().map(PathBuf::from).collect(),
+            };
+            self.stamps = files
+                .into_iter()
+                .map(|file| {
+                    let stamp = file_stamp(&file);
+                    (file, stamp)
+                })
+                .collect();
+            self.config = layered.map(|layered| layered.table);
+            self.path = path;
+        }
+
+        StarshipConfig {
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// How many directories the scan cache keeps the contents and git repository of.
+const MAX_CACHED_SCANS: usize = 64;
+
+/// The directory a prompt is rendered in, with the settings that change what is found there
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct ScanKey {
+    dir: PathBuf,
+    follow_symlinks: bool,
+    git_dir: Option<OsString>,
+    git_work_tree: Option<OsString>,
+}
+
+impl ScanKey {
+    fn new(context: &Context) -> Self {
+        ScanKey {
+            dir: context.current_dir.clone(),
+            follow_symlinks: context.root_config.follow_symlinks,
+            git_dir: context.get_env_os("GIT_DIR"),
+            git_work_tree: context.get_env_os("GIT_WORK_TREE"),
+        }
+    }
+}
+
+struct CachedScans {
+    /// The modification time of the directory when its contents were read
+    dir_stamp: Option<SystemTime>,
+    dir_contents: Option<DirContents>,
+    /// The modification times of the git directory, `HEAD`, the index and the common directory
+    repo_stamps: Vec<Option<SystemTime>>,
+    repo: Option<Repo>,
+    last_used: SystemTime,
+}
+
+/// Caches the directory contents and git repository of recently visited directories,
+/// so they are not scanned and discovered again on every prompt.
+#[derive(Default)]
+struct ScanCache {
+    entries: HashMap<ScanKey, CachedScans>,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn repo_stamps(repo: &Repo) -> Vec<Option<SystemTime>> {
+    let common_dir = repo.open().common_dir().to_path_buf();
+    [
+        repo.path.clone(),
+        repo.path.join("HEAD"),
+        repo.path.join("index"),
+        common_dir,
+    ]
+    .iter()
+    .map(|path| modified(path))
+    .collect()
+}
+
+impl ScanCache {
+    /// Fills in the scans cached for the directory of `context` that are still up to date
+    fn restore<'a>(&mut self, context: Context<'a>) -> Context<'a> {
+        let Some(entry) = self.entries.remove(&ScanKey::new(&context)) else {
+            return context;
+        };
+        let dir_contents = entry
+            .dir_contents
+            .filter(|_| entry.dir_stamp == modified(&context.current_dir));
+        let repo = entry
+            .repo
+            .filter(|repo| entry.repo_stamps == repo_stamps(repo));
+        context.with_scans(dir_contents, repo)
+    }
+
+    /// Keeps the scans made while rendering the prompt of `context`
+    fn store(&mut self, context: &mut Context, started: SystemTime) {
+        // Like racy git, anything modified around the time it was read might have changed
+        // while it was being read, so it's only cached once it has settled. Missing files,
+        // like the index of a new repository, invalidate the cache once they are created.
+        let settled = started - Duration::from_secs(1);
+        let is_settled =
+            |stamp: &Option<SystemTime>| !matches!(stamp, Some(stamp) if *stamp >= settled);
+
+        let key = ScanKey::new(context);
+        let (dir_contents, repo) = context.take_scans();
+        let dir_stamp = modified(&context.current_dir);
+        let dir_contents = dir_contents.filter(|_| is_settled(&dir_stamp));
+        let repo_stamps = repo.as_ref().map(repo_stamps).unwrap_or_default();
+        let repo = repo.filter(|_| repo_stamps.iter().all(is_settled));
+        if dir_contents.is_none() && repo.is_none() {
+            return;
+        }
+
+        if self.entries.len() >= MAX_CACHED_SCANS {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(
+            key,
+            CachedScans {
+                dir_stamp,
+                dir_contents,
+                repo_stamps,
+                repo,
+                last_used: started,
+            },
+        );
+    }
+}
+
+/// Runs the daemon for the current session until it has been idle for `idle_timeout`.
+pub fn run(idle_timeout: Duration) -> io::Result<()> {
+    let socket = socket_path().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "STARSHIP_SESSION_KEY is not set, run `starship init` first",
+        )
+    })?;
+
+    if UnixStream::connect(&socket).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("a daemon is already listening on {socket:?}"),
+        ));
+    }
+    // Remove a socket left behind by a daemon that did not shut down cleanly.
+    let _ = fs::remove_file(&socket);
+    if let Some(parent) = socket.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let listener = UnixListener::bind(&socket)?;
+    fs::set_permissions(&socket, fs::Permissions::from_mode(0o600))?;
+    log::debug!("Daemon listening on {socket:?}");
+
+    let last_request = Arc::new(AtomicU64::new(now_secs()));
+    if !idle_timeout.is_zero() {
+        let last_request = Arc::clone(&last_request);
+        let socket = socket.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(Duration::from_secs(1));
+            let idle = now_secs().saturating_sub(last_request.load(Ordering::Relaxed));
+            if idle >= idle_timeout.as_secs() {
+                log::debug!("Daemon idle for {idle}s, shutting down");
+                let _ = fs::remove_file(&socket);
+                std::process::exit(0);
+            }
+        });
+    }
+
+    serve(&listener, &last_request);
+    Ok(())
+}
+
+fn serve(listener: &UnixListener, last_request: &AtomicU64) {
+    let mut cache = ConfigCache::default();
+    let mut scans = ScanCache::default();
+    for stream in listener.incoming() {
+        last_request.store(now_secs(), Ordering::Relaxed);
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = handle_connection(stream, &mut cache, &mut scans) {
+                    log::warn!("Daemon failed to handle request: {e}");
+                }
+            }
+            Err(e) => log::warn!("Daemon failed to accept connection: {e}"),
+        }
+    }
+}
+
+fn handle_connection(
+    mut stream: UnixStream,
+    cache: &mut ConfigCache,
+    scans: &mut ScanCache,
+) -> io::Result<()> {
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let request: Request = serde_json::from_str(&line)?;
+    log::trace!("Daemon received request: {request:?}");
+
+    let args = PromptArgs::try_parse_from(
+        std::iter::once(OsString::from("prompt")).chain(request.args.iter().cloned()),
+    )
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+
+    // The daemon does not share the working directory of the client
+    let target = args.target();
+    let mut properties = args.properties;
+    properties.path.get_or_insert(request.cwd.into());
+
+    // Modules and the commands they run see the environment of the client
+    let env = request.env.map_or_else(Env::default, Env::from_vars);
+
+    let started = SystemTime::now();
+    let config = cache.get(request.config_path);
+    let mut context = scans.restore(Context::new_with_config(properties, target, config, env));
+
+    stream.write_all(print::render_prompt(&context).as_bytes())?;
+    scans.store(&mut context, started);
+    Ok(())
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{Properties, Shell, Target};
+    use crate::utils::write_file;
+
+    #[test]
+    fn config_cache_reloads_changed_file() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("starship.toml");
+        write_file(&path, "format = 'a'")?;
+
+        let mut cache = ConfigCache::default();
+        let config = cache.get(Some(path.clone().into()));
+        assert_eq!(
+            config.get_config(&["format"]),
+            Some(&toml::Value::from("a"))
+        );
+
+        write_file(&path, "format = 'bb'")?;
+        let config = cache.get(Some(path.into()));
+        assert_eq!(
+            config.get_config(&["format"]),
+            Some(&toml::Value::from("bb"))
+        );
+        dir.close()
+    }
+
+    #[test]
+    fn config_cache_reloads_changed_include() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("starship.toml");
+        let base = dir.path().join("base.toml");
+        write_file(&path, "include = ['base.toml']")?;
+        write_file(&base, "format = 'a'")?;
+
+        let mut cache = ConfigCache::default();
+        let config = cache.get(Some(path.clone().into()));
+        assert_eq!(
+            config.get_config(&["format"]),
+            Some(&toml::Value::from("a"))
+        );
+
+        write_file(&base, "format = 'bb'")?;
+        let config = cache.get(Some(path.into()));
+        assert_eq!(
+            config.get_config(&["format"]),
+            Some(&toml::Value::from("bb"))
+        );
+        dir.close()
+    }
+
+    #[test]
+    fn config_cache_without_file() {
+        let mut cache = ConfigCache::default();
+        assert!(cache.get(None).config.is_none());
+    }
+
+    fn scanned_context(dir: &Path, scans: &mut ScanCache) -> Context<'static> {
+        let context = Context::new_with_shell_and_path(
+            Properties::default(),
+            Shell::Unknown,
+            Target::Main,
+            dir.to_path_buf(),
+            dir.to_path_buf(),
+            Env::default(),
+        );
+        scans.restore(context)
+    }
+
+    #[test]
+    fn scan_cache_reuses_dir_contents_until_dir_changes() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        write_file(dir.path().join("index.js"), "")?;
+        let settled = SystemTime::now() + Duration::from_secs(10);
+
+        let mut scans = ScanCache::default();
+        let mut context = scanned_context(dir.path(), &mut scans);
+        assert!(context.dir_contents()?.has_file_name("index.js"));
+        scans.store(&mut context, settled);
+
+        let mut context = scanned_context(dir.path(), &mut scans);
+        assert!(context.take_scans().0.is_some());
+        scans.store(&mut context, settled);
+
+        write_file(dir.path().join("main.js"), "")?;
+        let mut context = scanned_context(dir.path(), &mut scans);
+        assert!(context.take_scans().0.is_none());
+        dir.close()
+    }
+
+    #[test]
+    fn scan_cache_reuses_repo_until_head_changes() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        crate::utils::create_command("git")?
+            .args(["init", "--quiet", "--initial-branch=main"])
+            .current_dir(dir.path())
+            .output()?;
+        let settled = SystemTime::now() + Duration::from_secs(10);
+
+        let mut scans = ScanCache::default();
+        let mut context = scanned_context(dir.path(), &mut scans);
+        assert!(context.get_repo().is_ok());
+        scans.store(&mut context, settled);
+
+        let mut context = scanned_context(dir.path(), &mut scans);
+        let (dir_contents, repo) = context.take_scans();
+        assert!(repo.is_some());
+        let mut context = context.with_scans(dir_contents, repo);
+        scans.store(&mut context, settled);
+
+        write_file(dir.path().join(".git/HEAD"), "ref: refs/heads/other\n")?;
+        let mut context = scanned_context(dir.path(), &mut scans);
+        assert!(context.take_scans().1.is_none());
+        dir.close()
+    }
+
+    #[test]
+    fn scan_cache_skips_recently_modified_dir() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        write_file(dir.path().join("index.js"), "")?;
+
+        let mut scans = ScanCache::default();
+        let mut context = scanned_context(dir.path(), &mut scans);
+        context.dir_contents()?;
+        scans.store(&mut context, SystemTime::now());
+
+        assert!(scans.entries.is_empty());
+        dir.close()
+    }
+
+    #[test]
+    fn request_keeps_non_utf8_arguments_and_environment() -> io::Result<()> {
+        use std::os::unix::ffi::OsStringExt;
+
+        let value = OsString::from_vec(vec![b'a', 0xff, b'b']);
+        let mut path = OsString::from("--path=");
+        path.push(&value);
+        let request = Request {
+            args: vec![path.clone()],
+            cwd: value.clone(),
+            config_path: None,
+            env: Some(vec![(OsString::from("NOT_UTF8"), value.clone())]),
+        };
+        let request: Request = serde_json::from_slice(&serde_json::to_vec(&request)?)?;
+
+        assert_eq!(request.args, vec![path]);
+        assert_eq!(request.cwd, value);
+        assert_eq!(request.env, Some(vec![(OsString::from("NOT_UTF8"), value)]));
+        Ok(())
+    }
+
+    #[test]
+    fn renders_prompt_over_socket() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = dir.path().join("starship.toml");
+        write_file(&config, "format = 'daemon>'\nadd_newline = false")?;
+        let socket = dir.path().join("daemon.sock");
+        let listener = UnixListener::bind(&socket)?;
+
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept()?;
+            handle_connection(
+                stream,
+                &mut ConfigCache::default(),
+                &mut ScanCache::default(),
+            )
+        });
+
+        let request = Request {
+            args: vec![OsString::from("--terminal-width=80")],
+            cwd: dir.path().into(),
+            config_path: Some(config.into()),
+            env: None,
+        };
+        let prompt = send_request(UnixStream::connect(&socket)?, &request)?;
+        server.join().unwrap()?;
+
+        assert_eq!(prompt, "daemon>");
+        dir.close()
+    }
+
+    #[test]
+    fn renders_prompt_with_client_environment() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = dir.path().join("starship.toml");
+        write_file(
+            &config,
+            "format = '$env_var>'\nadd_newline = false\n[env_var]\nvariable = 'STARSHIP_DAEMON_TEST'\nformat = '$env_value'",
+        )?;
+        let socket = dir.path().join("daemon.sock");
+        let listener = UnixListener::bind(&socket)?;
+
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept()?;
+            handle_connection(
+                stream,
+                &mut ConfigCache::default(),
+                &mut ScanCache::default(),
+            )
+        });
+
+        let request = Request {
+            args: Vec::new(),
+            cwd: dir.path().into(),
+            config_path: Some(config.into()),
+            env: Some(vec![(
+                OsString::from("STARSHIP_DAEMON_TEST"),
+                OsString::from("client"),
+            )]),
+        };
+        let prompt = send_request(UnixStream::connect(&socket)?, &request)?;
+        server.join().unwrap()?;
+
+        assert_eq!(prompt, "client>");
+        assert!(std::env::var_os("STARSHIP_DAEMON_TEST").is_none());
+        dir.close()
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let socket = dir.path().join("daemon.sock");
+        let listener = UnixListener::bind(&socket)?;
+
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept()?;
+            handle_connection(
+                stream,
+                &mut ConfigCache::default(),
+                &mut ScanCache::default(),
+            )
+        });
+
+        let request = Request {
+            args: vec![OsString::from("--no-such-flag")],
+            cwd: dir.path().into(),
+            config_path: None,
+            env: None,
+        };
+        assert!(send_request(UnixStream::connect(&socket)?, &request).is_err());
+        assert!(server.join().unwrap().is_err());
+        dir.close()
+    }
+}
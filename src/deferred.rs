@@ -0,0 +1,344 @@
+use sha1::{Digest, Sha1};
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, SystemTime};
+
+use crate::cache;
+use crate::context::{Context, Shell};
+use crate::module::Module;
+use crate::modules;
+use crate::segment::Segment;
+use crate::utils;
+
+/// How long a refresh job may run before another one is started for the same module.
+const STALE_LOCK: Duration = Duration::from_secs(60);
+
+/// Returns true if the module has `async = true` set in its configuration.
+/// `custom.<name>` and `env_var.<name>` modules are looked up in their parent table.
+pub fn is_async(context: &Context, name: &str) -> bool {
+    let path: Vec<&str> = name.split('.').collect();
+    context
+        .config
+        .get_config(&path)
+        .and_then(|config| config.as_table()?.get("async")?.as_bool())
+        .unwrap_or(false)
+}
+
+/// Renders the module from the result of the last background refresh, or as the
+/// `async_placeholder` if there is none yet, and starts a new refresh in the background.
+pub fn module<'a>(name: &str, context: &'a Context) -> Option<Module<'a>> {
+    let cache_file = cache_file(context, name);
+    let cached = utils::read_file(&cache_file)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Vec<Segment>>(&content).ok());
+
+    spawn_refresh(context, name, &cache_file);
+
+    let segments = match cached {
+        Some(segments) => segments,
+        None if context.root_config.async_placeholder.is_empty() => return None,
+        None => Segment::from_text(None, &context.root_config.async_placeholder),
+    };
+    let mut module = context.new_module(name);
+    module.set_segments(segments);
+    Some(module)
+}
+
+/// Computes the module and stores its segments for the next prompt.
+/// If the result changed, the shell is asked to repaint the prompt.
+pub fn refresh(name: &str, context: &Context) {
+    let cache_file = cache_file(context, name);
+    let segments = modules::handle(name, context).map_or_else(Vec::new, |m| m.segments);
+    if let Some(parent) = cache_file.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+
+    let content = match serde_json::to_string(&segments) {
+        Ok(content) => content,
+        Err(e) => {
+            log::warn!("Unable to serialize module {name}: {e}");
+            return;
+        }
+    };
+    let changed = utils::read_file(&cache_file).map_or(true, |previous| previous != content);
+
+    if changed {
+        if let Err(e) = fs::write(&cache_file, content) {
+            log::warn!("Unable to write cached module {name} to {cache_file:?}: {e}");
+        } else {
+            request_repaint(context);
+        }
+        if let Err(e) = cache::evict_entries(&cache_dir(context), SystemTime::now()) {
+            log::debug!("Unable to evict cached modules: {e}");
+        }
+    }
+    let _ = fs::remove_file(lock_file(&cache_file));
+}
+
+fn cache_dir(context: &Context) -> PathBuf {
+    cache::cache_dir(context).join("async")
+}
+
+/// The cache file is specific to the module, the directory the prompt is rendered for and,
+/// in a git repository, the checked out commit.
+fn cache_file(context: &Context, name: &str) -> PathBuf {
+    let mut hasher = Sha1::new();
+    hasher.update(name.as_bytes());
+    hasher.update([0]);
+    hasher.update(context.logical_dir.as_os_str().as_encoded_bytes());
+    if let Ok(repo) = context.get_repo() {
+        hasher.update([0]);
+        hasher.update(repo.branch.as_deref().unwrap_or_default().as_bytes());
+        if let Ok(head) = repo.open().head_id() {
+            hasher.update([0]);
+            hasher.update(head.as_bytes());
+        }
+    }
+    let key = utils::encode_to_hex(&hasher.finalize());
+
+    cache_dir(context).join(format!("{name}_{key}.json"))
+}
+
+fn lock_file(cache_file: &Path) -> PathBuf {
+    cache_file.with_extension("lock")
+}
+
+/// Starts `starship module <name> --async-refresh` unless a refresh is already running.
+fn spawn_refresh(context: &Context, name: &str, cache_file: &Path) {
+    let lock = lock_file(cache_file);
+    if let Some(parent) = lock.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if !acquire_lock(&lock) {
+        log::trace!("Refresh of module {name} is already running");
+        return;
+    }
+
+    let args = refresh_args(context, name);
+    if cfg!(test) {
+        log::trace!("Not spawning refresh of module {name} in tests: {args:?}");
+        return;
+    }
+
+    let spawned = std::env::current_exe()
+        .and_then(|exe| context.create_command(exe))
+        .and_then(|mut cmd| {
+            cmd.args(&args)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+        });
+    if let Err(e) = spawned {
+        log::warn!("Unable to start refresh of module {name}: {e}");
+        let _ = fs::remove_file(&lock);
+    }
+}
+
+/// Creates the lock file of a refresh, replacing it if the refresh that created it
+/// has been running for longer than `STALE_LOCK`.
+/// Returns false if another refresh holds the lock.
+fn acquire_lock(lock: &Path) -> bool {
+    for _ in 0..2 {
+        match OpenOptions::new().write(true).create_new(true).open(lock) {
+            Ok(_) => return true,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                let is_stale = fs::metadata(lock)
+                    .and_then(|m| m.modified())
+                    .is_ok_and(|modified| modified.elapsed().unwrap_or_default() >= STALE_LOCK);
+                if !is_stale {
+                    return false;
+                }
+                log::debug!("Removing stale lock file {lock:?}");
+                let _ = fs::remove_file(lock);
+            }
+            Err(e) => {
+                log::debug!("Unable to create lock file {lock:?}: {e}");
+                return false;
+            }
+        }
+    }
+    false
+}
+
+/// The arguments of `starship module` to render the module like the current prompt
+fn refresh_args(context: &Context, name: &str) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec![
+        "module".into(),
+        name.into(),
+        "--async-refresh".into(),
+        "--path".into(),
+        context.current_dir.clone().into(),
+        "--logical-path".into(),
+        context.logical_dir.clone().into(),
+        format!("--terminal-width={}", context.width).into(),
+        format!("--keymap={}", context.properties.keymap).into(),
+        format!("--jobs={}", context.properties.jobs).into(),
+    ];
+    if let Some(status) = &context.properties.status_code {
+        args.push(format!("--status={status}").into());
+    }
+    if let Some(pipestatus) = context.properties.pipestatus.as_ref() {
+        if !pipestatus.is_empty() {
+            args.push(format!("--pipestatus={}", pipestatus.join(" ")).into());
+        }
+    }
+    if let Some(duration) = &context.properties.cmd_duration {
+        args.push(format!("--cmd-duration={duration}").into());
+    }
+    if let Some(pid) = context.properties.shell_pid {
+        args.push(format!("--shell-pid={pid}").into());
+    }
+    args
+}
+
+/// Signals the shell passed with `--shell-pid` to redraw its prompt.
+/// Only zsh and fish install a handler for the signal, which would terminate other shells.
+#[cfg(unix)]
+fn request_repaint(context: &Context) {
+    use nix::sys::signal::{kill, Signal};
+    use nix::unistd::Pid;
+
+    if !matches!(context.shell, Shell::Zsh | Shell::Fish) {
+        return;
+    }
+    let Some(pid) = context.properties.shell_pid else {
+        return;
+    };
+    if let Err(e) = kill(Pid::from_raw(pid), Signal::SIGUSR1) {
+        log::debug!("Unable to signal shell {pid} to repaint the prompt: {e}");
+    }
+}
+
+#[cfg(not(unix))]
+fn request_repaint(_context: &Context) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{default_context, fixture_repo, FixtureProvider};
+    use crate::utils::create_command;
+
+    #[test]
+    fn detects_async_modules() {
+        let context = default_context().set_config(toml::toml! {
+            [git_status]
+            async = true
+            [git_branch]
+            async = false
+            [custom.slow]
+            async = true
+        });
+
+        assert!(is_async(&context, "git_status"));
+        assert!(!is_async(&context, "git_branch"));
+        assert!(!is_async(&context, "directory"));
+        assert!(is_async(&context, "custom.slow"));
+        assert!(!is_async(&context, "custom.fast"));
+    }
+
+    #[test]
+    fn renders_nothing_without_cache() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut context = default_context().set_config(toml::toml! {
+            [character]
+            async = true
+        });
+        context
+            .env
+            .insert("STARSHIP_CACHE", dir.path().to_string_lossy().to_string());
+
+        assert!(module("character", &context).is_none());
+        assert!(lock_file(&cache_file(&context, "character")).exists());
+        dir.close()
+    }
+
+    #[test]
+    fn renders_placeholder_without_cache() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut context = default_context().set_config(toml::toml! {
+            async_placeholder = "…"
+            [character]
+            async = true
+        });
+        context
+            .env
+            .insert("STARSHIP_CACHE", dir.path().to_string_lossy().to_string());
+
+        let actual = module("character", &context).map(|m| m.to_string());
+        assert_eq!(actual.as_deref(), Some("…"));
+        dir.close()
+    }
+
+    #[test]
+    fn renders_refreshed_value() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut context = default_context().set_config(toml::toml! {
+            [character]
+            async = true
+            format = "[>](bold red) "
+        });
+        context
+            .env
+            .insert("STARSHIP_CACHE", dir.path().to_string_lossy().to_string());
+        fs::create_dir_all(cache_dir(&context))?;
+
+        refresh("character", &context);
+        assert!(!lock_file(&cache_file(&context, "character")).exists());
+
+        let expected = modules::handle("character", &context).map(|m| m.to_string());
+        let actual = module("character", &context).map(|m| m.to_string());
+        assert!(expected.is_some());
+        assert_eq!(expected, actual);
+        dir.close()
+    }
+
+    #[test]
+    fn lock_is_only_acquired_once() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let lock = dir.path().join("module.lock");
+
+        assert!(acquire_lock(&lock));
+        assert!(!acquire_lock(&lock));
+        fs::remove_file(&lock)?;
+        assert!(acquire_lock(&lock));
+        dir.close()
+    }
+
+    #[test]
+    fn refresh_forwards_prompt_arguments() {
+        let mut context = default_context();
+        context.properties.status_code = Some(String::from("1"));
+        context.properties.pipestatus = Some(vec![String::from("0"), String::from("1")]);
+        context.properties.shell_pid = Some(42);
+
+        let args = refresh_args(&context, "status");
+        assert!(args.contains(&OsString::from("--status=1")));
+        assert!(args.contains(&OsString::from("--pipestatus=0 1")));
+        assert!(args.contains(&OsString::from("--shell-pid=42")));
+    }
+
+    #[test]
+    fn cache_file_changes_with_head() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+        let context_in_repo = || {
+            let mut context = default_context();
+            context.current_dir = repo_dir.path().to_path_buf();
+            context.logical_dir = repo_dir.path().to_path_buf();
+            context
+        };
+
+        let before = cache_file(&context_in_repo(), "git_status");
+        assert_eq!(before, cache_file(&context_in_repo(), "git_status"));
+
+        create_command("git")?
+            .args(["commit", "--allow-empty", "--no-gpg-sign", "-m", "empty"])
+            .current_dir(repo_dir.path())
+            .output()?;
+        assert_ne!(before, cache_file(&context_in_repo(), "git_status"));
+        repo_dir.close()
+    }
+}
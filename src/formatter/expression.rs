@@ -0,0 +1,269 @@
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+use super::model::VariableHolder;
+use super::string_formatter::StringFormatterError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnaryOperator {
+    Not,
+    Negate,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    And,
+    Or,
+}
+
+impl BinaryOperator {
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        Some(match symbol {
+            "+" => Self::Add,
+            "-" => Self::Subtract,
+            "*" => Self::Multiply,
+            "/" => Self::Divide,
+            "%" => Self::Remainder,
+            "==" => Self::Equal,
+            "!=" => Self::NotEqual,
+            "<" => Self::Less,
+            "<=" => Self::LessOrEqual,
+            ">" => Self::Greater,
+            ">=" => Self::GreaterOrEqual,
+            "&&" => Self::And,
+            "||" => Self::Or,
+            _ => return None,
+        })
+    }
+}
+
+/// The condition of an `$if(...)` block
+#[derive(Clone, Debug)]
+pub enum Expression<'a> {
+    Number(f64),
+    Text(Cow<'a, str>),
+    Variable(Cow<'a, str>),
+    Unary(UnaryOperator, Box<Expression<'a>>),
+    Binary(Box<Expression<'a>>, BinaryOperator, Box<Expression<'a>>),
+}
+
+/// The result of evaluating an `Expression`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Number(f64),
+    Text(String),
+}
+
+impl Value {
+    /// Returns the value as a number, parsing texts like `42`, `50%` or `1m30s`
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Self::Bool(value) => Some(if *value { 1.0 } else { 0.0 }),
+            Self::Number(value) => Some(*value),
+            Self::Text(text) => parse_number(text),
+        }
+    }
+
+    fn as_text(&self) -> Cow<'_, str> {
+        match self {
+            Self::Bool(value) => value.to_string().into(),
+            Self::Number(value) => value.to_string().into(),
+            Self::Text(text) => text.into(),
+        }
+    }
+
+    /// Empty texts and zero are false, everything else is true
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Self::Bool(value) => *value,
+            Self::Number(value) => *value != 0.0,
+            Self::Text(text) => parse_number(text).map_or(!text.is_empty(), |value| value != 0.0),
+        }
+    }
+}
+
+impl<'a> Expression<'a> {
+    /// Evaluates the expression, looking up the text of variables with `lookup`
+    ///
+    /// Values are compared as numbers if both of them are numbers, and as texts otherwise.
+    /// Arithmetic on a value that is not a number results in an empty text.
+    pub fn evaluate<F>(&self, lookup: &F) -> Result<Value, StringFormatterError>
+    where
+        F: Fn(&str) -> Result<String, StringFormatterError>,
+    {
+        Ok(match self {
+            Self::Number(value) => Value::Number(*value),
+            Self::Text(text) => Value::Text(text.to_string()),
+            Self::Variable(name) => Value::Text(lookup(name)?),
+            Self::Unary(UnaryOperator::Not, operand) => {
+                Value::Bool(!operand.evaluate(lookup)?.is_truthy())
+            }
+            Self::Unary(UnaryOperator::Negate, operand) => operand
+                .evaluate(lookup)?
+                .as_number()
+                .map_or_else(|| Value::Text(String::new()), |value| Value::Number(-value)),
+            Self::Binary(left, BinaryOperator::And, right) => Value::Bool(
+                left.evaluate(lookup)?.is_truthy() && right.evaluate(lookup)?.is_truthy(),
+            ),
+            Self::Binary(left, BinaryOperator::Or, right) => Value::Bool(
+                left.evaluate(lookup)?.is_truthy() || right.evaluate(lookup)?.is_truthy(),
+            ),
+            Self::Binary(left, operator, right) => {
+                apply(&left.evaluate(lookup)?, *operator, &right.evaluate(lookup)?)
+            }
+        })
+    }
+}
+
+fn apply(left: &Value, operator: BinaryOperator, right: &Value) -> Value {
+    let numbers = left.as_number().zip(right.as_number());
+    let ordering = match numbers {
+        Some((left, right)) => left.partial_cmp(&right),
+        None => Some(left.as_text().cmp(&right.as_text())),
+    };
+    let arithmetic = |f: fn(f64, f64) -> f64| {
+        numbers.map_or_else(
+            || Value::Text(String::new()),
+            |(left, right)| Value::Number(f(left, right)),
+        )
+    };
+
+    match operator {
+        BinaryOperator::Add => arithmetic(|a, b| a + b),
+        BinaryOperator::Subtract => arithmetic(|a, b| a - b),
+        BinaryOperator::Multiply => arithmetic(|a, b| a * b),
+        BinaryOperator::Divide => arithmetic(|a, b| a / b),
+        BinaryOperator::Remainder => arithmetic(|a, b| a % b),
+        BinaryOperator::Equal => Value::Bool(ordering.is_some_and(|o| o.is_eq())),
+        BinaryOperator::NotEqual => Value::Bool(!ordering.is_some_and(|o| o.is_eq())),
+        BinaryOperator::Less => Value::Bool(ordering.is_some_and(|o| o.is_lt())),
+        BinaryOperator::LessOrEqual => Value::Bool(ordering.is_some_and(|o| o.is_le())),
+        BinaryOperator::Greater => Value::Bool(ordering.is_some_and(|o| o.is_gt())),
+        BinaryOperator::GreaterOrEqual => Value::Bool(ordering.is_some_and(|o| o.is_ge())),
+        BinaryOperator::And | BinaryOperator::Or => unreachable!("evaluated lazily"),
+    }
+}
+
+/// Parses a number, a percentage (`50%`) or a duration (`1m30s`) in milliseconds
+pub fn parse_number(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let text = text.strip_suffix('%').map_or(text, str::trim_end);
+    match text.parse::<f64>() {
+        Ok(value) if value.is_finite() => Some(value),
+        _ => parse_duration(text),
+    }
+}
+
+fn parse_duration(mut text: &str) -> Option<f64> {
+    const UNITS: [(&str, f64); 5] = [
+        ("ms", 1.0),
+        ("s", 1000.0),
+        ("m", 60_000.0),
+        ("h", 3_600_000.0),
+        ("d", 86_400_000.0),
+    ];
+
+    if text.is_empty() {
+        return None;
+    }
+    let mut total = 0.0;
+    while !text.is_empty() {
+        let digits = text
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(text.len());
+        let value: f64 = text[..digits].parse().ok()?;
+        text = &text[digits..];
+
+        let (unit, millis) = UNITS.iter().find(|(unit, _)| text.starts_with(unit))?;
+        total += value * millis;
+        text = &text[unit.len()..];
+    }
+    Some(total)
+}
+
+impl<'a> VariableHolder<Cow<'a, str>> for Expression<'a> {
+    fn get_variables(&self) -> BTreeSet<Cow<'a, str>> {
+        match self {
+            Expression::Variable(name) => BTreeSet::from([name.clone()]),
+            Expression::Unary(_, operand) => operand.get_variables(),
+            Expression::Binary(left, _, right) => {
+                let mut variables = left.get_variables();
+                variables.extend(right.get_variables());
+                variables
+            }
+            Expression::Number(_) | Expression::Text(_) => BTreeSet::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_number() {
+        assert_eq!(parse_number("42"), Some(42.0));
+        assert_eq!(parse_number(" -1.5 "), Some(-1.5));
+        assert_eq!(parse_number("85%"), Some(85.0));
+        assert_eq!(parse_number("500ms"), Some(500.0));
+        assert_eq!(parse_number("1m30s"), Some(90_000.0));
+        assert_eq!(parse_number("2h"), Some(7_200_000.0));
+        assert_eq!(parse_number("1d2h3m4s5ms"), Some(93_784_005.0));
+        assert_eq!(parse_number(""), None);
+        assert_eq!(parse_number("main"), None);
+        assert_eq!(parse_number("inf"), None);
+        assert_eq!(parse_number("3x"), None);
+    }
+
+    #[test]
+    fn test_truthiness() {
+        assert!(Value::Text("main".to_string()).is_truthy());
+        assert!(Value::Text("1".to_string()).is_truthy());
+        assert!(!Value::Text("0".to_string()).is_truthy());
+        assert!(!Value::Text(String::new()).is_truthy());
+        assert!(!Value::Number(0.0).is_truthy());
+        assert!(Value::Bool(true).is_truthy());
+    }
+
+    #[test]
+    fn test_apply() {
+        let text = |text: &str| Value::Text(text.to_string());
+
+        assert_eq!(
+            apply(&text("10"), BinaryOperator::Add, &Value::Number(5.0)),
+            Value::Number(15.0)
+        );
+        assert_eq!(
+            apply(&text("a"), BinaryOperator::Add, &Value::Number(5.0)),
+            text("")
+        );
+        assert_eq!(
+            apply(&text("10"), BinaryOperator::Greater, &text("9")),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            apply(&text("10"), BinaryOperator::Greater, &text("9a")),
+            Value::Bool(false)
+        );
+        assert_eq!(
+            apply(&text("1m"), BinaryOperator::GreaterOrEqual, &text("60s")),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            apply(&text("main"), BinaryOperator::NotEqual, &text("master")),
+            Value::Bool(true)
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use std::borrow::Cow;
+use std::path::Path;
+use unicode_segmentation::UnicodeSegmentation;
+use versions::Versioning;
+
+/// An argument passed to a filter, e.g. `20` in `$branch|truncate(20)`
+#[derive(Clone, Debug, PartialEq)]
+pub enum FilterArgument<'a> {
+    Number(f64),
+    Text(Cow<'a, str>),
+}
+
+/// A filter that transforms the value of a variable, e.g. `$name|upper`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter<'a> {
+    Upper,
+    Lower,
+    Trim,
+    /// Keeps the first `length` graphemes, followed by `symbol` if anything was cut off
+    Truncate {
+        length: usize,
+        symbol: Cow<'a, str>,
+    },
+    /// Keeps the last `length` graphemes, preceded by `symbol` if anything was cut off
+    TruncateStart {
+        length: usize,
+        symbol: Cow<'a, str>,
+    },
+    Basename,
+    Dirname,
+    Major,
+    Minor,
+    Patch,
+    MajorMinor,
+}
+
+const DEFAULT_TRUNCATION_SYMBOL: &str = "…";
+
+impl<'a> Filter<'a> {
+    /// Creates the filter with the given name from its arguments
+    pub fn new(name: &str, args: Vec<FilterArgument<'a>>) -> Result<Self, String> {
+        let mut args = args.into_iter();
+        let filter = match name {
+            "upper" => Self::Upper,
+            "lower" => Self::Lower,
+            "trim" => Self::Trim,
+            "truncate" | "truncate_start" => {
+                let length = match args.next() {
+                    Some(FilterArgument::Number(length)) if length >= 0.0 => length as usize,
+                    _ => return Err(format!("`{name}` expects a length, e.g. `{name}(20)`")),
+                };
+                let symbol = match args.next() {
+                    Some(FilterArgument::Text(symbol)) => symbol,
+                    None => DEFAULT_TRUNCATION_SYMBOL.into(),
+                    Some(FilterArgument::Number(_)) => {
+                        return Err(format!("`{name}` expects a quoted truncation symbol"))
+                    }
+                };
+                if name == "truncate" {
+                    Self::Truncate { length, symbol }
+                } else {
+                    Self::TruncateStart { length, symbol }
+                }
+            }
+            "basename" => Self::Basename,
+            "dirname" => Self::Dirname,
+            "major" => Self::Major,
+            "minor" => Self::Minor,
+            "patch" => Self::Patch,
+            "major_minor" => Self::MajorMinor,
+            _ => return Err(format!("Unknown filter `{name}`")),
+        };
+        if args.next().is_some() {
+            return Err(format!("Too many arguments for filter `{name}`"));
+        }
+        Ok(filter)
+    }
+
+    /// Applies the filter to a value
+    pub fn apply(&self, value: &str) -> String {
+        match self {
+            Self::Upper => value.to_uppercase(),
+            Self::Lower => value.to_lowercase(),
+            Self::Trim => value.trim().to_string(),
+            Self::Truncate { length, symbol } => {
+                let graphemes: Vec<&str> = value.graphemes(true).collect();
+                if graphemes.len() <= *length {
+                    return value.to_string();
+                }
+                graphemes[..*length].concat() + symbol
+            }
+            Self::TruncateStart { length, symbol } => {
+                let graphemes: Vec<&str> = value.graphemes(true).collect();
+                if graphemes.len() <= *length {
+                    return value.to_string();
+                }
+                symbol.to_string() + &graphemes[graphemes.len() - length..].concat()
+            }
+            Self::Basename => Path::new(value)
+                .file_name()
+                .map_or_else(|| value.to_string(), |name| name.to_string_lossy().into()),
+            Self::Dirname => Path::new(value)
+                .parent()
+                .map_or_else(String::new, |parent| parent.to_string_lossy().into()),
+            Self::Major => version_part(value, 0).unwrap_or_else(|| value.to_string()),
+            Self::Minor => version_part(value, 1).unwrap_or_else(|| value.to_string()),
+            Self::Patch => version_part(value, 2).unwrap_or_else(|| value.to_string()),
+            Self::MajorMinor => version_part(value, 0)
+                .zip(version_part(value, 1))
+                .map_or_else(
+                    || value.to_string(),
+                    |(major, minor)| format!("{major}.{minor}"),
+                ),
+        }
+    }
+
+    /// Applies the filter to a value split into parts, like the segments of a module, as if the
+    /// parts were joined together. Filters that parse the whole value, like `basename` or
+    /// `major`, leave their result in the first part.
+    pub fn apply_to_parts(&self, parts: &mut [String]) {
+        if let [part] = parts {
+            *part = self.apply(part);
+            return;
+        }
+        match self {
+            Self::Upper | Self::Lower => {
+                for part in parts.iter_mut() {
+                    *part = self.apply(part);
+                }
+            }
+            Self::Trim => {
+                for part in parts.iter_mut() {
+                    *part = part.trim_start().to_string();
+                    if !part.is_empty() {
+                        break;
+                    }
+                }
+                for part in parts.iter_mut().rev() {
+                    *part = part.trim_end().to_string();
+                    if !part.is_empty() {
+                        break;
+                    }
+                }
+            }
+            Self::Truncate { length, symbol } => truncate_parts(parts, *length, symbol, false),
+            Self::TruncateStart { length, symbol } => truncate_parts(parts, *length, symbol, true),
+            _ => {
+                if let [first, rest @ ..] = parts {
+                    *first = self.apply(&(first.clone() + &rest.concat()));
+                    rest.iter_mut().for_each(String::clear);
+                }
+            }
+        }
+    }
+}
+
+/// Keeps `length` graphemes of the parts, counted from the start or the end, adding `symbol`
+/// to the part the limit falls into and clearing the parts beyond it
+fn truncate_parts(parts: &mut [String], length: usize, symbol: &str, from_end: bool) {
+    let total: usize = parts.iter().map(|part| part.graphemes(true).count()).sum();
+    if total <= length {
+        return;
+    }
+    let parts: Box<dyn Iterator<Item = &mut String>> = if from_end {
+        Box::new(parts.iter_mut().rev())
+    } else {
+        Box::new(parts.iter_mut())
+    };
+    let mut remaining = Some(length);
+    for part in parts {
+        let Some(keep) = remaining else {
+            part.clear();
+            continue;
+        };
+        let graphemes: Vec<&str> = part.graphemes(true).collect();
+        if graphemes.len() < keep {
+            remaining = Some(keep - graphemes.len());
+            continue;
+        }
+        *part = if from_end {
+            symbol.to_string() + &graphemes[graphemes.len() - keep..].concat()
+        } else {
+            graphemes[..keep].concat() + symbol
+        };
+        remaining = None;
+    }
+}
+
+/// Returns the nth component of a version like `1.2.3` or `v1.2.3`
+fn version_part(version: &str, index: usize) -> Option<String> {
+    let version = version.trim();
+    let version = version.strip_prefix('v').unwrap_or(version);
+    match Versioning::new(version)? {
+        Versioning::Ideal(v) => [v.major, v.minor, v.patch]
+            .get(index)
+            .map(ToString::to_string),
+        Versioning::General(v) => Some(v.nth_lenient(index)?.to_string()),
+        Versioning::Complex(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(name: &str, args: Vec<FilterArgument<'static>>) -> Filter<'static> {
+        Filter::new(name, args).unwrap()
+    }
+
+    #[test]
+    fn test_case_filters() {
+        assert_eq!(filter("upper", vec![]).apply("main"), "MAIN");
+        assert_eq!(filter("lower", vec![]).apply("MaIn"), "main");
+        assert_eq!(filter("trim", vec![]).apply("  main \n"), "main");
+    }
+
+    #[test]
+    fn test_truncate() {
+        let truncate = filter("truncate", vec![FilterArgument::Number(5.0)]);
+        assert_eq!(truncate.apply("feature/branch"), "featu…");
+        assert_eq!(truncate.apply("short"), "short");
+        assert_eq!(truncate.apply("🟢🔵🟡🟢🔵🟡"), "🟢🔵🟡🟢🔵…");
+
+        let truncate = filter(
+            "truncate",
+            vec![FilterArgument::Number(3.0), FilterArgument::Text("".into())],
+        );
+        assert_eq!(truncate.apply("feature"), "fea");
+
+        let truncate_start = filter(
+            "truncate_start",
+            vec![
+                FilterArgument::Number(6.0),
+                FilterArgument::Text("...".into()),
+            ],
+        );
+        assert_eq!(truncate_start.apply("feature/branch"), "...branch");
+    }
+
+    #[test]
+    fn test_path_filters() {
+        assert_eq!(
+            filter("basename", vec![]).apply("~/src/starship"),
+            "starship"
+        );
+        assert_eq!(filter("dirname", vec![]).apply("~/src/starship"), "~/src");
+        assert_eq!(filter("dirname", vec![]).apply("starship"), "");
+    }
+
+    #[test]
+    fn test_version_filters() {
+        assert_eq!(filter("major", vec![]).apply("v18.12.1"), "18");
+        assert_eq!(filter("minor", vec![]).apply("3.11.4"), "11");
+        assert_eq!(filter("patch", vec![]).apply("1.80.0"), "0");
+        assert_eq!(filter("major_minor", vec![]).apply("v18.12.1"), "18.12");
+        assert_eq!(filter("major_minor", vec![]).apply("nightly"), "nightly");
+    }
+
+    #[test]
+    fn test_filters_on_parts() {
+        let apply = |filter: Filter, parts: &[&str]| {
+            let mut parts: Vec<String> = parts.iter().map(ToString::to_string).collect();
+            filter.apply_to_parts(&mut parts);
+            parts
+        };
+        let truncate = |length: f64| filter("truncate", vec![FilterArgument::Number(length)]);
+        let truncate_start =
+            |length: f64| filter("truncate_start", vec![FilterArgument::Number(length)]);
+
+        assert_eq!(apply(truncate(3.0), &["ab", "cde"]), ["ab", "c…"]);
+        assert_eq!(apply(truncate(2.0), &["ab", "cde", "f"]), ["ab…", "", ""]);
+        assert_eq!(apply(truncate(5.0), &["ab", "cde"]), ["ab", "cde"]);
+        assert_eq!(apply(truncate_start(4.0), &["ab", "cde"]), ["…b", "cde"]);
+        assert_eq!(
+            apply(filter("trim", vec![]), &[" ", " a ", " "]),
+            ["", "a", ""]
+        );
+        assert_eq!(apply(filter("upper", vec![]), &["a", "b"]), ["A", "B"]);
+        assert_eq!(
+            apply(filter("basename", vec![]), &["~/src/", "starship"]),
+            ["starship", ""]
+        );
+    }
+
+    #[test]
+    fn test_invalid_filters() {
+        assert!(Filter::new("unknown", vec![]).is_err());
+        assert!(Filter::new("truncate", vec![]).is_err());
+        assert!(Filter::new("truncate", vec![FilterArgument::Text("5".into())]).is_err());
+        assert!(Filter::new("upper", vec![FilterArgument::Number(1.0)]).is_err());
+    }
+}
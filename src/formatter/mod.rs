@@ -1,8 +1,11 @@
+mod expression;
+mod filter;
 pub mod model;
 mod parser;
 pub mod string_formatter;
 mod version;
 
+pub use filter::Filter;
 pub use model::{StyleVariableHolder, VariableHolder};
 pub use string_formatter::StringFormatter;
 pub use version::VersionFormatter;
@@ -1,6 +1,9 @@
 use std::borrow::Cow;
 use std::collections::BTreeSet;
 
+use super::expression::Expression;
+use super::filter::Filter;
+
 /// Type that holds a number of variables of type `T`
 pub trait VariableHolder<T> {
     fn get_variables(&self) -> BTreeSet<T>;
@@ -17,12 +20,20 @@ pub struct TextGroup<'a> {
     pub style: Vec<StyleElement<'a>>,
 }
 
+#[derive(Clone)]
+pub struct IfBlock<'a> {
+    pub condition: Expression<'a>,
+    pub then: Vec<FormatElement<'a>>,
+    pub otherwise: Vec<FormatElement<'a>>,
+}
+
 #[derive(Clone)]
 pub enum FormatElement<'a> {
     Text(Cow<'a, str>),
-    Variable(Cow<'a, str>),
+    Variable(Cow<'a, str>, Vec<Filter<'a>>),
     TextGroup(TextGroup<'a>),
     Conditional(Vec<FormatElement<'a>>),
+    If(IfBlock<'a>),
 }
 
 #[derive(Clone)]
@@ -34,13 +45,19 @@ pub enum StyleElement<'a> {
 impl<'a> VariableHolder<Cow<'a, str>> for FormatElement<'a> {
     fn get_variables(&self) -> BTreeSet<Cow<'a, str>> {
         match self {
-            FormatElement::Variable(var) => {
+            FormatElement::Variable(var, _) => {
                 let mut variables = BTreeSet::new();
                 variables.insert(var.clone());
                 variables
             }
             FormatElement::TextGroup(textgroup) => textgroup.format.get_variables(),
             FormatElement::Conditional(format) => format.get_variables(),
+            FormatElement::If(if_block) => {
+                let mut variables = if_block.condition.get_variables();
+                variables.extend(if_block.then.get_variables());
+                variables.extend(if_block.otherwise.get_variables());
+                variables
+            }
             _ => Default::default(),
         }
     }
@@ -98,6 +115,11 @@ impl<'a> StyleVariableHolder<Cow<'a, str>> for Vec<FormatElement<'a>> {
                 acc.extend(format.get_style_variables());
                 acc
             }
+            FormatElement::If(if_block) => {
+                acc.extend(if_block.then.get_style_variables());
+                acc.extend(if_block.otherwise.get_style_variables());
+                acc
+            }
             _ => acc,
         })
     }
@@ -1,9 +1,15 @@
 // Can't rename internal Pest names
 #![allow(clippy::upper_case_acronyms)]
 
-use pest::{error::Error, iterators::Pair, Parser};
+use pest::{
+    error::{Error, ErrorVariant},
+    iterators::Pair,
+    Parser,
+};
 use pest_derive::*;
 
+use super::expression::{parse_number, BinaryOperator, Expression, UnaryOperator};
+use super::filter::{Filter, FilterArgument};
 use super::model::*;
 
 #[derive(Parser)]
@@ -13,11 +19,72 @@ struct IdentParser;
 fn parse_value(value: Pair<Rule>) -> FormatElement {
     match value.as_rule() {
         Rule::text => FormatElement::Text(parse_text(value).into()),
-        Rule::variable => FormatElement::Variable(parse_variable(value).into()),
+        Rule::variable => {
+            let mut inner_rules = value.into_inner();
+            let name = inner_rules.next().unwrap().as_str();
+            let filters = inner_rules
+                .map(|filter| parse_filter(filter).expect("filters are checked in `parse`"))
+                .collect();
+            FormatElement::Variable(name.into(), filters)
+        }
         Rule::textgroup => FormatElement::TextGroup(parse_textgroup(value)),
         Rule::conditional => {
             FormatElement::Conditional(parse_format(value.into_inner().next().unwrap()))
         }
+        Rule::if_block => FormatElement::If(parse_if_block(value)),
+        _ => unreachable!(),
+    }
+}
+
+fn parse_if_block(if_block: Pair<Rule>) -> IfBlock {
+    let mut inner_rules = if_block.into_inner();
+    let condition = parse_expression(inner_rules.next().unwrap());
+    let then = parse_format(inner_rules.next().unwrap());
+    let otherwise = inner_rules
+        .next()
+        .map(|else_block| parse_format(else_block.into_inner().next().unwrap()))
+        .unwrap_or_default();
+
+    IfBlock {
+        condition,
+        then,
+        otherwise,
+    }
+}
+
+fn parse_expression(expression: Pair<Rule>) -> Expression {
+    match expression.as_rule() {
+        Rule::condition | Rule::conjunction | Rule::comparison | Rule::sum | Rule::product => {
+            // Operands alternate with the operators between them
+            let mut inner_rules = expression.into_inner();
+            let mut result = parse_expression(inner_rules.next().unwrap());
+            while let Some(operator) = inner_rules.next() {
+                let operator = BinaryOperator::from_symbol(operator.as_str()).unwrap();
+                let right = parse_expression(inner_rules.next().unwrap());
+                result = Expression::Binary(Box::new(result), operator, Box::new(right));
+            }
+            result
+        }
+        Rule::unary => {
+            let mut inner_rules = expression.into_inner();
+            let first = inner_rules.next().unwrap();
+            match first.as_rule() {
+                Rule::unary_op => {
+                    let operator = match first.as_str() {
+                        "!" => UnaryOperator::Not,
+                        _ => UnaryOperator::Negate,
+                    };
+                    Expression::Unary(
+                        operator,
+                        Box::new(parse_expression(inner_rules.next().unwrap())),
+                    )
+                }
+                _ => parse_expression(first),
+            }
+        }
+        Rule::number => Expression::Number(parse_number(expression.as_str()).unwrap()),
+        Rule::quoted => Expression::Text(expression.into_inner().next().unwrap().as_str().into()),
+        Rule::condition_variable => Expression::Variable(parse_variable(expression).into()),
         _ => unreachable!(),
     }
 }
@@ -37,6 +104,26 @@ fn parse_variable(variable: Pair<Rule>) -> &str {
     variable.into_inner().next().unwrap().as_str()
 }
 
+fn parse_filter(filter: Pair<Rule>) -> Result<Filter, Box<Error<Rule>>> {
+    let span = filter.as_span();
+    let mut inner_rules = filter.into_inner();
+    let name = inner_rules.next().unwrap().as_str();
+    let args = inner_rules
+        .map(|arg| match arg.as_rule() {
+            Rule::number => FilterArgument::Number(parse_number(arg.as_str()).unwrap()),
+            Rule::quoted => FilterArgument::Text(arg.into_inner().next().unwrap().as_str().into()),
+            _ => unreachable!(),
+        })
+        .collect();
+
+    Filter::new(name, args).map_err(|message| {
+        Box::new(Error::new_from_span(
+            ErrorVariant::CustomError { message },
+            span,
+        ))
+    })
+}
+
 fn parse_text(text: Pair<Rule>) -> String {
     text.into_inner()
         .flat_map(|pair| pair.as_str().chars())
@@ -52,19 +139,24 @@ fn parse_style(style: Pair<Rule>) -> Vec<StyleElement> {
         .into_inner()
         .map(|pair| match pair.as_rule() {
             Rule::string => StyleElement::Text(pair.as_str().into()),
-            Rule::variable => StyleElement::Variable(parse_variable(pair).into()),
+            Rule::style_variable => StyleElement::Variable(parse_variable(pair).into()),
             _ => unreachable!(),
         })
         .collect()
 }
 
 pub fn parse(format: &str) -> Result<Vec<FormatElement>, Box<Error<Rule>>> {
-    IdentParser::parse(Rule::expression, format)
-        .map(|pairs| {
-            pairs
-                .take_while(|pair| pair.as_rule() != Rule::EOI)
-                .map(parse_value)
-                .collect()
-        })
-        .map_err(Box::new)
+    let pairs = IdentParser::parse(Rule::expression, format).map_err(Box::new)?;
+
+    // Check the names and arguments of all filters first, so that building the elements can't fail
+    pairs
+        .clone()
+        .flatten()
+        .filter(|pair| pair.as_rule() == Rule::filter)
+        .try_for_each(|filter| parse_filter(filter).map(drop))?;
+
+    Ok(pairs
+        .take_while(|pair| pair.as_rule() != Rule::EOI)
+        .map(parse_value)
+        .collect())
 }
@@ -9,6 +9,7 @@ use crate::config::{parse_style_string, Style};
 use crate::context::{Context, Shell};
 use crate::segment::Segment;
 
+use super::filter::Filter;
 use super::model::*;
 use super::parser::{parse, Rule};
 
@@ -96,6 +97,37 @@ impl<'a> StringFormatter<'a> {
         }
     }
 
+    /// Returns the variables in the order they first appear in the format string
+    pub fn get_variables_in_order(&self) -> Vec<String> {
+        fn collect(format: &[FormatElement], variables: &mut Vec<String>) {
+            for element in format {
+                match element {
+                    FormatElement::Variable(name, _) => {
+                        if !variables.iter().any(|variable| variable == name) {
+                            variables.push(name.to_string());
+                        }
+                    }
+                    FormatElement::TextGroup(textgroup) => collect(&textgroup.format, variables),
+                    FormatElement::Conditional(format) => collect(format, variables),
+                    FormatElement::If(if_block) => {
+                        for name in if_block.condition.get_variables() {
+                            if !variables.iter().any(|variable| *variable == name) {
+                                variables.push(name.to_string());
+                            }
+                        }
+                        collect(&if_block.then, variables);
+                        collect(&if_block.otherwise, variables);
+                    }
+                    FormatElement::Text(_) => {}
+                }
+            }
+        }
+
+        let mut variables = Vec::new();
+        collect(&self.format, &mut variables);
+        variables
+    }
+
     /// Maps variable name to its value
     ///
     /// You should provide a function or closure that accepts the variable name `name: &str` as a
@@ -309,23 +341,27 @@ impl<'a> StringFormatter<'a> {
                             };
                             parse_textgroup(textgroup, variables, style_variables, context)
                         }
-                        FormatElement::Variable(name) => variables
+                        FormatElement::Variable(name, filters) => variables
                             .get(name.as_ref())
                             .expect("Uncached variable found")
                             .as_ref()
                             .map(|segments| match segments.clone()? {
-                                VariableValue::Styled(segments) => Ok(segments
-                                    .into_iter()
-                                    .map(|mut segment| {
-                                        // Derive upper style if the style of segments are none.
-                                        segment.set_style_if_empty(style);
-                                        segment
-                                    })
-                                    .collect()),
+                                VariableValue::Styled(segments) => {
+                                    let mut segments: Vec<Segment> = segments
+                                        .into_iter()
+                                        .map(|mut segment| {
+                                            // Derive upper style if the style of segments are none.
+                                            segment.set_style_if_empty(style);
+                                            segment
+                                        })
+                                        .collect();
+                                    apply_filters_to_segments(&mut segments, &filters);
+                                    Ok(segments)
+                                }
                                 VariableValue::Plain(text) => Ok(Segment::from_text(
                                     style,
                                     shell_prompt_escape(
-                                        text,
+                                        apply_filters(&text, &filters),
                                         match context {
                                             None => Shell::Unknown,
                                             Some(c) => c.shell,
@@ -333,7 +369,7 @@ impl<'a> StringFormatter<'a> {
                                     ),
                                 )),
                                 VariableValue::NoEscapingPlain(text) => {
-                                    Ok(Segment::from_text(style, text))
+                                    Ok(Segment::from_text(style, apply_filters(&text, &filters)))
                                 }
                                 VariableValue::Meta(format) => {
                                     let formatter = StringFormatter {
@@ -341,7 +377,10 @@ impl<'a> StringFormatter<'a> {
                                         variables: clone_without_meta(variables),
                                         style_variables: style_variables.clone(),
                                     };
-                                    formatter.parse(style, context)
+                                    formatter.parse(style, context).map(|mut segments| {
+                                        apply_filters_to_segments(&mut segments, &filters);
+                                        segments
+                                    })
                                 }
                             })
                             .unwrap_or_else(|| Ok(Vec::new())),
@@ -395,6 +434,15 @@ impl<'a> StringFormatter<'a> {
                                 Ok(Vec::new())
                             }
                         }
+                        FormatElement::If(if_block) => {
+                            let lookup = |name: &str| variable_text(name, variables);
+                            let branch = if if_block.condition.evaluate(&lookup)?.is_truthy() {
+                                if_block.then
+                            } else {
+                                if_block.otherwise
+                            };
+                            parse_format(branch, style, variables, style_variables, context)
+                        }
                     }
                 })
                 .collect();
@@ -440,6 +488,55 @@ fn clone_without_meta<'a>(variables: &VariableMapType<'a>) -> VariableMapType<'a
         .collect()
 }
 
+/// Applies the filters of a variable to its value, from left to right
+fn apply_filters(value: &str, filters: &[Filter]) -> String {
+    filters
+        .iter()
+        .fold(value.to_string(), |value, filter| filter.apply(&value))
+}
+
+/// Applies the filters of a variable to the text of its segments as a whole, so that e.g.
+/// `truncate` counts the characters of all segments. Segments emptied by a filter are removed.
+fn apply_filters_to_segments(segments: &mut Vec<Segment>, filters: &[Filter]) {
+    if filters.is_empty() {
+        return;
+    }
+    let is_text = |segment: &Segment| matches!(segment, Segment::Text(_));
+    let mut parts: Vec<String> = segments
+        .iter()
+        .filter(|segment| is_text(segment))
+        .map(|segment| segment.value().to_string())
+        .collect();
+    let was_empty: Vec<bool> = parts.iter().map(String::is_empty).collect();
+    for filter in filters {
+        filter.apply_to_parts(&mut parts);
+    }
+
+    let mut parts = parts.into_iter().zip(was_empty);
+    segments.retain_mut(|segment| {
+        if !is_text(segment) {
+            return true;
+        }
+        let (part, was_empty) = parts.next().unwrap_or_default();
+        let emptied = part.is_empty() && !was_empty;
+        segment.map_text(|_| part);
+        !emptied
+    });
+}
+
+/// Returns the text of a variable for use in a condition, or an empty string if it isn't set
+fn variable_text(name: &str, variables: &VariableMapType) -> Result<String, StringFormatterError> {
+    match variables.get(name) {
+        Some(Some(Ok(value))) => Ok(match value {
+            VariableValue::Plain(text) | VariableValue::NoEscapingPlain(text) => text.to_string(),
+            VariableValue::Styled(segments) => segments.iter().map(Segment::value).collect(),
+            VariableValue::Meta(_) => String::new(),
+        }),
+        Some(Some(Err(error))) => Err(error.clone()),
+        _ => Ok(String::new()),
+    }
+}
+
 /// Escape interpretable characters for the shell prompt
 pub fn shell_prompt_escape<T>(text: T, shell: Shell) -> String
 where
@@ -760,6 +857,209 @@ mod tests {
         match_next!(result_iter, " ", None);
     }
 
+    fn if_mapper(variable: &str) -> Option<Result<&'static str, StringFormatterError>> {
+        match variable {
+            "status" => Some(Ok("1")),
+            "percentage" => Some(Ok("85%")),
+            "duration" => Some(Ok("1m3s")),
+            "branch" => Some(Ok("main")),
+            "empty" => Some(Ok("")),
+            _ => None,
+        }
+    }
+
+    fn render_if(format: &'static str) -> String {
+        StringFormatter::new(format)
+            .unwrap()
+            .map(if_mapper)
+            .parse(None, None)
+            .unwrap()
+            .iter()
+            .map(Segment::value)
+            .collect()
+    }
+
+    #[test]
+    fn test_if() {
+        assert_eq!(render_if("$if(status != 0)[✗ $status]"), "✗ 1");
+        assert_eq!(render_if("$if(status == 0)[✓]"), "");
+        assert_eq!(render_if("$if($status)[yes]$else[no]"), "yes");
+        assert_eq!(render_if("$if(empty)[yes]$else[no]"), "no");
+        assert_eq!(render_if("$if(!empty)[yes]$else[no]"), "yes");
+        assert_eq!(render_if("$if(unknown)[yes]$else[no]"), "no");
+        assert_eq!(render_if("a$if(status > 5)[b]c"), "ac");
+    }
+
+    #[test]
+    fn test_if_expressions() {
+        assert_eq!(render_if("$if(percentage >= 80)[high]"), "high");
+        assert_eq!(render_if("$if(percentage * 2 - 100 < 50)[low]"), "");
+        assert_eq!(
+            render_if("$if(duration > 1m && duration < 2m)[slow]"),
+            "slow"
+        );
+        assert_eq!(render_if("$if(duration > 10m || status == 1)[x]"), "x");
+        assert_eq!(render_if("$if( (status + 1) % 2 == 0 )[even]"), "even");
+        assert_eq!(render_if("$if(-status < 0)[negative]"), "negative");
+        assert_eq!(render_if("$if(branch == 'main')[on main]"), "on main");
+        assert_eq!(render_if(r#"$if(branch != "main")[elsewhere]"#), "");
+    }
+
+    #[test]
+    fn test_if_nested() {
+        const FORMAT_STR: &str =
+            "$if(status == 0)[ok]$else[$if(status == 1)[[warn](yellow)]$else[[$status](red)]]";
+
+        let formatter = StringFormatter::new(FORMAT_STR).unwrap().map(if_mapper);
+        let result = formatter.parse(None, None).unwrap();
+        let mut result_iter = result.iter();
+        match_next!(result_iter, "warn", Some(Color::Yellow.normal()));
+        assert!(result_iter.next().is_none());
+    }
+
+    #[test]
+    fn test_if_variable_holder() {
+        const FORMAT_STR: &str = "$if(a > b + 1)[$c]$else[[$d]($s)]";
+        let expected_variables = vec!["a", "b", "c", "d"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let expected_style_variables = vec!["s"].into_iter().map(String::from).collect();
+
+        let formatter = StringFormatter::new(FORMAT_STR).unwrap();
+        assert_eq!(formatter.get_variables(), expected_variables);
+        assert_eq!(formatter.get_style_variables(), expected_style_variables);
+    }
+
+    #[test]
+    fn test_if_parse_error() {
+        assert!(StringFormatter::new("$if(status ==)[x]").is_err());
+        assert!(StringFormatter::new("$if(status)[x").is_err());
+    }
+
+    #[test]
+    fn test_if_variable_error() {
+        const FORMAT_STR: &str = "$if(never > 1)[$some]";
+        let never_error = StringFormatterError::Custom("NEVER".to_owned());
+
+        let segments = StringFormatter::new(FORMAT_STR).and_then(|formatter| {
+            formatter
+                .map(|var| match var {
+                    "some" => Some(Ok("some")),
+                    "never" => Some(Err(never_error.clone())),
+                    _ => None,
+                })
+                .parse(None, None)
+        });
+        assert!(segments.is_err());
+    }
+
+    fn render_filters(format: &'static str) -> String {
+        StringFormatter::new(format)
+            .unwrap()
+            .map(|variable| match variable {
+                "branch" => Some(Ok("feature/very-long-branch-name")),
+                "version" => Some(Ok("v18.12.1")),
+                "path" => Some(Ok("~/src/starship")),
+                _ => None,
+            })
+            .parse(None, None)
+            .unwrap()
+            .iter()
+            .map(Segment::value)
+            .collect()
+    }
+
+    #[test]
+    fn test_filters() {
+        assert_eq!(render_filters("$branch|truncate(7)"), "feature…");
+        assert_eq!(render_filters("$branch|truncate(7, '')"), "feature");
+        assert_eq!(render_filters("$branch|truncate_start( 4 , '>' )"), ">name");
+        assert_eq!(render_filters("$version|major_minor"), "18.12");
+        assert_eq!(render_filters("$path|basename|upper"), "STARSHIP");
+        assert_eq!(render_filters("${path}|dirname"), "~/src");
+        assert_eq!(
+            render_filters("[$version|major](red) $version"),
+            "18 v18.12.1"
+        );
+    }
+
+    #[test]
+    fn test_filters_and_pipes() {
+        assert_eq!(
+            render_filters(r"$version\|$path|basename"),
+            "v18.12.1|starship"
+        );
+        assert_eq!(
+            render_filters("$version | $path|basename"),
+            "v18.12.1 | starship"
+        );
+        assert_eq!(render_filters("a|b"), "a|b");
+        assert_eq!(
+            render_filters("$path|host $version|uppercase"),
+            "~/src/starship|host v18.12.1|uppercase"
+        );
+    }
+
+    #[test]
+    fn test_filters_on_segments() {
+        const FORMAT_STR: &str = "$a|upper $all|truncate(2)";
+
+        let formatter = StringFormatter::new(FORMAT_STR)
+            .unwrap()
+            .map_meta(|var, _| match var {
+                "all" => Some("[$b](red)"),
+                _ => None,
+            })
+            .map_variables_to_segments(|variable| match variable {
+                "a" => Some(Ok(Segment::from_text(
+                    Some(Color::Green.normal().into()),
+                    "styled",
+                ))),
+                _ => None,
+            })
+            .map(|variable| match variable {
+                "b" => Some(Ok("text")),
+                _ => None,
+            });
+        let result = formatter.parse(None, None).unwrap();
+        let mut result_iter = result.iter();
+        match_next!(result_iter, "STYLED", Some(Color::Green.normal()));
+        match_next!(result_iter, " ", None);
+        match_next!(result_iter, "te…", Some(Color::Red.normal()));
+    }
+
+    #[test]
+    fn test_filters_across_segments() {
+        const FORMAT_STR: &str = "$module|truncate(5) $module|truncate_start(2)";
+
+        let formatter = StringFormatter::new(FORMAT_STR)
+            .unwrap()
+            .map_variables_to_segments(|variable| match variable {
+                "module" => Some(Ok([
+                    Segment::from_text(Some(Color::Green.normal().into()), "abc"),
+                    Segment::from_text(Some(Color::Red.normal().into()), "defg"),
+                ]
+                .concat())),
+                _ => None,
+            });
+        let result = formatter.parse(None, None).unwrap();
+        let mut result_iter = result.iter();
+        match_next!(result_iter, "abc", Some(Color::Green.normal()));
+        match_next!(result_iter, "de…", Some(Color::Red.normal()));
+        match_next!(result_iter, " ", None);
+        match_next!(result_iter, "…fg", Some(Color::Red.normal()));
+        assert!(result_iter.next().is_none());
+    }
+
+    #[test]
+    fn test_filter_errors() {
+        let error = StringFormatter::new("$branch|truncate").err().unwrap();
+        assert!(error.to_string().contains("`truncate` expects a length"));
+        assert!(StringFormatter::new("$branch|truncate('x')").is_err());
+        assert!(StringFormatter::new("$branch|upper(1)").is_err());
+    }
+
     #[test]
     fn test_variable_holder() {
         const FORMAT_STR: &str = "($a [($b) $c](none $s)) $d [t]($t)";
@@ -0,0 +1,164 @@
+use nu_ansi_term::Style as AnsiStyle;
+use serde::Serialize;
+
+use crate::config::color_to_string;
+use crate::module::Module;
+use crate::segment::Segment;
+
+/// A style with its `prev_fg`/`prev_bg` references resolved
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct JsonStyle {
+    fg: Option<String>,
+    bg: Option<String>,
+    bold: bool,
+    dimmed: bool,
+    italic: bool,
+    underline: bool,
+    blink: bool,
+    inverted: bool,
+    hidden: bool,
+    strikethrough: bool,
+}
+
+impl From<&AnsiStyle> for JsonStyle {
+    fn from(style: &AnsiStyle) -> Self {
+        Self {
+            fg: style.foreground.and_then(color_to_string),
+            bg: style.background.and_then(color_to_string),
+            bold: style.is_bold,
+            dimmed: style.is_dimmed,
+            italic: style.is_italic,
+            underline: style.is_underline,
+            blink: style.is_blink,
+            inverted: style.is_reverse,
+            hidden: style.is_hidden,
+            strikethrough: style.is_strikethrough,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JsonSegment {
+    Text {
+        text: String,
+        style: JsonStyle,
+    },
+    /// Fills the remaining width of the line by repeating `text`
+    Fill {
+        text: String,
+        style: JsonStyle,
+    },
+    LineBreak,
+}
+
+#[derive(Serialize, Debug)]
+pub struct JsonModule {
+    name: String,
+    description: String,
+    duration_ms: f64,
+    segments: Vec<JsonSegment>,
+}
+
+impl From<&Module<'_>> for JsonModule {
+    fn from(module: &Module) -> Self {
+        Self {
+            name: module.get_name().clone(),
+            description: module.get_description().clone(),
+            duration_ms: module.duration.as_secs_f64() * 1000.0,
+            segments: segments(&module.segments),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct JsonPrompt {
+    /// The segments of the whole prompt, including the text between modules
+    pub segments: Vec<JsonSegment>,
+    /// The modules in the prompt, in the order they are shown
+    pub modules: Vec<JsonModule>,
+}
+
+/// Converts segments, resolving styles the same way as they are printed as ANSI strings
+pub fn segments(segments: &[Segment]) -> Vec<JsonSegment> {
+    let mut prev_style: Option<AnsiStyle> = None;
+    segments
+        .iter()
+        .filter_map(|segment| {
+            let style = segment
+                .ansi_string(prev_style.as_ref())
+                .style_ref()
+                .to_owned();
+            let text = segment.value().to_string();
+            match segment {
+                Segment::Text(_) => {
+                    prev_style = Some(style);
+                    Some(JsonSegment::Text {
+                        text,
+                        style: (&style).into(),
+                    })
+                }
+                Segment::Fill(_) => {
+                    prev_style = None;
+                    Some(JsonSegment::Fill {
+                        text,
+                        style: (&style).into(),
+                    })
+                }
+                Segment::LineTerm => {
+                    prev_style = None;
+                    Some(JsonSegment::LineBreak)
+                }
+                Segment::GroupStart(_) | Segment::GroupEnd => None,
+            }
+        })
+        .collect()
+}
+
+/// Serializes a value for printing; the types in this module always serialize successfully
+pub fn to_string<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).expect("JSON output should serialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Style;
+    use nu_ansi_term::Color;
+
+    #[test]
+    fn resolves_previous_colors() {
+        let prev_bg = crate::config::parse_style_string("fg:prev_bg bg:green", None);
+        let mut input = Segment::from_text(Some(Color::Red.on(Color::Blue).into()), "a");
+        input.extend(Segment::from_text(prev_bg, "b"));
+        input.push(Segment::LineTerm);
+        input.push(Segment::fill(Some(Style::from(Color::Cyan.bold())), "."));
+
+        let expected = vec![
+            JsonSegment::Text {
+                text: "a".to_string(),
+                style: (&Color::Red.on(Color::Blue)).into(),
+            },
+            JsonSegment::Text {
+                text: "b".to_string(),
+                style: (&Color::Blue.on(Color::Green)).into(),
+            },
+            JsonSegment::LineBreak,
+            JsonSegment::Fill {
+                text: ".".to_string(),
+                style: (&Color::Cyan.bold()).into(),
+            },
+        ];
+        assert_eq!(segments(&input), expected);
+    }
+
+    #[test]
+    fn serializes_module() {
+        let mut module = Module::new("character", "The character module", None);
+        module.set_segments(Segment::from_text(Some(Color::Green.bold().into()), "❯"));
+
+        let actual = to_string(&JsonModule::from(&module));
+        let expected = r##"{"name":"character","description":"The character module","duration_ms":0.0,"segments":[{"type":"text","text":"❯","style":{"fg":"green","bg":null,"bold":true,"dimmed":false,"italic":false,"underline":false,"blink":false,"inverted":false,"hidden":false,"strikethrough":false}}]}"##;
+        assert_eq!(actual, expected);
+    }
+}
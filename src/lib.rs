@@ -7,19 +7,26 @@ shadow!(shadow);
 
 // Lib is present to allow for benchmarking
 pub mod bug_report;
+pub mod cache;
 pub mod config;
 pub mod configs;
 pub mod configure;
 pub mod context;
 pub mod context_env;
+#[cfg(unix)]
+pub mod daemon;
+mod deferred;
 pub mod formatter;
 pub mod init;
+mod json_output;
 pub mod logger;
 pub mod module;
 mod modules;
 pub mod print;
+pub mod project_config;
 mod segment;
 mod serde_utils;
+mod tmux;
 mod utils;
 
 #[cfg(test)]
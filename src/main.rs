@@ -10,7 +10,7 @@ use clap::{CommandFactory, Parser, Subcommand};
 use clap_complete::{generate, Shell as CompletionShell};
 use rand::distributions::Alphanumeric;
 use rand::Rng;
-use starship::context::{Context, Properties, Target};
+use starship::context::{Context, PromptArgs, Properties};
 use starship::module::ALL_MODULES;
 use starship::*;
 
@@ -32,6 +32,11 @@ struct Cli {
 enum Commands {
     /// Create a pre-populated GitHub issue with information about your configuration
     BugReport,
+    /// Inspect or clear data starship caches across prompts
+    Cache {
+        #[clap(subcommand)]
+        command: CacheCommands,
+    },
     /// Generate starship shell completions for your shell to stdout
     Completions {
         #[clap(value_enum)]
@@ -45,6 +50,13 @@ enum Commands {
         /// Value to place into that key
         value: Option<String>,
     },
+    #[cfg(unix)]
+    /// Keep a warm process for this shell session that renders prompts for `starship prompt`
+    Daemon {
+        /// Exit after this many seconds without a prompt request (0 to never exit)
+        #[clap(long, default_value_t = 3600)]
+        idle_timeout: u64,
+    },
     /// Explains the currently showing modules
     Explain(Properties),
     ///  Prints the shell function used to execute starship
@@ -61,6 +73,9 @@ enum Commands {
         /// List out all supported modules
         #[clap(short, long)]
         list: bool,
+        /// Compute the module for a prompt rendering it with `async = true`
+        #[clap(long, hide = true, requires = "name")]
+        async_refresh: bool,
         #[clap(flatten)]
         properties: Properties,
     },
@@ -85,19 +100,7 @@ enum Commands {
         name: Vec<String>,
     },
     /// Prints the full starship prompt
-    Prompt {
-        /// Print the right prompt (instead of the standard left prompt)
-        #[clap(long)]
-        right: bool,
-        /// Print the prompt with the specified profile name (instead of the standard left prompt)
-        #[clap(long, conflicts_with = "right")]
-        profile: Option<String>,
-        /// Print the continuation prompt (instead of the standard left prompt)
-        #[clap(long, conflicts_with = "right", conflicts_with = "profile")]
-        continuation: bool,
-        #[clap(flatten)]
-        properties: Properties,
-    },
+    Prompt(PromptArgs),
     /// Generate random session key
     Session,
     /// Prints time in milliseconds
@@ -113,11 +116,25 @@ enum Commands {
         #[clap(default_value = "disabled")]
         value: String,
     },
+    /// Allow the `.starship.toml` of the current project to overlay your config
+    Trust {
+        /// Stop trusting the project config instead
+        #[clap(long)]
+        revoke: bool,
+    },
     #[cfg(feature = "config-schema")]
     /// Generate a schema for the starship configuration as JSON-schema
     ConfigSchema,
 }
 
+#[derive(Subcommand, Debug)]
+enum CacheCommands {
+    /// Delete cached tool versions, untracked files and asynchronously rendered modules
+    Clear,
+    /// Prints the cached tool versions
+    Show,
+}
+
 fn main() {
     // Configure the current terminal on windows to support ANSI escape sequences.
     #[cfg(windows)]
@@ -176,23 +193,26 @@ fn main() {
                 init::init_stub(&shell).expect("can't init_stub");
             }
         }
-        Commands::Prompt {
-            properties,
-            right,
-            profile,
-            continuation,
-        } => {
-            let target = match (right, profile, continuation) {
-                (true, _, _) => Target::Right,
-                (_, Some(profile_name), _) => Target::Profile(profile_name),
-                (_, _, true) => Target::Continuation,
-                (_, _, _) => Target::Main,
-            };
-            print::prompt(properties, target);
+        Commands::Prompt(args) => {
+            #[cfg(unix)]
+            if let Some(prompt) = daemon::request_prompt() {
+                print!("{prompt}");
+                return;
+            }
+            let target = args.target();
+            print::prompt(args.properties, target);
+        }
+        #[cfg(unix)]
+        Commands::Daemon { idle_timeout } => {
+            if let Err(e) = daemon::run(std::time::Duration::from_secs(idle_timeout)) {
+                eprintln!("Unable to start daemon: {e}");
+                std::process::exit(1);
+            }
         }
         Commands::Module {
             name,
             list,
+            async_refresh,
             properties,
         } => {
             if list {
@@ -203,7 +223,11 @@ fn main() {
                 }
             }
             if let Some(module_name) = name {
-                print::module(&module_name, properties);
+                if async_refresh {
+                    print::refresh_async_module(&module_name, properties);
+                } else {
+                    print::module(&module_name, properties);
+                }
             }
         }
         Commands::Preset { name, list, output } => print::preset_command(name, output, list),
@@ -224,7 +248,32 @@ fn main() {
         Commands::Toggle { name, value } => {
             configure::toggle_configuration(&Context::default(), &name, &value);
         }
+        Commands::Trust { revoke } => {
+            let context = Context::default();
+            let result = if revoke {
+                project_config::revoke(&context)
+            } else {
+                project_config::trust(&context)
+            };
+            match result {
+                Ok(path) if revoke => println!("No longer trusting {}", path.display()),
+                Ok(path) => println!("Trusting the current content of {}", path.display()),
+                Err(e) => {
+                    eprintln!("Could not update trusted project configs: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
         Commands::BugReport => bug_report::create(),
+        Commands::Cache { command } => match command {
+            CacheCommands::Clear => {
+                if let Err(e) = cache::clear(&Context::default()) {
+                    eprintln!("Could not clear cache: {e}");
+                    std::process::exit(1);
+                }
+            }
+            CacheCommands::Show => cache::show(&Context::default()),
+        },
         Commands::Time => {
             match SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)
@@ -41,6 +41,7 @@ pub const ALL_MODULES: &[&str] = &[
     "git_metrics",
     "git_state",
     "git_status",
+    "git_worktree",
     "gleam",
     "golang",
     "gradle",
@@ -49,8 +50,11 @@ pub const ALL_MODULES: &[&str] = &[
     "haxe",
     "helm",
     "hg_branch",
+    "hg_status",
     "hostname",
     "java",
+    "jj_change",
+    "jj_status",
     "jobs",
     "julia",
     "kotlin",
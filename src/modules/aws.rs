@@ -1,16 +1,17 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::str::FromStr;
 
-use chrono::DateTime;
+use chrono::{DateTime, NaiveDateTime};
 use ini::Ini;
 use once_cell::unsync::OnceCell;
+use sha1::{Digest, Sha1};
 
 use super::{Context, Module, ModuleConfig};
 
 use crate::configs::aws::AwsConfig;
 use crate::formatter::StringFormatter;
-use crate::utils::render_time;
+use crate::utils::{read_file, render_time};
 
 type Profile = String;
 type Region = String;
@@ -82,6 +83,27 @@ fn get_profile_creds<'a>(
     }
 }
 
+// Get the sections of the given profile and of the profiles it assumes its role from,
+// following `source_profile`
+fn get_profile_chain<'a>(config: &'a Ini, profile: Option<&Profile>) -> Vec<&'a ini::Properties> {
+    let mut chain = Vec::new();
+    let mut visited = HashSet::new();
+    let mut profile = profile.cloned();
+    // A profile may use itself as its source profile, which ends the chain like any other cycle
+    while visited.insert(profile.clone()) {
+        let Some(section) = get_profile_config(config, profile.as_ref()) else {
+            break;
+        };
+        chain.push(section);
+        profile = match section.get("source_profile") {
+            Some("default") => None,
+            Some(source_profile) => Some(source_profile.to_string()),
+            None => break,
+        };
+    }
+    chain
+}
+
 fn get_aws_region_from_config(
     context: &Context,
     aws_profile: &Option<Profile>,
@@ -151,6 +173,62 @@ fn get_credentials_duration(
     Some(expiration_date.timestamp() - chrono::Local::now().timestamp())
 }
 
+// The AWS CLI caches SSO access tokens in a file named after the SHA-1 hash of the
+// `sso_session` name, or of the `sso_start_url` for legacy profiles
+fn get_sso_token_duration(
+    context: &Context,
+    aws_profile: Option<&Profile>,
+    aws_config: &AwsConfigFile,
+) -> Option<i64> {
+    let config = get_config(context, aws_config)?;
+    let cache_key = get_profile_chain(config, aws_profile)
+        .into_iter()
+        .find_map(|section| {
+            section
+                .get("sso_session")
+                .or_else(|| section.get("sso_start_url"))
+        })?;
+
+    let cache_file = context
+        .get_home()?
+        .join(".aws/sso/cache")
+        .join(format!("{:x}.json", Sha1::digest(cache_key.as_bytes())));
+    let token: serde_json::Value = serde_json::from_str(&read_file(cache_file).ok()?).ok()?;
+    let expires_at = token.get("expiresAt")?.as_str()?;
+
+    // Older versions of the AWS CLI write e.g. `2024-01-01T12:00:00UTC`
+    let expiration = DateTime::parse_from_rfc3339(expires_at)
+        .map(|date| date.timestamp())
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(expires_at, "%Y-%m-%dT%H:%M:%SUTC")
+                .map(|date| date.and_utc().timestamp())
+        })
+        .ok()?;
+
+    Some(expiration - chrono::Local::now().timestamp())
+}
+
+// Get the name of the role of the profile, from its `role_arn` or `sso_role_name`, or those
+// of the profiles it's chained from
+fn get_role(
+    context: &Context,
+    aws_profile: Option<&Profile>,
+    aws_config: &AwsConfigFile,
+) -> Option<String> {
+    let config = get_config(context, aws_config)?;
+    get_profile_chain(config, aws_profile)
+        .into_iter()
+        .find_map(|section| {
+            section
+                // e.g. `arn:aws:iam::123456789012:role/path/Admin`
+                .get("role_arn")
+                .and_then(|role_arn| role_arn.rsplit('/').next())
+                .or_else(|| section.get("sso_role_name"))
+                .filter(|role| !role.is_empty())
+        })
+        .map(String::from)
+}
+
 fn alias_name(name: Option<String>, aliases: &HashMap<String, &str>) -> Option<String> {
     name.as_ref()
         .and_then(|n| aliases.get(n))
@@ -258,15 +336,19 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     }
 
     let duration = {
-        get_credentials_duration(context, aws_profile.as_ref(), &aws_creds).map(|duration| {
-            if duration > 0 {
-                render_time((duration * 1000) as u128, false)
-            } else {
-                config.expiration_symbol.to_string()
-            }
-        })
+        get_credentials_duration(context, aws_profile.as_ref(), &aws_creds)
+            .or_else(|| get_sso_token_duration(context, aws_profile.as_ref(), &aws_config))
+            .map(|duration| {
+                if duration > 0 {
+                    render_time((duration * 1000) as u128, false)
+                } else {
+                    config.expiration_symbol.to_string()
+                }
+            })
     };
 
+    let role = get_role(context, aws_profile.as_ref(), &aws_config);
+
     let mapped_region = alias_name(aws_region, &config.region_aliases);
 
     let mapped_profile = alias_name(aws_profile, &config.profile_aliases);
@@ -285,6 +367,7 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                 "profile" => mapped_profile.as_ref().map(Ok),
                 "region" => mapped_region.as_ref().map(Ok),
                 "duration" => duration.as_ref().map(Ok),
+                "role" => role.as_ref().map(Ok),
                 _ => None,
             })
             .parse(None, Some(context))
@@ -1196,4 +1279,147 @@ source_profile = starship
         assert_eq!(expected, actual);
         dir.close()
     }
+
+    fn write_sso_cache(
+        home: &std::path::Path,
+        cache_key: &str,
+        expires_at: &str,
+    ) -> io::Result<()> {
+        use sha1::{Digest, Sha1};
+
+        let cache_dir = home.join(".aws/sso/cache");
+        std::fs::create_dir_all(&cache_dir)?;
+        std::fs::write(
+            cache_dir.join(format!("{:x}.json", Sha1::digest(cache_key.as_bytes()))),
+            format!(
+                r#"{{"startUrl": "https://starship.rs/sso", "accessToken": "dummy", "expiresAt": "{expires_at}"}}"#
+            ),
+        )
+    }
+
+    #[test]
+    fn sso_token_expiration() -> io::Result<()> {
+        use chrono::{DateTime, SecondsFormat, Utc};
+
+        let dir = tempfile::tempdir()?;
+        let config_path = dir.path().join("config");
+        std::fs::write(
+            &config_path,
+            "[profile astronauts]
+sso_session = my-sso
+sso_account_id = 123456789011
+sso_role_name = readOnly
+region = us-west-2
+
+[sso-session my-sso]
+sso_region = us-east-1
+sso_start_url = https://starship.rs/sso
+",
+        )?;
+        let now_plus_half_hour: DateTime<Utc> =
+            DateTime::from_timestamp(chrono::Local::now().timestamp() + 1800, 0).unwrap();
+        write_sso_cache(
+            dir.path(),
+            "my-sso",
+            &now_plus_half_hour.to_rfc3339_opts(SecondsFormat::Secs, true),
+        )?;
+
+        let actual = ModuleRenderer::new("aws")
+            .config(toml::toml! {
+                [aws]
+                format = "$profile $duration"
+            })
+            .env("HOME", dir.path().to_string_lossy().as_ref())
+            .env("AWS_CONFIG_FILE", config_path.to_string_lossy().as_ref())
+            .env("AWS_PROFILE", "astronauts")
+            .collect();
+
+        let possible_values = [
+            "30m2s", "30m1s", "30m0s", "29m59s", "29m58s", "29m57s", "29m56s", "29m55s",
+        ]
+        .map(|duration| Some(format!("astronauts {duration}")));
+        assert!(
+            possible_values.contains(&actual),
+            "time is not in range: {actual:?}"
+        );
+        dir.close()
+    }
+
+    #[test]
+    fn sso_legacy_token_expired_through_source_profile() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config_path = dir.path().join("config");
+        std::fs::write(
+            &config_path,
+            "[profile sso]
+sso_start_url = https://starship.rs/sso
+sso_region = us-east-1
+sso_account_id = 123456789011
+sso_role_name = readOnly
+
+[profile astronauts]
+role_arn = arn:aws:iam::123456789012:role/launch/Commander
+source_profile = sso
+",
+        )?;
+        // Written by AWS CLI v1
+        write_sso_cache(
+            dir.path(),
+            "https://starship.rs/sso",
+            "2020-01-01T00:00:00UTC",
+        )?;
+
+        let actual = ModuleRenderer::new("aws")
+            .config(toml::toml! {
+                [aws]
+                format = "$profile $role \\[$duration\\]"
+            })
+            .env("HOME", dir.path().to_string_lossy().as_ref())
+            .env("AWS_CONFIG_FILE", config_path.to_string_lossy().as_ref())
+            .env("AWS_PROFILE", "astronauts")
+            .collect();
+
+        assert_eq!(Some("astronauts Commander [X]".to_string()), actual);
+        dir.close()
+    }
+
+    #[test]
+    fn role_from_sso_role_name_and_source_profile_cycle() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config_path = dir.path().join("config");
+        std::fs::write(
+            &config_path,
+            "[default]
+sso_start_url = https://starship.rs/sso
+sso_role_name = readOnly
+
+[profile a]
+source_profile = b
+
+[profile b]
+source_profile = a
+",
+        )?;
+
+        let render = |profile: Option<&str>| {
+            let renderer = ModuleRenderer::new("aws")
+                .config(toml::toml! {
+                    [aws]
+                    format = "($role)"
+                    force_display = true
+                })
+                .env("HOME", dir.path().to_string_lossy().as_ref())
+                .env("AWS_CONFIG_FILE", config_path.to_string_lossy().as_ref())
+                .env("AWS_REGION", "us-east-1");
+            match profile {
+                Some(profile) => renderer.env("AWS_PROFILE", profile),
+                None => renderer,
+            }
+            .collect()
+        };
+
+        assert_eq!(Some("readOnly".to_string()), render(None));
+        assert_eq!(None, render(Some("a")));
+        dir.close()
+    }
 }
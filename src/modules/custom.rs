@@ -9,10 +9,7 @@ use process_control::{ChildExt, Control, Output};
 
 use super::{Context, Module, ModuleConfig};
 
-use crate::{
-    config::Either, configs::custom::CustomConfig, formatter::StringFormatter,
-    utils::create_command,
-};
+use crate::{config::Either, configs::custom::CustomConfig, formatter::StringFormatter};
 
 /// Creates a custom module with some configuration
 ///
@@ -152,7 +149,7 @@ fn shell_command(cmd: &str, config: &CustomConfig, context: &Context) -> Option<
     let (shell, shell_args) = get_shell(config.shell.0.as_ref(), context);
     let mut use_stdin = config.use_stdin;
 
-    let mut command = match create_command(shell.as_ref()) {
+    let mut command = match context.create_command(shell.as_ref()) {
         Ok(command) => command,
         // Don't attempt to use fallback shell if the user specified a shell
         Err(error) if !shell_args.is_empty() => {
@@ -165,11 +162,11 @@ fn shell_command(cmd: &str, config: &CustomConfig, context: &Context) -> Option<
             use_stdin = Some(!cfg!(windows));
 
             if cfg!(windows) {
-                let mut c = create_command("cmd").ok()?;
+                let mut c = context.create_command("cmd").ok()?;
                 c.arg("/C");
                 c
             } else {
-                let mut c = create_command("/usr/bin/env").ok()?;
+                let mut c = context.create_command("/usr/bin/env").ok()?;
                 c.arg("sh");
                 c
             }
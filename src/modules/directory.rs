@@ -209,7 +209,7 @@ fn is_readonly_dir(path: &Path) -> bool {
 ///
 /// Replaces the `top_level_path` in a given `full_path` with the provided
 /// `top_level_replacement`.
-fn contract_path<'a>(
+pub fn contract_path<'a>(
     full_path: &'a Path,
     top_level_path: &'a Path,
     top_level_replacement: &'a str,
@@ -1,8 +1,11 @@
-use unicode_segmentation::UnicodeSegmentation;
+use once_cell::sync::OnceCell;
 
+use super::utils::truncate::truncation_filter;
+use super::utils::{jj, revision};
 use super::{Context, Module, ModuleConfig};
 
 use crate::configs::git_branch::GitBranchConfig;
+use crate::context;
 use crate::formatter::StringFormatter;
 
 /// Creates a module with the Git branch in the current directory
@@ -12,8 +15,6 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     let mut module = context.new_module("git_branch");
     let config = GitBranchConfig::try_load(module.config);
 
-    let truncation_symbol = get_first_grapheme(config.truncation_symbol);
-
     let len = if config.truncation_length <= 0 {
         log::warn!(
             "\"truncation_length\" should be a positive value, found {}",
@@ -26,12 +27,15 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
 
     let repo = context.get_repo().ok()?;
 
+    if config.disabled_in_jj && jj::is_colocated(repo) {
+        return None;
+    }
+
     if config.only_attached && repo.open().head().ok()?.is_detached() {
         return None;
     }
 
     let branch_name = repo.branch.as_deref().unwrap_or("HEAD");
-    let mut graphemes: Vec<&str> = branch_name.graphemes(true).collect();
 
     if config
         .ignore_branches
@@ -41,34 +45,39 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
         return None;
     }
 
-    let mut remote_branch_graphemes: Vec<&str> = Vec::new();
-    let mut remote_name_graphemes: Vec<&str> = Vec::new();
-    if let Some(remote) = repo.remote.as_ref() {
-        if let Some(branch) = &remote.branch {
-            remote_branch_graphemes = branch.graphemes(true).collect()
-        };
-        if let Some(name) = &remote.name {
-            remote_name_graphemes = name.graphemes(true).collect()
-        };
-    }
-
     // Truncate fields if need be
-    for e in &mut [
-        &mut graphemes,
-        &mut remote_branch_graphemes,
-        &mut remote_name_graphemes,
-    ] {
-        let e = &mut **e;
-        let trunc_len = len.min(e.len());
-        if trunc_len < e.len() {
-            // The truncation symbol should only be added if we truncate
-            e[trunc_len] = truncation_symbol;
-            e.truncate(trunc_len + 1);
-        }
-    }
-
-    let show_remote = config.always_show_remote
-        || (!graphemes.eq(&remote_branch_graphemes) && !remote_branch_graphemes.is_empty());
+    let truncate = truncation_filter(len, config.truncation_symbol);
+    let branch = truncate.apply(branch_name);
+    let truncate_remote = |field: Option<&String>| field.map(|field| truncate.apply(field));
+    let remote_branch =
+        truncate_remote(repo.remote.as_ref().and_then(|r| r.branch.as_ref())).unwrap_or_default();
+    let remote_name_text =
+        truncate_remote(repo.remote.as_ref().and_then(|r| r.name.as_ref())).unwrap_or_default();
+
+    let show_remote =
+        config.always_show_remote || (branch != remote_branch && !remote_branch.is_empty());
+
+    let remote_name = repo
+        .remote
+        .as_ref()
+        .and_then(|remote| remote.name.as_deref());
+    let remote_repository = OnceCell::new();
+    let get_remote_repository = || {
+        remote_repository
+            .get_or_init(|| repo.remote.as_ref().and_then(context::Remote::repository))
+            .as_ref()
+    };
+    let style = remote_name
+        .and_then(|name| config.remote_styles.get(name))
+        .copied()
+        .unwrap_or(config.style);
+
+    let default_branch = OnceCell::new();
+    let get_default_branch = || {
+        default_branch
+            .get_or_init(|| get_default_branch(repo))
+            .as_ref()
+    };
 
     let parsed = StringFormatter::new(config.format).and_then(|formatter| {
         formatter
@@ -77,25 +86,38 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                 _ => None,
             })
             .map_style(|variable| match variable {
-                "style" => Some(Ok(config.style)),
+                "style" => Some(Ok(style)),
                 _ => None,
             })
             .map(|variable| match variable {
-                "branch" => Some(Ok(graphemes.concat())),
+                "branch" => Some(Ok(branch.clone())),
                 "remote_branch" => {
-                    if show_remote && !remote_branch_graphemes.is_empty() {
-                        Some(Ok(remote_branch_graphemes.concat()))
+                    if show_remote && !remote_branch.is_empty() {
+                        Some(Ok(remote_branch.clone()))
                     } else {
                         None
                     }
                 }
                 "remote_name" => {
-                    if show_remote && !remote_name_graphemes.is_empty() {
-                        Some(Ok(remote_name_graphemes.concat()))
+                    if show_remote && !remote_name_text.is_empty() {
+                        Some(Ok(remote_name_text.clone()))
                     } else {
                         None
                     }
                 }
+                "default_branch" => get_default_branch().map(|branch| Ok(branch.name.clone())),
+                "ahead_of_default" => get_default_branch()
+                    .filter(|branch| branch.ahead > 0)
+                    .map(|branch| Ok(branch.ahead.to_string())),
+                "behind_default" => get_default_branch()
+                    .filter(|branch| branch.behind > 0)
+                    .map(|branch| Ok(branch.behind.to_string())),
+                "gone" => is_upstream_gone(repo).then(|| Ok(config.gone.to_string())),
+                "remote_owner" => get_remote_repository().map(|remote| Ok(remote.owner.clone())),
+                "remote_repo" => get_remote_repository().map(|remote| Ok(remote.name.clone())),
+                "fork" => remote_name
+                    .filter(|&name| is_fork_remote(repo, name, config.upstream_remote))
+                    .map(|_| Ok(config.fork.to_string())),
                 _ => None,
             })
             .parse(None, Some(context))
@@ -112,10 +134,72 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     Some(module)
 }
 
-fn get_first_grapheme(text: &str) -> &str {
-    UnicodeSegmentation::graphemes(text, true)
-        .next()
-        .unwrap_or("")
+/// The default branch of the remote, as pointed to by its `HEAD`
+struct DefaultBranch {
+    name: String,
+    ahead: usize,
+    behind: usize,
+}
+
+/// Finds the default branch of the upstream remote, or `origin`, and counts the commits
+/// HEAD is ahead and behind of it
+fn get_default_branch(repo: &context::Repo) -> Option<DefaultBranch> {
+    let remote = repo
+        .remote
+        .as_ref()
+        .and_then(|remote| remote.name.as_deref())
+        .unwrap_or("origin");
+    let git_repo = repo.open();
+    let mut reference = git_repo
+        .try_find_reference(format!("refs/remotes/{remote}/HEAD").as_str())
+        .ok()??;
+    let name = match reference.target() {
+        gix::refs::TargetRef::Symbolic(target) => {
+            let target = target.shorten().to_string();
+            match target.strip_prefix(&format!("{remote}/")) {
+                Some(name) => name.to_string(),
+                None => target,
+            }
+        }
+        gix::refs::TargetRef::Peeled(_) => return None,
+    };
+    let default = reference.peel_to_id_in_place().ok()?.detach();
+    let head = git_repo.head_id().ok()?.detach();
+
+    let (ahead, behind) = revision::count_ahead_behind(&git_repo, head, default)
+        .map_err(|e| log::debug!("Unable to compare HEAD with {remote}/{name}: {e}"))
+        .ok()?;
+    Some(DefaultBranch {
+        name,
+        ahead,
+        behind,
+    })
+}
+
+/// Whether the current branch tracks an upstream branch that no longer exists,
+/// e.g. because it was deleted on the remote after being merged
+fn is_upstream_gone(repo: &context::Repo) -> bool {
+    let git_repo = repo.open();
+    let Ok(Some(head_name)) = git_repo.head_name() else {
+        return false;
+    };
+    match git_repo
+        .branch_remote_tracking_ref_name(head_name.as_ref(), gix::remote::Direction::Fetch)
+    {
+        Some(Ok(upstream)) => matches!(git_repo.try_find_reference(upstream.as_ref()), Ok(None)),
+        _ => false,
+    }
+}
+
+/// Whether the branch tracks a fork rather than the canonical repository, which is assumed
+/// when the repository also has a remote named like `upstream_remote`
+fn is_fork_remote(repo: &context::Repo, remote_name: &str, upstream_remote: &str) -> bool {
+    remote_name != upstream_remote
+        && repo
+            .open()
+            .remote_names()
+            .iter()
+            .any(|name| name.as_ref() == upstream_remote)
 }
 
 #[cfg(test)]
@@ -428,6 +512,268 @@ mod tests {
         remote_dir.close()
     }
 
+    #[test]
+    fn test_ahead_and_behind_default_branch() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+
+        for args in [
+            [
+                "symbolic-ref",
+                "refs/remotes/origin/HEAD",
+                "refs/remotes/origin/master",
+            ]
+            .as_slice(),
+            &["checkout", "-b", "feature", "@~1"],
+            &["commit", "--allow-empty", "-m", "First"],
+            &["commit", "--allow-empty", "-m", "Second"],
+        ] {
+            create_command("git")?
+                .args(args)
+                .current_dir(repo_dir.path())
+                .output()?;
+        }
+
+        let actual = ModuleRenderer::new("git_branch")
+            .path(repo_dir.path())
+            .config(toml::toml! {
+                [git_branch]
+                format = "$branch \\($default_branch ⇡$ahead_of_default ⇣$behind_default\\)"
+            })
+            .collect();
+
+        let expected = Some("feature (master ⇡2 ⇣1)");
+
+        assert_eq!(expected, actual.as_deref());
+        repo_dir.close()
+    }
+
+    #[test]
+    fn test_ahead_of_default_branch_in_the_same_second() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+
+        create_command("git")?
+            .args(["checkout", "-b", "feature"])
+            .current_dir(repo_dir.path())
+            .output()?;
+        for message in ["First", "Second", "Third", "Fourth"] {
+            create_command("git")?
+                .args(["commit", "--allow-empty", "-m", message])
+                .env("GIT_AUTHOR_DATE", "2022-01-01 00:00:00 +0000")
+                .env("GIT_COMMITTER_DATE", "2022-01-01 00:00:00 +0000")
+                .current_dir(repo_dir.path())
+                .output()?;
+        }
+        for args in [
+            ["update-ref", "refs/remotes/origin/master", "HEAD~1"].as_slice(),
+            &[
+                "symbolic-ref",
+                "refs/remotes/origin/HEAD",
+                "refs/remotes/origin/master",
+            ],
+        ] {
+            create_command("git")?
+                .args(args)
+                .current_dir(repo_dir.path())
+                .output()?;
+        }
+
+        let actual = ModuleRenderer::new("git_branch")
+            .path(repo_dir.path())
+            .config(toml::toml! {
+                [git_branch]
+                format = "$branch \\($default_branch ⇡$ahead_of_default( ⇣$behind_default)\\)"
+            })
+            .collect();
+
+        let expected = Some("feature (master ⇡1)");
+
+        assert_eq!(expected, actual.as_deref());
+        repo_dir.close()
+    }
+
+    #[test]
+    fn test_hides_counts_on_default_branch() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+
+        create_command("git")?
+            .args([
+                "symbolic-ref",
+                "refs/remotes/origin/HEAD",
+                "refs/remotes/origin/master",
+            ])
+            .current_dir(repo_dir.path())
+            .output()?;
+
+        let actual = ModuleRenderer::new("git_branch")
+            .path(repo_dir.path())
+            .config(toml::toml! {
+                [git_branch]
+                format = "$branch( ⇡$ahead_of_default)( ⇣$behind_default)"
+            })
+            .collect();
+
+        let expected = Some("master");
+
+        assert_eq!(expected, actual.as_deref());
+        repo_dir.close()
+    }
+
+    #[test]
+    fn test_gone_upstream() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+
+        for args in [
+            ["checkout", "-b", "test_branch"].as_slice(),
+            &["branch", "--set-upstream-to", "origin/master"],
+            &["update-ref", "-d", "refs/remotes/origin/master"],
+        ] {
+            create_command("git")?
+                .args(args)
+                .current_dir(repo_dir.path())
+                .output()?;
+        }
+
+        let actual = ModuleRenderer::new("git_branch")
+            .path(repo_dir.path())
+            .config(toml::toml! {
+                [git_branch]
+                format = "$branch( $gone)"
+            })
+            .collect();
+
+        let expected = Some("test_branch [gone]");
+
+        assert_eq!(expected, actual.as_deref());
+        repo_dir.close()
+    }
+
+    #[test]
+    fn test_upstream_not_gone() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+
+        let actual = ModuleRenderer::new("git_branch")
+            .path(repo_dir.path())
+            .config(toml::toml! {
+                [git_branch]
+                format = "$branch( $gone)"
+            })
+            .collect();
+
+        let expected = Some("master");
+
+        assert_eq!(expected, actual.as_deref());
+        repo_dir.close()
+    }
+
+    #[test]
+    fn test_remote_owner_and_repo() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+
+        create_command("git")?
+            .args([
+                "remote",
+                "set-url",
+                "origin",
+                "git@github.com:me/starship.git",
+            ])
+            .current_dir(repo_dir.path())
+            .output()?;
+
+        let actual = ModuleRenderer::new("git_branch")
+            .path(repo_dir.path())
+            .config(toml::toml! {
+                [git_branch]
+                format = "$branch@$remote_owner/$remote_repo( $fork)"
+            })
+            .collect();
+
+        let expected = Some("master@me/starship");
+
+        assert_eq!(expected, actual.as_deref());
+        repo_dir.close()
+    }
+
+    #[test]
+    fn test_fork_remote() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+
+        for args in [
+            [
+                "remote",
+                "set-url",
+                "origin",
+                "https://github.com/me/starship",
+            ]
+            .as_slice(),
+            &[
+                "remote",
+                "add",
+                "upstream",
+                "https://github.com/starship/starship",
+            ],
+        ] {
+            create_command("git")?
+                .args(args)
+                .current_dir(repo_dir.path())
+                .output()?;
+        }
+
+        let render = |upstream_remote: &str| {
+            ModuleRenderer::new("git_branch")
+                .path(repo_dir.path())
+                .config(toml::toml! {
+                    [git_branch]
+                    format = "$branch( $fork)"
+                    upstream_remote = upstream_remote
+                })
+                .collect()
+        };
+
+        assert_eq!(Some("master ⑂"), render("upstream").as_deref());
+        assert_eq!(Some("master"), render("origin").as_deref());
+        repo_dir.close()
+    }
+
+    #[test]
+    fn test_remote_styles() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+
+        let actual = ModuleRenderer::new("git_branch")
+            .path(repo_dir.path())
+            .config(toml::toml! {
+                [git_branch]
+                format = "[$branch]($style)"
+                remote_styles = { origin = "red", upstream = "blue" }
+            })
+            .collect();
+
+        let expected = Some(format!("{}", Color::Red.paint("master")));
+
+        assert_eq!(expected, actual);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn test_disabled_in_colocated_jj_repo() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+        std::fs::create_dir(repo_dir.path().join(".jj"))?;
+
+        let render = |disabled_in_jj: bool| {
+            ModuleRenderer::new("git_branch")
+                .path(repo_dir.path())
+                .config(toml::toml! {
+                    [git_branch]
+                    format = "$branch"
+                    disabled_in_jj = disabled_in_jj
+                })
+                .collect()
+        };
+
+        assert_eq!(Some("master"), render(false).as_deref());
+        assert_eq!(None, render(true));
+        repo_dir.close()
+    }
+
     #[test]
     fn test_branch_fallback_on_detached() -> io::Result<()> {
         let repo_dir = fixture_repo(FixtureProvider::Git)?;
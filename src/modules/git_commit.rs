@@ -1,3 +1,4 @@
+use super::utils::jj;
 use super::{Context, Module, ModuleConfig};
 use gix::commit::describe::SelectRef::AllTags;
 
@@ -13,6 +14,10 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     let config: GitCommitConfig = GitCommitConfig::try_load(module.config);
 
     let repo = context.get_repo().ok()?;
+
+    if config.disabled_in_jj && jj::is_colocated(repo) {
+        return None;
+    }
     let git_repo = repo.open();
     let git_head = git_repo.head().ok()?;
 
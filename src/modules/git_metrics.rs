@@ -1,11 +1,19 @@
+use gix::bstr::ByteSlice;
+use once_cell::sync::OnceCell;
 use regex::Regex;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::{Duration, Instant};
 
 use crate::{
     config::ModuleConfig, configs::git_metrics::GitMetricsConfig,
     formatter::string_formatter::StringFormatterError, formatter::StringFormatter, module::Module,
 };
 
+use super::utils::{fsmonitor, jj};
 use super::Context;
+use crate::context;
 
 /// Creates a module with the current added/deleted lines in the git repository at the
 /// current directory
@@ -20,14 +28,36 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     };
 
     let repo = context.get_repo().ok()?;
-    let mut git_args = vec!["diff", "--shortstat"];
-    if config.ignore_submodules {
-        git_args.push("--ignore-submodules");
-    }
 
-    let diff = repo.exec_git(context, &git_args)?.stdout;
+    if config.disabled_in_jj && jj::is_colocated(repo) {
+        return None;
+    }
 
-    let stats = GitDiff::parse(&diff);
+    // Each diff is only computed if the format uses one of its variables
+    let unstaged = OnceCell::new();
+    let staged = OnceCell::new();
+    let get_unstaged = || {
+        unstaged.get_or_init(|| {
+            let mut diff = get_unstaged_diff(context, repo, &config);
+            if config.include_untracked {
+                match get_untracked_diff(context, repo) {
+                    Some(untracked) => diff = diff.map(|diff| diff + untracked),
+                    None => log::debug!("Unable to list untracked files"),
+                }
+            }
+            diff
+        })
+    };
+    let get_staged = || {
+        staged.get_or_init(|| {
+            let mut git_args = vec!["diff", "--cached", "--shortstat"];
+            if config.ignore_submodules {
+                git_args.push("--ignore-submodules");
+            }
+            repo.exec_git(context, &git_args)
+                .map(|output| GitDiff::parse(&output.stdout))
+        })
+    };
 
     let parsed = StringFormatter::new(config.format).and_then(|formatter| {
         formatter
@@ -36,14 +66,26 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                 "deleted_style" => Some(Ok(config.deleted_style)),
                 _ => None,
             })
-            .map(|variable| match variable {
-                "added" => GitDiff::get_variable(config.only_nonzero_diffs, stats.added),
-                "deleted" => GitDiff::get_variable(config.only_nonzero_diffs, stats.deleted),
-                _ => None,
+            .map(|variable| {
+                let count = match variable {
+                    "added" => get_unstaged().as_ref()?.added,
+                    "deleted" => get_unstaged().as_ref()?.deleted,
+                    "files_changed" => get_unstaged().as_ref()?.files_changed,
+                    "staged_added" => get_staged().as_ref()?.added,
+                    "staged_deleted" => get_staged().as_ref()?.deleted,
+                    "staged_files_changed" => get_staged().as_ref()?.files_changed,
+                    _ => return None,
+                };
+                GitDiff::get_variable(config.only_nonzero_diffs, count)
             })
             .parse(None, Some(context))
     });
 
+    // Like before the diffs were split, nothing is shown if a diff the format uses failed
+    if matches!(unstaged.get(), Some(None)) || matches!(staged.get(), Some(None)) {
+        return None;
+    }
+
     module.set_segments(match parsed {
         Ok(segments) => segments,
         Err(error) => {
@@ -55,43 +97,170 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     Some(module)
 }
 
+/// Gets the changes in the working directory that haven't been staged yet
+fn get_unstaged_diff(
+    context: &Context,
+    repo: &context::Repo,
+    config: &GitMetricsConfig,
+) -> Option<GitDiff> {
+    let mut git_args = vec!["diff".to_string(), "--shortstat".to_string()];
+    if config.ignore_submodules {
+        git_args.push("--ignore-submodules".to_string());
+    }
+
+    // Only the files the filesystem monitor reports can differ from the index
+    let changed_paths = if config.fsmonitor {
+        fsmonitor::changed_paths(context, repo)
+    } else {
+        None
+    };
+    match changed_paths {
+        Some(paths) if paths.is_empty() => Some(GitDiff::default()),
+        paths => {
+            if let Some(paths) = paths {
+                git_args.push("--".to_string());
+                git_args.extend(
+                    paths
+                        .iter()
+                        .map(|path| format!(":(literal){}", path.to_str_lossy())),
+                );
+            }
+            let diff = repo.exec_git(context, &git_args)?.stdout;
+            Some(GitDiff::parse(&diff))
+        }
+    }
+}
+
+/// Untracked files larger than this are counted as changed without counting their lines
+const MAX_UNTRACKED_SIZE: u64 = 1024 * 1024;
+
+/// Git treats a file as binary if its first 8000 bytes contain a NUL byte
+const BINARY_CHECK_SIZE: usize = 8000;
+
+/// Counts the lines of untracked files as added, skipping binary files like git does.
+/// Once `command_timeout` has passed, the remaining files are counted without their lines.
+fn get_untracked_diff(context: &Context, repo: &context::Repo) -> Option<GitDiff> {
+    let deadline = Instant::now() + Duration::from_millis(context.root_config.command_timeout);
+    let workdir = repo.workdir.as_ref()?;
+    let files = repo.exec_git(
+        context,
+        &[
+            "ls-files",
+            "--others",
+            "--exclude-standard",
+            "--full-name",
+            "-z",
+            "--",
+            ":/",
+        ],
+    )?;
+
+    let mut diff = GitDiff::default();
+    for file in files.stdout.split('\0').filter(|file| !file.is_empty()) {
+        let path = workdir.join(file);
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        diff.files_changed += 1;
+        if metadata.len() > MAX_UNTRACKED_SIZE {
+            log::debug!("Not counting the lines of large untracked file {path:?}");
+            continue;
+        }
+        if Instant::now() > deadline {
+            log::debug!("Counting the lines of untracked files timed out at {path:?}");
+            continue;
+        }
+        match count_lines(&path) {
+            Ok(Some(lines)) => diff.added += lines,
+            Ok(None) => {}
+            Err(e) => log::debug!("Unable to count the lines of {path:?}: {e}"),
+        }
+    }
+    Some(diff)
+}
+
+/// Counts the lines of a file without reading it into memory, or returns `None` for binary files
+fn count_lines(path: &Path) -> io::Result<Option<usize>> {
+    let mut file = File::open(path)?;
+    let mut buf = [0; BINARY_CHECK_SIZE];
+    let mut read = 0;
+    let mut lines = 0;
+    let mut last = b'\n';
+    loop {
+        let n = match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let chunk = &buf[..n];
+        if read < BINARY_CHECK_SIZE && chunk[..n.min(BINARY_CHECK_SIZE - read)].contains(&0) {
+            return Ok(None);
+        }
+        read += n;
+        lines += chunk.iter().filter(|&&byte| byte == b'\n').count();
+        last = chunk[n - 1];
+    }
+    // A last line without a newline counts as well
+    if last != b'\n' {
+        lines += 1;
+    }
+    Ok(Some(lines))
+}
+
 /// Represents the parsed output from a git diff.
-struct GitDiff<'a> {
-    added: &'a str,
-    deleted: &'a str,
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct GitDiff {
+    added: usize,
+    deleted: usize,
+    files_changed: usize,
 }
 
-impl<'a> GitDiff<'a> {
+impl GitDiff {
     /// Returns the first capture group given a regular expression and a string.
-    /// If it fails to get the capture group it will return "0".
-    fn get_matched_str(diff: &'a str, re: &Regex) -> &'a str {
+    /// If it fails to get the capture group it will return 0.
+    fn get_matched_count(diff: &str, re: &Regex) -> usize {
         match re.captures(diff) {
-            Some(caps) => caps.get(1).unwrap().as_str(),
-            _ => "0",
+            Some(caps) => caps.get(1).unwrap().as_str().parse().unwrap_or_default(),
+            _ => 0,
         }
     }
 
     /// Parses the result of 'git diff --shortstat' as a `GitDiff` struct.
-    pub fn parse(diff: &'a str) -> Self {
+    pub fn parse(diff: &str) -> Self {
         let added_re = Regex::new(r"(\d+) \w+\(\+\)").unwrap();
         let deleted_re = Regex::new(r"(\d+) \w+\(\-\)").unwrap();
+        let files_re = Regex::new(r"(\d+) files? changed").unwrap();
 
         Self {
-            added: GitDiff::get_matched_str(diff, &added_re),
-            deleted: GitDiff::get_matched_str(diff, &deleted_re),
+            added: GitDiff::get_matched_count(diff, &added_re),
+            deleted: GitDiff::get_matched_count(diff, &deleted_re),
+            files_changed: GitDiff::get_matched_count(diff, &files_re),
         }
     }
 
     pub fn get_variable(
         only_nonzero_diffs: bool,
-        changed: &str,
-    ) -> Option<Result<&str, StringFormatterError>> {
+        changed: usize,
+    ) -> Option<Result<String, StringFormatterError>> {
         match only_nonzero_diffs {
             true => match changed {
-                "0" => None,
-                _ => Some(Ok(changed)),
+                0 => None,
+                _ => Some(Ok(changed.to_string())),
             },
-            false => Some(Ok(changed)),
+            false => Some(Ok(changed.to_string())),
+        }
+    }
+}
+
+impl std::ops::Add for GitDiff {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            added: self.added + other.added,
+            deleted: self.deleted + other.deleted,
+            files_changed: self.files_changed + other.files_changed,
         }
     }
 }
@@ -100,13 +269,14 @@ impl<'a> GitDiff<'a> {
 mod tests {
     use crate::utils::{create_command, write_file};
     use std::ffi::OsStr;
-    use std::fs::OpenOptions;
+    use std::fs::{self, OpenOptions};
     use std::io::{self, Error, ErrorKind, Write};
     use std::path::Path;
     use std::process::Stdio;
 
     use nu_ansi_term::Color;
 
+    use super::MAX_UNTRACKED_SIZE;
     use crate::test::ModuleRenderer;
 
     #[test]
@@ -122,6 +292,31 @@ mod tests {
         repo_dir.close()
     }
 
+    #[test]
+    fn shows_nothing_if_diff_fails() -> io::Result<()> {
+        let repo_dir = create_repo_with_commit()?;
+        let path = repo_dir.path();
+
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(path.join("the_file"))?;
+        writeln!(file, "Added line")?;
+        file.sync_all()?;
+        fs::write(path.join(".git").join("index"), "corrupt")?;
+
+        let actual = ModuleRenderer::new("git_metrics")
+            .config(toml::toml! {
+                [git_metrics]
+                disabled = false
+                format = "changed: $added"
+            })
+            .path(path)
+            .collect();
+
+        assert_eq!(None, actual);
+        repo_dir.close()
+    }
+
     #[test]
     fn shows_added_lines() -> io::Result<()> {
         let repo_dir = create_repo_with_commit()?;
@@ -244,6 +439,115 @@ mod tests {
         repo_dir.close()
     }
 
+    #[test]
+    fn shows_staged_and_unstaged_changes() -> io::Result<()> {
+        let repo_dir = create_repo_with_commit()?;
+        let path = repo_dir.path();
+
+        write_file(path.join("the_file"), "First Line\nSecond Line\n")?;
+        run_git_cmd(["add", "the_file"], Some(path), true)?;
+        write_file(
+            path.join("the_file"),
+            "First Line\nSecond Line\nAdded\nAdded\n",
+        )?;
+
+        let actual = ModuleRenderer::new("git_metrics")
+            .config(toml::toml! {
+                [git_metrics]
+                disabled = false
+                format = "$staged_files_changed ($staged_added )$staged_deleted|$files_changed $added ($deleted)"
+            })
+            .path(path)
+            .collect();
+
+        let expected = Some(String::from("1 1|1 2 "));
+
+        assert_eq!(expected, actual);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn counts_untracked_lines() -> io::Result<()> {
+        let repo_dir = create_repo_with_commit()?;
+        let path = repo_dir.path();
+
+        std::fs::create_dir(path.join("subdir"))?;
+        write_file(path.join("new_file"), "One\nTwo\nThree")?;
+        write_file(path.join("subdir").join("binary"), "\0\n\n")?;
+        write_file(path.join("empty"), "")?;
+        write_file(
+            path.join("large"),
+            "\n".repeat(MAX_UNTRACKED_SIZE as usize + 1),
+        )?;
+        write_file(
+            path.join("the_file"),
+            "First Line\nSecond Line\nThird Line\nAdded\n",
+        )?;
+
+        let render = |include_untracked: bool| {
+            ModuleRenderer::new("git_metrics")
+                .config(toml::toml! {
+                    [git_metrics]
+                    disabled = false
+                    include_untracked = include_untracked
+                    format = "$files_changed $added"
+                })
+                .path(path.join("subdir"))
+                .collect()
+        };
+
+        assert_eq!(render(false), Some(String::from("1 1")));
+        assert_eq!(render(true), Some(String::from("5 4")));
+        repo_dir.close()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn only_diffs_paths_reported_by_fsmonitor() -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let repo_dir = create_repo_with_commit()?;
+        let path = repo_dir.path();
+
+        write_file(path.join("other_file"), "First Line\n")?;
+        run_git_cmd(["add", "other_file"], Some(path), true)?;
+        run_git_cmd(
+            ["commit", "--message", "Commit B", "--no-gpg-sign"],
+            Some(path),
+            true,
+        )?;
+
+        let hook = path.join(".git").join("fsmonitor-hook");
+        write_file(&hook, "#!/bin/sh\nprintf 'token\\0the_file\\0'\n")?;
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o700))?;
+        run_git_cmd(
+            ["config", "core.fsmonitor", hook.to_str().unwrap()],
+            Some(path),
+            true,
+        )?;
+        run_git_cmd(["status"], Some(path), true)?;
+
+        write_file(
+            path.join("the_file"),
+            "First Line\nSecond Line\nThird Line\nAdded\n",
+        )?;
+        write_file(path.join("other_file"), "Modified\n")?;
+
+        let actual = ModuleRenderer::new("git_metrics")
+            .config(toml::toml! {
+                [git_metrics]
+                disabled = false
+                fsmonitor = true
+            })
+            .path(path)
+            .collect();
+
+        let expected = Some(format!("{} ", Color::Green.bold().paint("+1")));
+
+        assert_eq!(expected, actual);
+        repo_dir.close()
+    }
+
     fn render_metrics(path: &Path) -> Option<String> {
         ModuleRenderer::new("git_metrics")
             .config(toml::toml! {
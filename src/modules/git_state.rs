@@ -1,6 +1,9 @@
+use gix::prelude::ObjectIdExt;
 use gix::state::InProgress;
+use once_cell::sync::OnceCell;
 use std::path::PathBuf;
 
+use super::utils::jj;
 use super::{Context, Module, ModuleConfig};
 use crate::configs::git_state::GitStateConfig;
 use crate::context::Repo;
@@ -9,14 +12,22 @@ use crate::formatter::StringFormatter;
 /// Creates a module with the state of the git repository at the current directory
 ///
 /// During a git operation it will show: REBASING, BISECTING, MERGING, etc.
-/// If the progress information is available (e.g. rebasing 3/10), it will show that too.
+/// If the progress information is available (e.g. rebasing 3/10), it will show that too,
+/// as well as what is being rebased onto, the subject of the commit being applied and the
+/// number of commits marked during a bisect.
 pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     let mut module = context.new_module("git_state");
     let config: GitStateConfig = GitStateConfig::try_load(module.config);
 
     let repo = context.get_repo().ok()?;
 
+    if config.disabled_in_jj && jj::is_colocated(repo) {
+        return None;
+    }
+
     let state_description = get_state_description(repo, &config)?;
+    let bisect_counts = OnceCell::new();
+    let bisect_counts = || bisect_counts.get_or_init(|| get_bisect_counts(repo));
 
     let parsed = StringFormatter::new(config.format).and_then(|formatter| {
         formatter
@@ -29,8 +40,12 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                 _ => None,
             })
             .map(|variable| match variable {
-                "progress_current" => state_description.current.as_ref().map(Ok),
-                "progress_total" => state_description.total.as_ref().map(Ok),
+                "progress_current" => state_description.current.clone().map(Ok),
+                "progress_total" => state_description.total.clone().map(Ok),
+                "onto" => get_rebase_onto(repo).map(Ok),
+                "subject" => get_subject(repo).map(Ok),
+                "bisect_good" => format_count(bisect_counts().good).map(Ok),
+                "bisect_bad" => format_count(bisect_counts().bad).map(Ok),
                 _ => None,
             })
             .parse(None, Some(context))
@@ -155,6 +170,129 @@ struct StateDescription<'a> {
     total: Option<String>,
 }
 
+/// Reads a file of the repository's git directory, e.g. `rebase-merge/onto`
+fn read_state_file(repo: &Repo, relative_path: &str) -> Option<String> {
+    crate::utils::read_file(repo.path.join(relative_path)).ok()
+}
+
+/// Returns the branch the current rebase is onto, or its abbreviated commit id if no branch
+/// points at it
+fn get_rebase_onto(repo: &Repo) -> Option<String> {
+    let onto = read_state_file(repo, "rebase-merge/onto")
+        .or_else(|| read_state_file(repo, "rebase-apply/onto"))?;
+    let id = gix::ObjectId::from_hex(onto.trim().as_bytes()).ok()?;
+    let git_repo = repo.open();
+
+    let branch = git_repo.references().ok().and_then(|references| {
+        let local = references.local_branches().ok()?;
+        let remote = references.remote_branches().ok()?;
+        local.chain(remote).flatten().find_map(|mut reference| {
+            let name = reference.name().shorten().to_string();
+            // Skip symbolic refs like `origin/HEAD`, they duplicate the branch they point to
+            let is_symbolic = reference.target().try_id().is_none();
+            (!is_symbolic && reference.peel_to_id_in_place().ok()? == id).then_some(name)
+        })
+    });
+    Some(branch.unwrap_or_else(|| id.attach(&git_repo).shorten_or_id().to_string()))
+}
+
+/// Returns the subject of the commit being applied or, during a merge, of the merge commit
+fn get_subject(repo: &Repo) -> Option<String> {
+    match repo.state.as_ref()? {
+        InProgress::Merge => first_line(&read_state_file(repo, "MERGE_MSG")?),
+        InProgress::CherryPick | InProgress::CherryPickSequence => {
+            commit_subject(repo, read_state_file(repo, "CHERRY_PICK_HEAD")?.trim())
+        }
+        InProgress::Revert | InProgress::RevertSequence => {
+            commit_subject(repo, read_state_file(repo, "REVERT_HEAD")?.trim())
+        }
+        InProgress::Bisect => None,
+        InProgress::Rebase
+        | InProgress::RebaseInteractive
+        | InProgress::ApplyMailbox
+        | InProgress::ApplyMailboxRebase => get_rebase_subject(repo),
+    }
+}
+
+fn get_rebase_subject(repo: &Repo) -> Option<String> {
+    if let Some(done) = read_state_file(repo, "rebase-merge/done") {
+        // The last line of the todo list that has been worked on is the current one, e.g.
+        // `pick 1a2b3c4 Subject of the commit` or `fixup -C 1a2b3c4 Subject`
+        let line = done
+            .lines()
+            .map(str::trim)
+            .rfind(|line| !line.is_empty() && !line.starts_with('#'))?;
+        let mut words = line.split(' ').filter(|word| !word.is_empty()).skip(1);
+        let rev = words.find(|word| !word.starts_with('-'))?;
+        return commit_subject(repo, rev).or_else(|| {
+            let subject = words.collect::<Vec<_>>().join(" ");
+            (!subject.is_empty()).then_some(subject)
+        });
+    }
+
+    // `git am` and the apply backend of `git rebase` split the current patch into `info`
+    let info = read_state_file(repo, "rebase-apply/info")?;
+    info.lines()
+        .find_map(|line| line.strip_prefix("Subject: "))
+        .and_then(first_line)
+}
+
+fn commit_subject(repo: &Repo, rev: &str) -> Option<String> {
+    let git_repo = repo.open();
+    let commit = git_repo
+        .rev_parse_single(rev)
+        .ok()?
+        .object()
+        .ok()?
+        .try_into_commit()
+        .ok()?;
+    let subject = commit.message().ok()?.summary().to_string();
+    (!subject.is_empty()).then_some(subject)
+}
+
+fn first_line(text: &str) -> Option<String> {
+    let line = text.lines().next()?.trim();
+    (!line.is_empty()).then(|| line.to_string())
+}
+
+#[derive(Default)]
+struct BisectCounts {
+    good: usize,
+    bad: usize,
+}
+
+/// Counts the commits marked as good and bad in `BISECT_LOG`, which has a line like
+/// `# good: [<id>] <subject>` for every marked commit
+fn get_bisect_counts(repo: &Repo) -> BisectCounts {
+    let Some(log) = read_state_file(repo, "BISECT_LOG") else {
+        return BisectCounts::default();
+    };
+    // Custom terms (`git bisect start --term-old fixed --term-new broken`) replace good and bad
+    let terms = read_state_file(repo, "BISECT_TERMS").unwrap_or_default();
+    let mut terms = terms.lines().map(str::trim);
+    let bad_term = terms
+        .next()
+        .filter(|term| !term.is_empty())
+        .unwrap_or("bad");
+    let good_term = terms
+        .next()
+        .filter(|term| !term.is_empty())
+        .unwrap_or("good");
+
+    let count = |term: &str| {
+        let prefix = format!("# {term}: [");
+        log.lines().filter(|line| line.starts_with(&prefix)).count()
+    };
+    BisectCounts {
+        good: count(good_term),
+        bad: count(bad_term),
+    }
+}
+
+fn format_count(count: usize) -> Option<String> {
+    (count > 0).then(|| count.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use nu_ansi_term::Color;
@@ -258,6 +396,157 @@ mod tests {
         repo_dir.close()
     }
 
+    #[test]
+    fn shows_rebase_onto_and_subject() -> io::Result<()> {
+        let repo_dir = create_repo_with_conflict()?;
+        let path = repo_dir.path();
+
+        run_git_cmd(["rebase", "other-branch"], Some(path), false)?;
+
+        let actual = ModuleRenderer::new("git_state")
+            .config(toml::toml! {
+                [git_state]
+                format = "$state onto $onto: $subject"
+            })
+            .path(path)
+            .collect();
+
+        let expected = Some(String::from("REBASING onto other-branch: Commit C"));
+
+        assert_eq!(expected, actual);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn shows_rebase_onto_commit_without_branch() -> io::Result<()> {
+        let repo_dir = create_repo_with_conflict()?;
+        let path = repo_dir.path();
+
+        run_git_cmd(
+            ["branch", "--delete", "--force", "other-branch"],
+            Some(path),
+            true,
+        )?;
+        run_git_cmd(["rebase", "HEAD@{2}"], Some(path), false)?;
+
+        let onto = std::fs::read_to_string(path.join(".git/rebase-merge/onto"))?;
+        let actual = ModuleRenderer::new("git_state")
+            .config(toml::toml! {
+                [git_state]
+                format = "$onto"
+            })
+            .path(path)
+            .collect();
+
+        let expected = Some(onto[..7].to_string());
+
+        assert_eq!(expected, actual);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn shows_merge_subject() -> io::Result<()> {
+        let repo_dir = create_repo_with_conflict()?;
+        let path = repo_dir.path();
+
+        run_git_cmd(["merge", "other-branch"], Some(path), false)?;
+
+        let actual = ModuleRenderer::new("git_state")
+            .config(toml::toml! {
+                [git_state]
+                format = "$subject"
+            })
+            .path(path)
+            .collect();
+
+        let expected = Some(String::from("Merge branch 'other-branch'"));
+
+        assert_eq!(expected, actual);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn shows_cherry_pick_subject() -> io::Result<()> {
+        let repo_dir = create_repo_with_conflict()?;
+        let path = repo_dir.path();
+
+        run_git_cmd(["cherry-pick", "other-branch"], Some(path), false)?;
+
+        let actual = ModuleRenderer::new("git_state")
+            .config(toml::toml! {
+                [git_state]
+                format = "$state $subject"
+            })
+            .path(path)
+            .collect();
+
+        let expected = Some(String::from("CHERRY-PICKING Commit B"));
+
+        assert_eq!(expected, actual);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn shows_bisect_counts() -> io::Result<()> {
+        let repo_dir = create_repo_with_conflict()?;
+        let path = repo_dir.path();
+
+        let format = "$state( good:$bisect_good)( bad:$bisect_bad)";
+        let render = || {
+            ModuleRenderer::new("git_state")
+                .config(toml::toml! {
+                    [git_state]
+                    format = format
+                })
+                .path(path)
+                .collect()
+        };
+
+        run_git_cmd(["bisect", "start"], Some(path), true)?;
+        assert_eq!(Some(String::from("BISECTING")), render());
+
+        run_git_cmd(["bisect", "bad"], Some(path), true)?;
+        assert_eq!(Some(String::from("BISECTING bad:1")), render());
+
+        run_git_cmd(["bisect", "good", "HEAD~1"], Some(path), true)?;
+        assert_eq!(Some(String::from("BISECTING good:1 bad:1")), render());
+
+        repo_dir.close()
+    }
+
+    #[test]
+    fn shows_bisect_counts_with_custom_terms() -> io::Result<()> {
+        let repo_dir = create_repo_with_conflict()?;
+        let path = repo_dir.path();
+
+        run_git_cmd(
+            [
+                "bisect",
+                "start",
+                "--term-old",
+                "fixed",
+                "--term-new",
+                "broken",
+            ],
+            Some(path),
+            true,
+        )?;
+        run_git_cmd(["bisect", "fixed", "HEAD~1"], Some(path), true)?;
+
+        let actual = ModuleRenderer::new("git_state")
+            .config(toml::toml! {
+                [git_state]
+                format = "$state( good:$bisect_good)( bad:$bisect_bad)"
+            })
+            .path(path)
+            .collect();
+
+        let expected = Some(String::from("BISECTING good:1"));
+
+        assert_eq!(expected, actual);
+        repo_dir.close()
+    }
+
     fn run_git_cmd<A, S>(args: A, dir: Option<&Path>, should_succeed: bool) -> io::Result<()>
     where
         A: IntoIterator<Item = S>,
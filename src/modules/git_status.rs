@@ -1,13 +1,26 @@
+use gix::bstr::{BStr, BString, ByteSlice};
+use gix::dir::walk::EmissionMode;
+use gix::status::index_worktree::BuiltinSubmoduleStatus;
+use gix::status::plumbing::index_as_worktree::traits::FastEq;
+use gix::status::plumbing::index_as_worktree::{Change, EntryStatus};
+use gix::status::plumbing::index_as_worktree_with_renames::{Entry, VisitEntry};
+use gix::status::Submodule;
+use gix::submodule::config::Ignore;
 use once_cell::sync::OnceCell;
 use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
+use super::utils::{fsmonitor, jj, revision};
 use super::{Context, Module, ModuleConfig};
 
+use crate::cache;
 use crate::configs::git_status::GitStatusConfig;
 use crate::context;
 use crate::formatter::StringFormatter;
 use crate::segment::Segment;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
 
 const ALL_STATUS_FORMAT: &str =
     "$conflicted$stashed$deleted$renamed$modified$typechanged$staged$untracked";
@@ -34,6 +47,10 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     // Return None if not in git repository
     let repo = context.get_repo().ok()?;
 
+    if config.disabled_in_jj && jj::is_colocated(repo) {
+        return None;
+    }
+
     if repo.kind.is_bare() {
         log::debug!("This is a bare repository, git_status is not applicable");
         return None;
@@ -107,6 +124,40 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                     "typechanged" => info.get_typechanged().and_then(|count| {
                         format_count(config.typechanged, "git_status.typechanged", context, count)
                     }),
+                    "categories" => info.get_categories().and_then(|counts| {
+                        let categories: Vec<Vec<Segment>> = config
+                            .categories
+                            .keys()
+                            .zip(counts)
+                            .filter(|(_, count)| **count > 0)
+                            .filter_map(|(name, count)| {
+                                format_text(
+                                    config.category_format,
+                                    "git_status.category_format",
+                                    context,
+                                    |variable| match variable {
+                                        "name" => Some(name.clone()),
+                                        "count" => Some(count.to_string()),
+                                        _ => None,
+                                    },
+                                )
+                            })
+                            .collect();
+                        let separator = Segment::from_text(None, config.category_separator);
+                        let segments = categories.join(separator.as_slice());
+                        (!segments.is_empty()).then_some(segments)
+                    }),
+                    "status_partial" => info.is_partial().and_then(|partial| {
+                        if partial {
+                            format_symbol(
+                                config.status_partial,
+                                "git_status.status_partial",
+                                context,
+                            )
+                        } else {
+                            None
+                        }
+                    }),
                     _ => None,
                 };
                 segments.map(Ok)
@@ -155,7 +206,9 @@ impl<'a> GitStatusInfo<'a> {
     }
 
     pub fn get_ahead_behind(&self) -> Option<(Option<usize>, Option<usize>)> {
-        self.get_repo_status().map(|data| (data.ahead, data.behind))
+        self.get_repo_status()
+            .as_ref()
+            .map(|data| (data.ahead, data.behind))
     }
 
     pub fn get_repo_status(&self) -> &Option<RepoStatus> {
@@ -182,31 +235,41 @@ impl<'a> GitStatusInfo<'a> {
     }
 
     pub fn get_conflicted(&self) -> Option<usize> {
-        self.get_repo_status().map(|data| data.conflicted)
+        self.get_repo_status().as_ref().map(|data| data.conflicted)
     }
 
     pub fn get_deleted(&self) -> Option<usize> {
-        self.get_repo_status().map(|data| data.deleted)
+        self.get_repo_status().as_ref().map(|data| data.deleted)
     }
 
     pub fn get_renamed(&self) -> Option<usize> {
-        self.get_repo_status().map(|data| data.renamed)
+        self.get_repo_status().as_ref().map(|data| data.renamed)
     }
 
     pub fn get_modified(&self) -> Option<usize> {
-        self.get_repo_status().map(|data| data.modified)
+        self.get_repo_status().as_ref().map(|data| data.modified)
     }
 
     pub fn get_staged(&self) -> Option<usize> {
-        self.get_repo_status().map(|data| data.staged)
+        self.get_repo_status().as_ref().map(|data| data.staged)
     }
 
     pub fn get_untracked(&self) -> Option<usize> {
-        self.get_repo_status().map(|data| data.untracked)
+        self.get_repo_status().as_ref().map(|data| data.untracked)
     }
 
     pub fn get_typechanged(&self) -> Option<usize> {
-        self.get_repo_status().map(|data| data.typechanged)
+        self.get_repo_status().as_ref().map(|data| data.typechanged)
+    }
+
+    pub fn get_categories(&self) -> Option<&[usize]> {
+        self.get_repo_status()
+            .as_ref()
+            .map(|data| data.categories.as_slice())
+    }
+
+    pub fn is_partial(&self) -> Option<bool> {
+        self.get_repo_status().as_ref().map(|data| data.partial)
     }
 }
 
@@ -218,8 +281,23 @@ fn get_repo_status(
 ) -> Option<RepoStatus> {
     log::debug!("New repo status created");
 
+    match get_repo_status_gix(context, repo, config) {
+        Ok(repo_status) => Some(repo_status),
+        Err(error) => {
+            log::debug!("Unable to get the repo status with gix, falling back to git: {error}");
+            get_repo_status_git(context, repo, config)
+        }
+    }
+}
+
+/// Gets the repo status from `git status`
+fn get_repo_status_git(
+    context: &Context,
+    repo: &context::Repo,
+    config: &GitStatusConfig,
+) -> Option<RepoStatus> {
     let mut repo_status = RepoStatus::default();
-    let mut args = vec!["status", "--porcelain=2"];
+    let mut args = vec!["status", "--porcelain=2", "-z"];
 
     // for performance reasons, only pass flags if necessary...
     let has_ahead_behind = !config.ahead.is_empty() || !config.behind.is_empty();
@@ -240,19 +318,491 @@ fn get_repo_status(
     }
 
     let status_output = repo.exec_git(context, &args)?;
-    let statuses = status_output.stdout.lines();
+    let mut statuses = status_output.stdout.split('\0');
 
-    statuses.for_each(|status| {
+    while let Some(status) = statuses.next() {
         if status.starts_with("# branch.ab ") {
             repo_status.set_ahead_behind(status);
-        } else if !status.starts_with('#') {
-            repo_status.add(status);
+        } else if !status.is_empty() && !status.starts_with('#') {
+            repo_status.add(status, config);
+            // Renamed entries are followed by the original path
+            if status.starts_with('2') {
+                statuses.next();
+            }
         }
-    });
+    }
 
     Some(repo_status)
 }
 
+/// Gets the repo status in-process, counting files the same way as `git status --porcelain=2`
+fn get_repo_status_gix(
+    context: &Context,
+    repo: &context::Repo,
+    config: &GitStatusConfig,
+) -> Result<RepoStatus, Box<dyn std::error::Error>> {
+    let git_repo = repo.open();
+    let mut repo_status = RepoStatus::default();
+
+    let has_ahead_behind = !config.ahead.is_empty() || !config.behind.is_empty();
+    let has_up_to_date_diverged = !config.up_to_date.is_empty() || !config.diverged.is_empty();
+    if has_ahead_behind || has_up_to_date_diverged {
+        if let Some((ahead, behind)) = get_ahead_behind(&git_repo)? {
+            repo_status.ahead = Some(ahead);
+            repo_status.behind = Some(behind);
+        }
+    }
+
+    let index = git_repo.index_or_empty()?;
+    let mut conflicted = BTreeSet::new();
+    let mut index_entries = BTreeMap::new();
+    for entry in index.entries() {
+        let path = entry.path(&index);
+        if entry.stage() == gix::index::entry::Stage::Unconflicted {
+            index_entries.insert(path, entry);
+        } else {
+            conflicted.insert(path.to_owned());
+        }
+    }
+
+    // Changes between HEAD and the index, like the `X` in `git status --porcelain=2`.
+    // Directories whose tree is still cached in the index and equal to HEAD are skipped.
+    let mut head_entries = BTreeMap::new();
+    let mut unchanged_dirs = HashSet::new();
+    match git_repo.head_tree_id() {
+        Ok(tree) => collect_head_entries(
+            &git_repo,
+            tree.detach(),
+            index.tree(),
+            BString::default(),
+            &mut head_entries,
+            &mut unchanged_dirs,
+        )?,
+        Err(_) if git_repo.head()?.is_unborn() => {}
+        Err(error) => return Err(error.into()),
+    }
+
+    let mut staged: BTreeMap<BString, char> = BTreeMap::new();
+    for (path, entry) in &index_entries {
+        if conflicted.contains(*path) || is_in_dirs(path, &unchanged_dirs) {
+            continue;
+        }
+        let change = match head_entries.get(*path) {
+            Some(head) if head.id == entry.id && head.mode == entry.mode => continue,
+            Some(head) if entry_kind(head.mode) != entry_kind(entry.mode) => 'T',
+            Some(_) => 'M',
+            // `git add --intent-to-add` only shows up in the worktree status
+            None if entry
+                .flags
+                .contains(gix::index::entry::Flags::INTENT_TO_ADD) =>
+            {
+                continue
+            }
+            None => 'A',
+        };
+        staged.insert((*path).to_owned(), change);
+    }
+
+    // Like git, a deleted and an added file with the same content are a rename.
+    // Only exact renames are detected, a renamed file that was also changed is deleted and added.
+    let detect_renames = git_repo
+        .config_snapshot()
+        .boolean("status.renames")
+        .or_else(|| git_repo.config_snapshot().boolean("diff.renames"))
+        .unwrap_or(true);
+    let mut added_by_id: HashMap<gix::ObjectId, Vec<BString>> = HashMap::new();
+    if detect_renames {
+        // In reverse, so the first added path is matched first
+        for (path, _) in staged.iter().rev().filter(|(_, change)| **change == 'A') {
+            let id = index_entries[path.as_bstr()].id;
+            added_by_id.entry(id).or_default().push(path.clone());
+        }
+    }
+    for (path, entry) in &head_entries {
+        if index_entries.contains_key(path.as_bstr()) || conflicted.contains(path) {
+            continue;
+        }
+        match added_by_id.get_mut(&entry.id).and_then(Vec::pop) {
+            Some(renamed_to) => {
+                staged.insert(renamed_to, 'R');
+            }
+            None => {
+                staged.insert(path.clone(), 'D');
+            }
+        }
+    }
+
+    // Changes between the index and the worktree, like the `Y` in `git status --porcelain=2`
+    let has_untracked = !config.untracked.is_empty();
+    let ignore_submodules = if config.ignore_submodules {
+        Some(Ignore::Dirty)
+    } else if !has_untracked {
+        Some(Ignore::Untracked)
+    } else {
+        None
+    };
+    let submodules = match ignore_submodules {
+        Some(ignore) => Submodule::Given {
+            ignore,
+            check_dirty: true,
+        },
+        None => Submodule::AsConfigured { check_dirty: true },
+    };
+
+    // Only the files the filesystem monitor reports can differ from the index
+    let changed_paths = if config.fsmonitor {
+        fsmonitor::changed_paths(context, repo)
+    } else {
+        None
+    };
+    let interrupt = Arc::new(AtomicBool::new(false));
+    let _deadline = interrupt_after(
+        Duration::from_millis(context.root_config.command_timeout),
+        Arc::clone(&interrupt),
+    );
+
+    // Untracked files aren't reported by the monitor if they already existed, so the whole
+    // worktree is walked for them once, and after that only where the monitor reports changes
+    let untracked_mode = if has_untracked {
+        untracked_files_mode(&git_repo)
+    } else {
+        None
+    };
+    let dirwalk_options = match untracked_mode {
+        Some(mode) if changed_paths.is_none() => {
+            Some(git_repo.dirwalk_options()?.emit_untracked(mode))
+        }
+        _ => None,
+    };
+
+    let mut worktree = WorktreeChanges::default();
+    if !matches!(&changed_paths, Some(paths) if paths.is_empty()) {
+        let patterns = changed_paths
+            .iter()
+            .flatten()
+            .map(|path| literal_pattern(path.as_ref()));
+        let status = git_repo.index_worktree_status(
+            &index,
+            patterns,
+            &mut worktree,
+            FastEq,
+            BuiltinSubmoduleStatus::new(git_repo.clone().into_sync(), submodules)?,
+            &mut gix::progress::Discard,
+            &interrupt,
+            gix::status::index_worktree::Options {
+                sorting: None,
+                dirwalk_options,
+                rewrites: None,
+                thread_limit: None,
+            },
+        );
+        match status {
+            Ok(_) => {}
+            Err(_) if interrupt.load(Ordering::Relaxed) => {
+                log::debug!("git_status timed out, showing the changes found so far");
+                repo_status.partial = true;
+            }
+            Err(error) => return Err(error.into()),
+        }
+    }
+    conflicted.extend(worktree.conflicted);
+
+    if let (Some(mode), Some(changed), false) =
+        (untracked_mode, &changed_paths, repo_status.partial)
+    {
+        // A changed `.gitignore` can change whether any file is ignored
+        let key = fsmonitor::untracked_key(&git_repo, &index, mode)
+            .filter(|_| !changed.iter().any(|path| path.ends_with(b".gitignore")));
+        let cached = key
+            .as_deref()
+            .zip(repo.workdir.as_deref())
+            .and_then(|(key, workdir)| cache::untracked_files(context, workdir, key));
+        let mut patterns = None;
+        if let Some(cached) = cached {
+            let (stale, kept): (Vec<_>, Vec<_>) = cached.into_iter().partition(|path| {
+                changed
+                    .iter()
+                    .any(|changed| overlaps(path.as_ref(), changed.as_ref()))
+            });
+            worktree.untracked = kept;
+            patterns = Some(
+                changed
+                    .iter()
+                    .chain(&stale)
+                    .map(|path| untracked_pattern(&index, path.as_ref(), mode))
+                    .collect::<Vec<_>>(),
+            );
+        }
+        if !matches!(&patterns, Some(patterns) if patterns.is_empty()) {
+            let interrupted = walk_untracked(
+                &git_repo,
+                &index,
+                mode,
+                patterns.into_iter().flatten(),
+                &interrupt,
+                &mut worktree.untracked,
+            )?;
+            repo_status.partial |= interrupted;
+        }
+        if let (Some(key), Some(workdir), false) =
+            (key, repo.workdir.as_deref(), repo_status.partial)
+        {
+            worktree.untracked.sort();
+            worktree.untracked.dedup();
+            cache::store_untracked_files(context, workdir, &key, &worktree.untracked);
+        }
+    }
+
+    for path in &conflicted {
+        repo_status.add_file('u', "UU", &path.to_str_lossy(), config);
+    }
+    let paths: BTreeSet<&BString> = staged.keys().chain(worktree.changes.keys()).collect();
+    for path in paths {
+        if conflicted.contains(path) {
+            continue;
+        }
+        let x = staged.get(path).copied().unwrap_or('.');
+        let y = worktree.changes.get(path).copied().unwrap_or('.');
+        let kind = if x == 'R' { '2' } else { '1' };
+        repo_status.add_file(kind, &format!("{x}{y}"), &path.to_str_lossy(), config);
+    }
+    for path in &worktree.untracked {
+        repo_status.add_file('?', "??", &path.to_str_lossy(), config);
+    }
+
+    Ok(repo_status)
+}
+
+/// Adds the untracked files matching `patterns` to `untracked`, or all of them if there are
+/// no patterns. Returns whether the walk was interrupted before it was done.
+fn walk_untracked(
+    repo: &gix::Repository,
+    index: &gix::worktree::Index,
+    mode: EmissionMode,
+    patterns: impl IntoIterator<Item = BString>,
+    interrupt: &Arc<AtomicBool>,
+    untracked: &mut Vec<BString>,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let options = repo.dirwalk_options()?.emit_untracked(mode);
+    let entries = repo.dirwalk_iter(
+        index.clone(),
+        patterns,
+        Arc::clone(interrupt).into(),
+        options,
+    )?;
+    for item in entries {
+        match item {
+            Ok(item) if item.entry.status == gix::dir::entry::Status::Untracked => {
+                untracked.push(item.entry.rela_path);
+            }
+            Ok(_) => {}
+            Err(_) if interrupt.load(Ordering::Relaxed) => {
+                log::debug!("git_status timed out looking for untracked files");
+                return Ok(true);
+            }
+            Err(error) => return Err(error.into()),
+        }
+    }
+    Ok(false)
+}
+
+/// Matches `path` exactly, without treating any of its characters as wildcards
+fn literal_pattern(path: &BStr) -> BString {
+    let mut pattern = BString::from(":(literal)");
+    pattern.extend_from_slice(path);
+    pattern
+}
+
+/// Matches the untracked files at `path`. Untracked directories are collapsed unless all
+/// untracked files are shown, so this matches the topmost directory of `path` without
+/// tracked files, if there is one.
+fn untracked_pattern(index: &gix::index::File, path: &BStr, mode: EmissionMode) -> BString {
+    if matches!(mode, EmissionMode::CollapseDirectory) {
+        for end in path.find_iter("/") {
+            let dir = path[..=end].as_bstr();
+            if index.prefixed_entries(dir).map_or(true, <[_]>::is_empty) {
+                return literal_pattern(dir);
+            }
+        }
+    }
+    literal_pattern(path)
+}
+
+/// Whether one of the paths is the other or inside it, ignoring trailing slashes of directories
+fn overlaps(a: &BStr, b: &BStr) -> bool {
+    let a = a.strip_suffix(b"/").unwrap_or(a);
+    let b = b.strip_suffix(b"/").unwrap_or(b);
+    let (shorter, longer) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    longer.starts_with(shorter) && (longer.len() == shorter.len() || longer[shorter.len()] == b'/')
+}
+
+/// Collects the changes between the index and the worktree, like the `Y` in
+/// `git status --porcelain=2`
+#[derive(Default)]
+struct WorktreeChanges {
+    changes: BTreeMap<BString, char>,
+    conflicted: Vec<BString>,
+    untracked: Vec<BString>,
+}
+
+impl<'index> VisitEntry<'index> for WorktreeChanges {
+    type ContentChange = ();
+    type SubmoduleStatus = gix::submodule::Status;
+
+    fn visit_entry(&mut self, entry: Entry<'index, (), gix::submodule::Status>) {
+        match entry {
+            Entry::Modification {
+                rela_path, status, ..
+            } => {
+                let change = match status {
+                    EntryStatus::Conflict(_) => {
+                        self.conflicted.push(rela_path.to_owned());
+                        return;
+                    }
+                    EntryStatus::Change(Change::Removed) => 'D',
+                    EntryStatus::Change(Change::Type) => 'T',
+                    EntryStatus::Change(
+                        Change::Modification { .. } | Change::SubmoduleModification(_),
+                    ) => 'M',
+                    EntryStatus::IntentToAdd => 'A',
+                    EntryStatus::NeedsUpdate(_) => return,
+                };
+                self.changes.insert(rela_path.to_owned(), change);
+            }
+            Entry::DirectoryContents { entry, .. }
+                if entry.status == gix::dir::entry::Status::Untracked =>
+            {
+                self.untracked.push(entry.rela_path);
+            }
+            Entry::DirectoryContents { .. } | Entry::Rewrite { .. } => {}
+        }
+    }
+}
+
+/// Returns how untracked files are listed according to `status.showUntrackedFiles`
+fn untracked_files_mode(repo: &gix::Repository) -> Option<EmissionMode> {
+    match repo
+        .config_snapshot()
+        .string("status.showUntrackedFiles")
+        .as_deref()
+        .and_then(|value| value.to_str().ok())
+    {
+        Some("no") => None,
+        Some("all") => Some(EmissionMode::Matching),
+        _ => Some(EmissionMode::CollapseDirectory),
+    }
+}
+
+/// Sets `interrupt` once `timeout` has passed, unless the returned sender is dropped first
+fn interrupt_after(timeout: Duration, interrupt: Arc<AtomicBool>) -> mpsc::Sender<()> {
+    let (sender, receiver) = mpsc::channel::<()>();
+    std::thread::spawn(move || {
+        if let Err(mpsc::RecvTimeoutError::Timeout) = receiver.recv_timeout(timeout) {
+            interrupt.store(true, Ordering::Relaxed);
+        }
+    });
+    sender
+}
+
+/// Regular files, executable or not, have the same kind
+/// A file in the tree of HEAD
+struct HeadEntry {
+    id: gix::ObjectId,
+    mode: gix::index::entry::Mode,
+}
+
+/// Collects the files of a tree, except in directories whose tree in the cache of the index
+/// has the same id, as their entries in the index are the same as in the tree. Like git, only
+/// the directories that changed since the cache was written are read.
+fn collect_head_entries(
+    repo: &gix::Repository,
+    tree_id: gix::ObjectId,
+    cache: Option<&gix::index::extension::Tree>,
+    dir: BString,
+    entries: &mut BTreeMap<BString, HeadEntry>,
+    unchanged_dirs: &mut HashSet<BString>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if cache.is_some_and(|cache| cache.num_entries.is_some() && cache.id == tree_id) {
+        unchanged_dirs.insert(dir);
+        return Ok(());
+    }
+
+    let tree = repo.find_object(tree_id)?.try_into_tree()?;
+    for entry in tree.decode()?.entries {
+        let mut path = dir.clone();
+        if !path.is_empty() {
+            path.push(b'/');
+        }
+        path.extend_from_slice(entry.filename);
+        if entry.mode.is_tree() {
+            let cache = cache.and_then(|cache| {
+                cache
+                    .children
+                    .iter()
+                    .find(|child| child.name.as_slice() == entry.filename.as_bytes())
+            });
+            collect_head_entries(
+                repo,
+                entry.oid.to_owned(),
+                cache,
+                path,
+                entries,
+                unchanged_dirs,
+            )?;
+        } else {
+            let entry = HeadEntry {
+                id: entry.oid.to_owned(),
+                mode: entry.mode.into(),
+            };
+            entries.insert(path, entry);
+        }
+    }
+    Ok(())
+}
+
+/// Whether the path is in one of the directories, where the root is the empty path
+fn is_in_dirs(path: &BStr, dirs: &HashSet<BString>) -> bool {
+    !dirs.is_empty()
+        && (dirs.contains(b"".as_bstr())
+            || path
+                .iter()
+                .enumerate()
+                .any(|(i, byte)| *byte == b'/' && dirs.contains(path[..i].as_bstr())))
+}
+
+fn entry_kind(mode: gix::index::entry::Mode) -> u8 {
+    if mode.is_submodule() {
+        2
+    } else if mode == gix::index::entry::Mode::SYMLINK {
+        1
+    } else {
+        0
+    }
+}
+
+/// Counts the commits the current branch is ahead and behind of its upstream branch,
+/// or returns `None` if it doesn't track an upstream branch that exists
+fn get_ahead_behind(
+    repo: &gix::Repository,
+) -> Result<Option<(usize, usize)>, Box<dyn std::error::Error>> {
+    let Some(head_name) = repo.head_name()? else {
+        return Ok(None);
+    };
+    let Some(upstream) =
+        repo.branch_remote_tracking_ref_name(head_name.as_ref(), gix::remote::Direction::Fetch)
+    else {
+        return Ok(None);
+    };
+    let Some(mut upstream) = repo.try_find_reference(upstream?.as_ref())? else {
+        return Ok(None);
+    };
+    let Ok(head) = repo.head_id() else {
+        return Ok(None);
+    };
+    let upstream = upstream.peel_to_id_in_place()?;
+    revision::count_ahead_behind(repo, head.detach(), upstream.detach()).map(Some)
+}
+
 fn get_stashed_count(repo: &context::Repo) -> Option<usize> {
     let repo = repo.open();
     let reference = match repo.try_find_reference("refs/stash") {
@@ -279,7 +829,7 @@ fn get_stashed_count(repo: &context::Repo) -> Option<usize> {
     }
 }
 
-#[derive(Default, Debug, Copy, Clone)]
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
 struct RepoStatus {
     ahead: Option<usize>,
     behind: Option<usize>,
@@ -290,6 +840,9 @@ struct RepoStatus {
     staged: usize,
     typechanged: usize,
     untracked: usize,
+    partial: bool,
+    /// The number of changed files matching each of the configured `categories`
+    categories: Vec<usize>,
 }
 
 impl RepoStatus {
@@ -332,21 +885,65 @@ impl RepoStatus {
         }
     }
 
-    fn add(&mut self, s: &str) {
-        match s.chars().next() {
-            Some('1') => self.parse_normal_status(&s[2..4]),
-            Some('2') => {
+    /// Counts a file with the type of its `git status --porcelain=2` entry, unless the file
+    /// is ignored with `ignore_paths`
+    fn add_file(&mut self, kind: char, short_status: &str, path: &str, config: &GitStatusConfig) {
+        // Untracked directories end with a slash
+        let path = path.trim_end_matches('/');
+        if config
+            .ignore_paths
+            .iter()
+            .any(|pattern| matches_pathspec(pattern, path))
+        {
+            return;
+        }
+
+        match kind {
+            '1' => self.parse_normal_status(short_status),
+            '2' => {
                 self.renamed += 1;
-                self.parse_normal_status(&s[2..4])
+                self.parse_normal_status(short_status)
+            }
+            'u' => self.conflicted += 1,
+            '?' => self.untracked += 1,
+            _ => return,
+        }
+
+        self.categories.resize(config.categories.len(), 0);
+        for (count, patterns) in self.categories.iter_mut().zip(config.categories.values()) {
+            if patterns
+                .0
+                .iter()
+                .any(|pattern| matches_pathspec(pattern, path))
+            {
+                *count += 1;
             }
-            Some('u') => self.conflicted += 1,
-            Some('?') => self.untracked += 1,
-            Some('!') => (),
-            Some(_) => log::error!("Unknown line type in git status output"),
-            None => log::error!("Missing line type in git status output"),
         }
     }
 
+    fn add(&mut self, s: &str, config: &GitStatusConfig) {
+        // The number of fields before the path
+        let fields = match s.chars().next() {
+            Some('1') => 8,
+            Some('2') => 9,
+            Some('u') => 10,
+            Some('?') => 1,
+            Some('!') => return,
+            Some(_) => return log::error!("Unknown line type in git status output"),
+            None => return log::error!("Missing line type in git status output"),
+        };
+        let Some(path) = s.splitn(fields + 1, ' ').nth(fields) else {
+            return log::error!("Missing path in git status output");
+        };
+        let short_status = s.get(2..4).unwrap_or_default();
+        self.add_file(
+            s.chars().next().unwrap_or_default(),
+            short_status,
+            path,
+            config,
+        );
+    }
+
     fn set_ahead_behind(&mut self, s: &str) {
         let re = Regex::new(r"branch\.ab \+([0-9]+) \-([0-9]+)").unwrap();
 
@@ -357,6 +954,19 @@ impl RepoStatus {
     }
 }
 
+/// Matches a path relative to the root of the repository like a git pathspec,
+/// either as a leading directory or as a glob where `*` also matches `/`
+fn matches_pathspec(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.trim_end_matches('/');
+    path.strip_prefix(pattern)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        || gix::glob::wildmatch(
+            pattern.into(),
+            path.into(),
+            gix::glob::wildmatch::Mode::empty(),
+        )
+}
+
 fn format_text<F>(
     format_str: &str,
     config_path: &str,
@@ -404,9 +1014,7 @@ fn format_symbol(format_str: &str, config_path: &str, context: &Context) -> Opti
 
 #[cfg(target_os = "linux")]
 fn git_status_wsl(context: &Context, conf: &GitStatusConfig) -> Option<String> {
-    use crate::utils::create_command;
     use nix::sys::utsname::uname;
-    use std::env;
     use std::ffi::OsString;
     use std::io::ErrorKind;
 
@@ -426,7 +1034,8 @@ fn git_status_wsl(context: &Context, conf: &GitStatusConfig) -> Option<String> {
     log::trace!("Using WSL mode");
 
     // Get Windows path
-    let wslpath = create_command("wslpath")
+    let wslpath = context
+        .create_command("wslpath")
         .map(|mut c| {
             c.arg("-w").arg(&context.current_dir);
             c
@@ -467,12 +1076,13 @@ fn git_status_wsl(context: &Context, conf: &GitStatusConfig) -> Option<String> {
 
     // Get foreign starship to use WSL config
     // https://devblogs.microsoft.com/commandline/share-environment-vars-between-wsl-and-windows/
-    let wslenv = env::var("WSLENV").map_or_else(
-        |_| "STARSHIP_CONFIG/wp".to_string(),
+    let wslenv = context.get_env("WSLENV").map_or_else(
+        || "STARSHIP_CONFIG/wp".to_string(),
         |e| e + ":STARSHIP_CONFIG/wp",
     );
 
-    let exe = create_command(starship_exe)
+    let exe = context
+        .create_command(starship_exe)
         .map(|mut c| {
             c.env(
                 "STARSHIP_CONFIG",
@@ -521,6 +1131,7 @@ mod tests {
     use std::io::{self, prelude::*};
     use std::path::Path;
 
+    use super::*;
     use crate::test::{fixture_repo, FixtureProvider, ModuleRenderer};
     use crate::utils::create_command;
 
@@ -785,6 +1396,99 @@ mod tests {
         repo_dir.close()
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn only_checks_paths_reported_by_fsmonitor() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+
+        create_fsmonitor_hook(repo_dir.path(), "readme.md")?;
+        fs::write(repo_dir.path().join("readme.md"), "modified")?;
+        fs::write(repo_dir.path().join("Cargo.toml"), "modified")?;
+
+        let render = |fsmonitor: bool| {
+            ModuleRenderer::new("git_status")
+                .config(toml::toml! {
+                    [git_status]
+                    modified = "!$count"
+                    untracked = ""
+                    fsmonitor = fsmonitor
+                })
+                .path(repo_dir.path())
+                .collect()
+        };
+        assert_eq!(render(false), format_output("!2"));
+        assert_eq!(render(true), format_output("!1"));
+        repo_dir.close()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn only_looks_for_untracked_files_where_fsmonitor_reports_changes() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+        let cache_dir = tempfile::tempdir()?;
+
+        create_untracked(repo_dir.path())?;
+        create_fsmonitor_hook(repo_dir.path(), "readme.md")?;
+        let hook = repo_dir.path().join(".git").join("fsmonitor-hook");
+        let render = || {
+            ModuleRenderer::new("git_status")
+                .config(toml::toml! {
+                    [git_status]
+                    untracked = "?$count"
+                    fsmonitor = true
+                })
+                .path(repo_dir.path())
+                .env("STARSHIP_CACHE", cache_dir.path().to_string_lossy())
+                .collect()
+        };
+        assert_eq!(render(), format_output("?1"));
+
+        // Files the monitor doesn't report aren't looked at again
+        fs::create_dir(repo_dir.path().join("new"))?;
+        fs::write(repo_dir.path().join("new").join("a"), "")?;
+        assert_eq!(render(), format_output("?1"));
+
+        fs::write(&hook, "#!/bin/sh\nprintf 'token\\0new/a\\0'\n")?;
+        assert_eq!(render(), format_output("?2"));
+
+        // The untracked directory is still shown once
+        fs::write(repo_dir.path().join("new").join("b"), "")?;
+        fs::write(&hook, "#!/bin/sh\nprintf 'token\\0new/b\\0'\n")?;
+        assert_eq!(render(), format_output("?2"));
+
+        fs::remove_file(repo_dir.path().join("license"))?;
+        fs::write(&hook, "#!/bin/sh\nprintf 'token\\0license\\0'\n")?;
+        assert_eq!(render(), format_output("?1"));
+
+        cache_dir.close()?;
+        repo_dir.close()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn checks_everything_if_fsmonitor_reports_root() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+
+        create_fsmonitor_hook(repo_dir.path(), "/")?;
+        fs::write(repo_dir.path().join("readme.md"), "modified")?;
+        fs::write(repo_dir.path().join("Cargo.toml"), "modified")?;
+        create_untracked(repo_dir.path())?;
+
+        let actual = ModuleRenderer::new("git_status")
+            .config(toml::toml! {
+                [git_status]
+                modified = "!$count"
+                untracked = "?$count"
+                fsmonitor = true
+            })
+            .path(repo_dir.path())
+            .collect();
+        let expected = format_output("!2?1");
+
+        assert_eq!(expected, actual);
+        repo_dir.close()
+    }
+
     #[test]
     fn shows_stashed() -> io::Result<()> {
         let repo_dir = fixture_repo(FixtureProvider::Git)?;
@@ -895,6 +1599,24 @@ mod tests {
         repo_dir.close()
     }
 
+    #[test]
+    fn shows_racily_modified_file() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+
+        // Same size as the committed file, right after the index was written
+        let readme = repo_dir.path().join("readme.md");
+        let content = fs::read(&readme)?;
+        fs::write(&readme, "x".repeat(content.len()))?;
+
+        let actual = ModuleRenderer::new("git_status")
+            .path(repo_dir.path())
+            .collect();
+        let expected = format_output("!");
+
+        assert_eq!(expected, actual);
+        repo_dir.close()
+    }
+
     #[test]
     fn shows_modified_with_count() -> io::Result<()> {
         let repo_dir = fixture_repo(FixtureProvider::Git)?;
@@ -1026,6 +1748,46 @@ mod tests {
         repo_dir.close()
     }
 
+    #[test]
+    fn shows_staged_changes_in_subdirectories() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+        let git = |args: &[&str]| -> io::Result<()> {
+            let output = create_command("git")?
+                .args(args)
+                .current_dir(repo_dir.path())
+                .output()?;
+            assert!(output.status.success(), "git {args:?} failed: {output:?}");
+            Ok(())
+        };
+        for dir in ["docs", "src", "tests"] {
+            fs::create_dir(repo_dir.path().join(dir))?;
+            fs::write(repo_dir.path().join(dir).join("file"), dir)?;
+        }
+        fs::write(repo_dir.path().join("docs").join("other"), "other")?;
+        git(&["add", "--all"])?;
+        // Committing writes the cache of trees to the index
+        git(&["commit", "-m", "add directories", "--no-gpg-sign"])?;
+
+        git(&["mv", "docs/file", "docs/moved"])?;
+        fs::write(repo_dir.path().join("src").join("file"), "changed")?;
+        git(&["add", "src/file"])?;
+        git(&["rm", "--quiet", "docs/other"])?;
+
+        let actual = ModuleRenderer::new("git_status")
+            .path(repo_dir.path())
+            .config(toml::toml! {
+                [git_status]
+                format = "$deleted$renamed$staged"
+                deleted = "D$count"
+                renamed = "R$count"
+                staged = "S$count"
+            })
+            .collect();
+
+        assert_eq!(Some(String::from("D1R1S1")), actual);
+        repo_dir.close()
+    }
+
     #[test]
     fn shows_renamed_file_with_count() -> io::Result<()> {
         let repo_dir = fixture_repo(FixtureProvider::Git)?;
@@ -1173,6 +1935,116 @@ mod tests {
         repo_dir.close()
     }
 
+    #[test]
+    fn gix_status_matches_git() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+
+        ahead(repo_dir.path())?;
+        create_staged_and_ignored(repo_dir.path())?;
+        create_typechanged(repo_dir.path())?;
+        create_untracked(repo_dir.path())?;
+
+        let mut context = crate::test::default_context();
+        context.current_dir = repo_dir.path().to_owned();
+        let repo = context.get_repo().unwrap();
+        let config = GitStatusConfig::default();
+
+        let gix_status = get_repo_status_gix(&context, repo, &config).unwrap();
+        let git_status = get_repo_status_git(&context, repo, &config).unwrap();
+        assert_eq!(gix_status, git_status);
+        assert_eq!(gix_status.ahead, Some(1));
+        assert_eq!(gix_status.behind, Some(0));
+        assert_eq!(gix_status.staged, 1);
+        assert_eq!(gix_status.typechanged, 1);
+        assert_eq!(gix_status.untracked, 1);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn shows_categories() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+
+        create_modified(repo_dir.path())?;
+        fs::create_dir(repo_dir.path().join("src"))?;
+        fs::write(repo_dir.path().join("src").join("main.rs"), "")?;
+        fs::write(repo_dir.path().join("src").join("lib.rs"), "")?;
+
+        let actual = ModuleRenderer::new("git_status")
+            .config(toml::toml! {
+                [git_status]
+                format = "$categories"
+                untracked = "?"
+                categories = { src = "src", docs = ["docs", "*.md"], tests = "tests" }
+            })
+            .path(repo_dir.path())
+            .collect();
+        let expected = Some(String::from("src:1 docs:1"));
+
+        assert_eq!(expected, actual);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn ignores_paths() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+
+        fs::write(repo_dir.path().join("Cargo.toml"), "modified")?;
+        fs::create_dir(repo_dir.path().join("generated"))?;
+        fs::write(repo_dir.path().join("generated").join("schema.rs"), "")?;
+
+        let render = |ignore_paths: &[&str]| {
+            ModuleRenderer::new("git_status")
+                .config(toml::toml! {
+                    [git_status]
+                    ignore_paths = (ignore_paths.to_vec())
+                })
+                .path(repo_dir.path())
+                .collect()
+        };
+        assert_eq!(render(&[]), format_output("!?"));
+        assert_eq!(render(&["Cargo.toml"]), format_output("?"));
+        assert_eq!(render(&["Cargo.*", "generated/"]), None);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn gix_and_git_filter_paths_alike() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+
+        create_renamed(repo_dir.path())?;
+        fs::write(repo_dir.path().join("Cargo.toml"), "modified")?;
+        fs::create_dir(repo_dir.path().join("new dir"))?;
+        fs::write(repo_dir.path().join("new dir").join("a file"), "")?;
+
+        let mut context = crate::test::default_context().set_config(toml::toml! {
+            [git_status]
+            ignore_paths = ["Cargo.toml"]
+            categories = { readme = "readme*", new = "new dir" }
+        });
+        context.current_dir = repo_dir.path().to_owned();
+        let repo = context.get_repo().unwrap();
+        let config = GitStatusConfig::load(context.config.get_module_config("git_status").unwrap());
+
+        let gix_status = get_repo_status_gix(&context, repo, &config).unwrap();
+        let git_status = get_repo_status_git(&context, repo, &config).unwrap();
+        assert_eq!(gix_status, git_status);
+        assert_eq!(gix_status.modified, 0);
+        assert_eq!(gix_status.renamed, 1);
+        assert_eq!(gix_status.categories, vec![1, 1]);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn matches_pathspecs() {
+        assert!(matches_pathspec("src", "src/main.rs"));
+        assert!(matches_pathspec("src/", "src/main.rs"));
+        assert!(matches_pathspec("src", "src"));
+        assert!(!matches_pathspec("src", "srcs/main.rs"));
+        assert!(matches_pathspec("*.md", "docs/readme.md"));
+        assert!(matches_pathspec("docs/*.md", "docs/a/readme.md"));
+        assert!(!matches_pathspec("Cargo.lock", "crates/Cargo.lock"));
+    }
+
     #[test]
     fn doesnt_generate_git_status_for_bare_repo() -> io::Result<()> {
         let repo_dir = fixture_repo(FixtureProvider::GitBare)?;
@@ -1297,6 +2169,29 @@ mod tests {
         Ok(())
     }
 
+    /// Installs a `core.fsmonitor` hook that always reports `changed`, and lets git record
+    /// its token in the index
+    #[cfg(unix)]
+    fn create_fsmonitor_hook(repo_dir: &Path, changed: &str) -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let hook = repo_dir.join(".git").join("fsmonitor-hook");
+        fs::write(&hook, format!("#!/bin/sh\nprintf 'token\\0{changed}\\0'\n"))?;
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o700))?;
+
+        create_command("git")?
+            .args(["config", "core.fsmonitor"])
+            .arg(&hook)
+            .current_dir(repo_dir)
+            .output()?;
+        create_command("git")?
+            .arg("status")
+            .current_dir(repo_dir)
+            .output()?;
+
+        Ok(())
+    }
+
     fn create_untracked(repo_dir: &Path) -> io::Result<()> {
         File::create(repo_dir.join("license"))?.sync_all()?;
 
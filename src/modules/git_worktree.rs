@@ -0,0 +1,218 @@
+use gix::repository::Kind;
+use path_slash::PathExt;
+use std::path::Path;
+
+use super::directory::contract_path;
+use super::{Context, Module, ModuleConfig};
+
+use crate::configs::git_worktree::GitWorktreeConfig;
+use crate::context::Repo;
+use crate::formatter::StringFormatter;
+
+/// Creates a module with the linked worktree or the superproject of the submodule
+/// in the current directory
+///
+/// Will display nothing in the main worktree of a repo, or in a bare repo
+pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
+    let mut module = context.new_module("git_worktree");
+    let config = GitWorktreeConfig::try_load(module.config);
+
+    let repo = context.get_repo().ok()?;
+    let location = match repo.kind {
+        Kind::WorkTree { is_linked: true } => get_linked_worktree(context, repo)?,
+        Kind::Submodule => get_submodule(repo)?,
+        Kind::WorkTree { is_linked: false } | Kind::Bare => return None,
+    };
+
+    let parsed = StringFormatter::new(config.format).and_then(|formatter| {
+        formatter
+            .map_meta(|var, _| match var {
+                "worktree_symbol" => Some(config.worktree_symbol),
+                "submodule_symbol" => Some(config.submodule_symbol),
+                _ => None,
+            })
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(config.style)),
+                _ => None,
+            })
+            .map(|variable| match (variable, &location) {
+                ("worktree", Location::Worktree { name, .. }) => Some(Ok(name.clone())),
+                ("main_worktree", Location::Worktree { main, .. }) => main.clone().map(Ok),
+                ("superproject", Location::Submodule { superproject, .. }) => {
+                    Some(Ok(superproject.clone()))
+                }
+                ("submodule", Location::Submodule { path, .. }) => Some(Ok(path.clone())),
+                _ => None,
+            })
+            .parse(None, Some(context))
+    });
+
+    module.set_segments(match parsed {
+        Ok(segments) => segments,
+        Err(error) => {
+            log::warn!("Error in module `git_worktree`:\n{}", error);
+            return None;
+        }
+    });
+
+    Some(module)
+}
+
+enum Location {
+    /// A linked worktree, created with `git worktree add`
+    Worktree {
+        name: String,
+        /// The path of the main worktree, unless the repository is bare
+        main: Option<String>,
+    },
+    /// A submodule checked out inside the worktree of another repository
+    Submodule {
+        /// The name of the directory of the superproject
+        superproject: String,
+        /// The path of the submodule, relative to the root of the superproject
+        path: String,
+    },
+}
+
+fn get_linked_worktree(context: &Context, repo: &Repo) -> Option<Location> {
+    let git_repo = repo.open();
+    let name = git_repo.worktree()?.id()?.to_string();
+    let main = git_repo
+        .main_repo()
+        .ok()
+        .and_then(|main_repo| main_repo.work_dir().map(Path::to_path_buf))
+        .map(|main| match context.get_home() {
+            Some(home) => contract_path(&main, &home, "~").to_string(),
+            None => main.to_slash_lossy().to_string(),
+        });
+    Some(Location::Worktree { name, main })
+}
+
+/// Finds the superproject by discovering the repository containing the submodule's worktree,
+/// the way `git rev-parse --show-superproject-working-tree` does
+fn get_submodule(repo: &Repo) -> Option<Location> {
+    let workdir = repo.workdir.as_deref()?;
+    let superproject = gix::discover(workdir.parent()?)
+        .map_err(|e| log::debug!("Unable to find the superproject of the submodule: {e}"))
+        .ok()?;
+    let super_workdir = superproject.work_dir()?;
+    let path = workdir.strip_prefix(super_workdir).ok()?;
+    Some(Location::Submodule {
+        superproject: super_workdir.file_name()?.to_string_lossy().to_string(),
+        path: path.to_slash_lossy().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use nu_ansi_term::Color;
+    use std::io;
+    use std::path::Path;
+
+    use crate::test::{fixture_repo, FixtureProvider, ModuleRenderer};
+    use crate::utils::create_command;
+
+    fn git(dir: &Path, args: &[&str]) -> io::Result<()> {
+        let output = create_command("git")?
+            .args(args)
+            .current_dir(dir)
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "git {args:?} failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn show_nothing_on_empty_dir() -> io::Result<()> {
+        let repo_dir = tempfile::tempdir()?;
+
+        let actual = ModuleRenderer::new("git_worktree")
+            .path(repo_dir.path())
+            .collect();
+
+        assert_eq!(None, actual);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn show_nothing_in_main_worktree() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+
+        let actual = ModuleRenderer::new("git_worktree")
+            .path(repo_dir.path())
+            .collect();
+
+        assert_eq!(None, actual);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn shows_linked_worktree() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+        let worktrees_dir = tempfile::tempdir()?;
+        let worktree = worktrees_dir.path().join("feature");
+        git(
+            repo_dir.path(),
+            &[
+                "worktree",
+                "add",
+                "-b",
+                "feature",
+                &worktree.to_string_lossy(),
+            ],
+        )?;
+
+        let actual = ModuleRenderer::new("git_worktree")
+            .path(&worktree)
+            .env("HOME", repo_dir.path().parent().unwrap().to_string_lossy())
+            .config(toml::toml! {
+                [git_worktree]
+                format = "$worktree_symbol$worktree \\($main_worktree\\)"
+            })
+            .collect();
+
+        let main = repo_dir.path().file_name().unwrap().to_string_lossy();
+        let expected = Some(format!("🌲 feature (~/{main})"));
+
+        assert_eq!(expected, actual);
+        worktrees_dir.close()?;
+        repo_dir.close()
+    }
+
+    #[test]
+    fn shows_superproject_of_submodule() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+        let submodule_dir = fixture_repo(FixtureProvider::Git)?;
+        git(
+            repo_dir.path(),
+            &[
+                "-c",
+                "protocol.file.allow=always",
+                "submodule",
+                "add",
+                &submodule_dir.path().to_string_lossy(),
+                "libs/sub",
+            ],
+        )?;
+
+        let actual = ModuleRenderer::new("git_worktree")
+            .path(repo_dir.path().join("libs").join("sub"))
+            .collect();
+
+        let superproject = repo_dir.path().file_name().unwrap().to_string_lossy();
+        let expected = Some(format!(
+            "{} ",
+            Color::Green
+                .bold()
+                .paint(format!("⊂ {superproject}/libs/sub"))
+        ));
+
+        assert_eq!(expected, actual);
+        submodule_dir.close()?;
+        repo_dir.close()
+    }
+}
@@ -26,7 +26,7 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     }
 
     let golang_version =
-        Lazy::new(|| parse_go_version(&context.exec_cmd("go", &["version"])?.stdout));
+        Lazy::new(|| parse_go_version(&context.exec_version_cmd("go", &["version"])?.stdout));
     let mod_version = Lazy::new(|| get_go_mod_version(context));
 
     let parsed = StringFormatter::new(config.format).and_then(|formatter| {
@@ -0,0 +1,1030 @@
+use gix::bstr::{BStr, BString, ByteSlice, ByteVec};
+use once_cell::sync::OnceCell;
+use regex::bytes::Regex;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use super::utils::hg::{self, Dirstate, DirstateEntry};
+use super::{Context, Module, ModuleConfig};
+
+use crate::configs::hg_status::HgStatusConfig;
+use crate::formatter::StringFormatter;
+use crate::segment::Segment;
+
+const ALL_STATUS_FORMAT: &str = "$unresolved$missing$removed$modified$added$unknown$status_partial";
+
+/// Creates a module with the Mercurial status in the current directory
+///
+/// The status is read from `.hg/dirstate` and `.hg/merge` instead of running `hg`, which
+/// is slow to start. Files that were touched since they were recorded in the dirstate,
+/// but kept their size, are counted as modified, as telling whether their content changed
+/// would need the revlogs. Will display the status of the repo if the current directory is an
+/// hg repo. By default, the following symbols are used:
+///   - `=` — This repo has unresolved merge conflicts
+///   - `⊘` — A tracked file is missing from the working directory
+///   - `✘` — A file has been removed with `hg remove`
+///   - `!` — A tracked file has been modified
+///   - `+` — A file has been added with `hg add`
+///   - `?` — There are files that are neither tracked nor ignored
+///   - `…` — Finding unknown files timed out, so their count is incomplete
+pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
+    let mut module = context.new_module("hg_status");
+    let config = HgStatusConfig::try_load(module.config);
+
+    // As we default to disabled=true, we have to check here after loading our config module,
+    // before it was only checking against whatever is in the config starship.toml
+    if config.disabled {
+        return None;
+    };
+
+    let repo_root = context.begin_ancestor_scan().set_folders(&[".hg"]).scan()?;
+    let repo = hg::Repository::open(repo_root);
+    let dirstate = repo.dirstate();
+
+    // Finding unknown files has to walk the working directory, so it's only done if needed
+    let tracked = OnceCell::new();
+    let unknown = OnceCell::new();
+    let get_tracked = || {
+        *tracked.get_or_init(|| {
+            dirstate
+                .as_ref()
+                .map(|dirstate| get_tracked_status(repo_root, dirstate))
+                .unwrap_or_default()
+        })
+    };
+    let get_unknown = || {
+        *unknown.get_or_init(|| {
+            dirstate
+                .as_ref()
+                .map(|dirstate| count_unknown(context, repo_root, dirstate))
+                .unwrap_or_default()
+        })
+    };
+
+    let parsed = StringFormatter::new(config.format).and_then(|formatter| {
+        formatter
+            .map_meta(|variable, _| match variable {
+                "all_status" => Some(ALL_STATUS_FORMAT),
+                _ => None,
+            })
+            .map_style(|variable: &str| match variable {
+                "style" => Some(Ok(config.style)),
+                _ => None,
+            })
+            .map_variables_to_segments(|variable: &str| {
+                let segments = match variable {
+                    "unresolved" => format_count(
+                        config.unresolved,
+                        "hg_status.unresolved",
+                        context,
+                        repo.unresolved_count(),
+                    ),
+                    "missing" => format_count(
+                        config.missing,
+                        "hg_status.missing",
+                        context,
+                        get_tracked().missing,
+                    ),
+                    "removed" => format_count(
+                        config.removed,
+                        "hg_status.removed",
+                        context,
+                        get_tracked().removed,
+                    ),
+                    "modified" => format_count(
+                        config.modified,
+                        "hg_status.modified",
+                        context,
+                        get_tracked().modified,
+                    ),
+                    "added" => format_count(
+                        config.added,
+                        "hg_status.added",
+                        context,
+                        get_tracked().added,
+                    ),
+                    "unknown" => format_count(
+                        config.unknown,
+                        "hg_status.unknown",
+                        context,
+                        get_unknown().count,
+                    ),
+                    "status_partial" => format_count(
+                        config.status_partial,
+                        "hg_status.status_partial",
+                        context,
+                        usize::from(get_unknown().partial),
+                    ),
+                    _ => None,
+                };
+                segments.map(Ok)
+            })
+            .parse(None, Some(context))
+    });
+
+    module.set_segments(match parsed {
+        Ok(segments) => {
+            if segments.is_empty() {
+                return None;
+            } else {
+                segments
+            }
+        }
+        Err(error) => {
+            log::warn!("Error in module `hg_status`:\n{}", error);
+            return None;
+        }
+    });
+
+    Some(module)
+}
+
+fn format_count(
+    format_str: &str,
+    config_path: &str,
+    context: &Context,
+    count: usize,
+) -> Option<Vec<Segment>> {
+    if count == 0 {
+        return None;
+    }
+
+    match StringFormatter::new(format_str) {
+        Ok(formatter) => formatter
+            .map(|variable| match variable {
+                "count" => Some(Ok(count.to_string())),
+                _ => None,
+            })
+            .parse(None, Some(context))
+            .ok(),
+        Err(_) => {
+            log::warn!("Error parsing format string `{}`", &config_path);
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct TrackedStatus {
+    modified: usize,
+    added: usize,
+    removed: usize,
+    missing: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct UnknownFiles {
+    count: usize,
+    /// Whether finding them timed out before the whole working directory was checked
+    partial: bool,
+}
+
+/// Whether a file changed since its state was recorded in the dirstate
+enum FileState {
+    Clean,
+    Modified,
+    /// The size and mode match, but the file was touched, so its content has to be compared
+    Lookup,
+}
+
+/// Compares the tracked files with the dirstate the way `hg status` does, except that files
+/// whose content would have to be compared are counted as modified
+fn get_tracked_status(root: &Path, dirstate: &Dirstate) -> TrackedStatus {
+    let mut status = TrackedStatus::default();
+    for (path, entry) in &dirstate.entries {
+        if entry.state == b'r' {
+            status.removed += 1;
+            continue;
+        }
+        let metadata = gix::path::try_from_bstr(path.as_bstr())
+            .ok()
+            .and_then(|path| fs::symlink_metadata(root.join(path)).ok());
+        let Some(metadata) = metadata else {
+            status.missing += 1;
+            continue;
+        };
+        if entry.state == b'a' {
+            status.added += 1;
+            continue;
+        }
+        match compare_metadata(entry, &metadata) {
+            FileState::Clean => {}
+            FileState::Modified => status.modified += 1,
+            FileState::Lookup => {
+                log::trace!(
+                    "Counting {path} as modified, as it was touched since the dirstate was written"
+                );
+                status.modified += 1;
+            }
+        }
+    }
+    status
+}
+
+fn compare_metadata(entry: &DirstateEntry, metadata: &fs::Metadata) -> FileState {
+    // Sizes and times are stored as 31 bit integers
+    const RANGE_MASK: i64 = 0x7fff_ffff;
+
+    if entry.state == b'm' || entry.size == -2 {
+        return FileState::Modified;
+    }
+    let size = metadata.len() as i64;
+    // A size of -1 means the mode and size are unknown
+    let size_changed = i64::from(entry.size) != size && i64::from(entry.size) != size & RANGE_MASK;
+    if entry.size >= 0 && (size_changed || is_exec_changed(entry, metadata)) {
+        return FileState::Modified;
+    }
+
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|mtime| mtime.duration_since(UNIX_EPOCH).ok())
+        .map(|mtime| mtime.as_secs() as i64 & RANGE_MASK);
+    if mtime == Some(i64::from(entry.mtime)) {
+        FileState::Clean
+    } else {
+        FileState::Lookup
+    }
+}
+
+#[cfg(unix)]
+fn is_exec_changed(entry: &DirstateEntry, metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    (entry.mode as u32 ^ metadata.mode()) & 0o100 != 0
+}
+
+#[cfg(not(unix))]
+fn is_exec_changed(_entry: &DirstateEntry, _metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// Counts the files in the working directory that are neither tracked nor ignored, giving
+/// up once `command_timeout` has passed
+fn count_unknown(context: &Context, root: &Path, dirstate: &Dirstate) -> UnknownFiles {
+    let start = Instant::now();
+    let timeout = Duration::from_millis(context.root_config.command_timeout);
+    let ignore = Ignore::load(context, root);
+    let mut unknown = UnknownFiles::default();
+    let mut dirs = vec![(root.to_path_buf(), BString::default())];
+    while let Some((dir, prefix)) = dirs.pop() {
+        if start.elapsed() >= timeout {
+            log::debug!(
+                "Finding unknown files in {} took longer than {}ms",
+                root.display(),
+                timeout.as_millis()
+            );
+            unknown.partial = true;
+            break;
+        }
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let Ok(name) = gix::path::os_str_into_bstr(&file_name) else {
+                continue;
+            };
+            let mut path = prefix.clone();
+            if !path.is_empty() {
+                path.push(b'/');
+            }
+            path.push_str(name);
+
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                // Skip the repository itself and nested repositories
+                if name != ".hg"
+                    && !entry.path().join(".hg").exists()
+                    && !ignore.is_match(path.as_bstr())
+                {
+                    dirs.push((entry.path(), path));
+                }
+            } else if !dirstate.entries.contains_key(&path) && !ignore.is_match(path.as_bstr()) {
+                unknown.count += 1;
+            }
+        }
+    }
+    unknown
+}
+
+/// The patterns of the `.hgignore` file in the root of the repository, the files set with
+/// `ignore` in the `[ui]` section of the configuration and the files they include
+struct Ignore {
+    /// The patterns of each file, with the directory they apply to for subincludes
+    rules: Vec<(BString, Vec<Regex>)>,
+}
+
+impl Ignore {
+    /// How deep ignore files may include each other
+    const MAX_INCLUDE_DEPTH: usize = 8;
+
+    fn load(context: &Context, root: &Path) -> Self {
+        let mut ignore = Self { rules: Vec::new() };
+        ignore.add_file(root, &root.join(".hgignore"), BString::default(), 0);
+        for file in get_ui_ignore_files(context, root) {
+            ignore.add_file(root, &file, BString::default(), 0);
+        }
+        ignore
+    }
+
+    fn add_file(&mut self, root: &Path, file: &Path, dir: BString, depth: usize) {
+        if depth > Self::MAX_INCLUDE_DEPTH {
+            log::debug!("Too many nested includes in {}", file.display());
+            return;
+        }
+        let Ok(content) = fs::read(file) else {
+            return;
+        };
+        let HgIgnore { patterns, includes } = parse_hgignore(&content);
+        self.rules.push((dir.clone(), patterns));
+
+        // Included files are relative to the file including them
+        let parent = file.parent().unwrap_or(root);
+        for include in includes {
+            match include {
+                Include::File(path) => {
+                    self.add_file(root, &parent.join(path), dir.clone(), depth + 1)
+                }
+                // The patterns of a subinclude only apply to its own directory
+                Include::Subdirectory(path) => {
+                    let file = parent.join(path);
+                    let Some(sub_dir) = file
+                        .parent()
+                        .and_then(|sub_dir| sub_dir.strip_prefix(root).ok())
+                        .and_then(|sub_dir| gix::path::try_into_bstr(sub_dir).ok())
+                    else {
+                        continue;
+                    };
+                    let sub_dir = gix::path::to_unix_separators_on_windows(sub_dir).into_owned();
+                    self.add_file(root, &file, sub_dir, depth + 1);
+                }
+            }
+        }
+    }
+
+    fn is_match(&self, path: &BStr) -> bool {
+        self.rules.iter().any(|(dir, patterns)| {
+            let path = if dir.is_empty() {
+                Some(path.as_bytes())
+            } else {
+                path.strip_prefix(dir.as_bytes())
+                    .and_then(|path| path.strip_prefix(b"/"))
+            };
+            path.is_some_and(|path| patterns.iter().any(|pattern| pattern.is_match(path)))
+        })
+    }
+}
+
+/// Finds the ignore files set with `ignore` or `ignore.<name>` in the `[ui]` section of the
+/// configuration, relative to the root of the repository. Like hg, the system and user
+/// configuration files are read, unless `$HGRCPATH` lists others, and then `.hg/hgrc`.
+fn get_ui_ignore_files(context: &Context, root: &Path) -> Vec<PathBuf> {
+    let home = context.get_home();
+    let mut config_files = Vec::new();
+    match context.get_env_os("HGRCPATH") {
+        // Directories in the list stand for the `.rc` files they contain
+        Some(paths) => {
+            for path in std::env::split_paths(&paths) {
+                if path.is_dir() {
+                    config_files.extend(list_rc_files(&path));
+                } else {
+                    config_files.push(path);
+                }
+            }
+        }
+        None => {
+            let system_dir = if cfg!(windows) {
+                context
+                    .get_env("PROGRAMDATA")
+                    .map(|dir| PathBuf::from(dir).join("Mercurial"))
+            } else {
+                Some(PathBuf::from("/etc/mercurial"))
+            };
+            if let Some(system_dir) = system_dir {
+                config_files.push(system_dir.join("hgrc"));
+                config_files.extend(list_rc_files(&system_dir.join("hgrc.d")));
+            }
+            if let Some(home) = &home {
+                config_files.push(home.join(".hgrc"));
+                if cfg!(windows) {
+                    config_files.push(home.join("Mercurial.ini"));
+                }
+            }
+            let config_dir = context
+                .get_env("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|| Some(home.as_ref()?.join(".config")));
+            if let Some(config_dir) = config_dir {
+                config_files.push(config_dir.join("hg").join("hgrc"));
+            }
+        }
+    }
+    config_files.push(root.join(".hg").join("hgrc"));
+
+    // Later files override the ignore files of the same name set by earlier ones
+    let mut ignore_files = BTreeMap::new();
+    for config_file in config_files {
+        read_ui_ignore_files(&config_file, home.as_deref(), 0, &mut ignore_files);
+    }
+    ignore_files
+        .into_values()
+        .map(|path| root.join(path))
+        .collect()
+}
+
+/// The `.rc` files in a directory of configuration files, in the order hg reads them
+fn list_rc_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rc"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Reads the `ignore` keys of the `[ui]` section of a configuration file and the files it
+/// includes with `%include`, which are relative to the including file
+fn read_ui_ignore_files(
+    file: &Path,
+    home: Option<&Path>,
+    depth: usize,
+    ignore_files: &mut BTreeMap<String, PathBuf>,
+) {
+    if depth > Ignore::MAX_INCLUDE_DEPTH {
+        log::debug!("Too many nested includes in {}", file.display());
+        return;
+    }
+    let Ok(content) = fs::read_to_string(file) else {
+        return;
+    };
+    let expand_home = |path: &str| match (path.strip_prefix("~/"), home) {
+        (Some(path), Some(home)) => home.join(path),
+        _ => PathBuf::from(path),
+    };
+
+    let mut section = String::new();
+    for line in content.lines() {
+        // Indented lines continue the value of the previous key
+        if line.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let line = line.trim_end();
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            section = name.trim().to_string();
+        } else if let Some(path) = line.strip_prefix("%include ") {
+            let path = expand_home(path.trim());
+            let path = file
+                .parent()
+                .map_or_else(|| path.clone(), |dir| dir.join(&path));
+            read_ui_ignore_files(&path, home, depth + 1, ignore_files);
+        } else if let Some(key) = line.strip_prefix("%unset ") {
+            if section == "ui" {
+                ignore_files.remove(key.trim());
+            }
+        } else if let Some((key, value)) = line.split_once('=') {
+            let key = key.trim();
+            if section == "ui" && (key == "ignore" || key.starts_with("ignore.")) {
+                ignore_files.insert(key.to_string(), expand_home(value.trim()));
+            }
+        }
+    }
+}
+
+/// An ignore file included by another one
+#[derive(Debug, PartialEq, Eq)]
+enum Include {
+    /// `include:`, whose patterns apply to the whole repository
+    File(String),
+    /// `subinclude:`, whose patterns only apply to the directory of the file
+    Subdirectory(String),
+}
+
+struct HgIgnore {
+    patterns: Vec<Regex>,
+    includes: Vec<Include>,
+}
+
+/// Converts the patterns of an `.hgignore` file to regexes. Neither globs nor regexes are
+/// rooted, unless they use the `rootglob` syntax or start with `^`.
+fn parse_hgignore(content: &[u8]) -> HgIgnore {
+    // `#` starts a comment, unless it's escaped with a backslash
+    let comment = Regex::new(r"((?:^|[^\\])(?:\\\\)*)#.*").unwrap();
+
+    let mut syntax = "relre";
+    let mut patterns = Vec::new();
+    let mut includes = Vec::new();
+    for line in content.lines() {
+        let line = comment.replace(line, &b"$1"[..]).replace(r"\#", "#");
+        let Ok(line) = std::str::from_utf8(line.trim_end()) else {
+            continue;
+        };
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(path) = line.strip_prefix("include:") {
+            includes.push(Include::File(path.trim().to_string()));
+            continue;
+        }
+        if let Some(path) = line.strip_prefix("subinclude:") {
+            includes.push(Include::Subdirectory(path.trim().to_string()));
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("syntax:") {
+            match name.trim() {
+                "re" | "regexp" => syntax = "relre",
+                "glob" => syntax = "relglob",
+                "rootglob" => syntax = "rootglob",
+                name => log::debug!("Unknown syntax in .hgignore: {name}"),
+            }
+            continue;
+        }
+
+        let (kind, pattern) = [
+            ("re:", "relre"),
+            ("regexp:", "relre"),
+            ("relre:", "relre"),
+            ("glob:", "relglob"),
+            ("relglob:", "relglob"),
+            ("rootglob:", "rootglob"),
+            ("path:", "path"),
+        ]
+        .into_iter()
+        .find_map(|(prefix, kind)| Some((kind, line.strip_prefix(prefix)?)))
+        .unwrap_or((syntax, line));
+        // Like hg, normalize paths, which removes trailing slashes
+        let pattern = match kind {
+            "relre" => pattern,
+            _ => pattern.trim_end_matches('/'),
+        };
+        let regex = match kind {
+            "relglob" => format!("^(?:|.*/){}(?:/|$)", glob_to_regex(pattern)),
+            "rootglob" => format!("^{}(?:/|$)", glob_to_regex(pattern)),
+            "path" => format!("^{}(?:/|$)", regex::escape(pattern)),
+            _ => pattern.to_string(),
+        };
+        match Regex::new(&regex) {
+            Ok(regex) => patterns.push(regex),
+            Err(e) => log::debug!("Unsupported pattern in .hgignore {pattern:?}: {e}"),
+        }
+    }
+    HgIgnore { patterns, includes }
+}
+
+/// Converts a glob to a regex like hg does, where `*` doesn't match across directories
+/// but `**` does, and `{a,b}` matches either alternative
+fn glob_to_regex(glob: &str) -> String {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut regex = String::new();
+    let mut groups = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        match c {
+            '*' if chars.get(i) == Some(&'*') => {
+                i += 1;
+                if chars.get(i) == Some(&'/') {
+                    i += 1;
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push('.'),
+            '[' => {
+                let mut j = i;
+                if matches!(chars.get(j), Some('!' | ']')) {
+                    j += 1;
+                }
+                while j < chars.len() && chars[j] != ']' {
+                    j += 1;
+                }
+                if j >= chars.len() {
+                    regex.push_str(r"\[");
+                } else {
+                    let class: String = chars[i..j].iter().collect();
+                    let class = class.replace('\\', r"\\");
+                    i = j + 1;
+                    regex.push('[');
+                    match class.strip_prefix('!') {
+                        Some(negated) => {
+                            regex.push('^');
+                            regex.push_str(negated);
+                        }
+                        None if class.starts_with('^') => {
+                            regex.push('\\');
+                            regex.push_str(&class);
+                        }
+                        None => regex.push_str(&class),
+                    }
+                    regex.push(']');
+                }
+            }
+            '{' => {
+                groups += 1;
+                regex.push_str("(?:");
+            }
+            '}' if groups > 0 => {
+                groups -= 1;
+                regex.push(')');
+            }
+            ',' if groups > 0 => regex.push('|'),
+            '\\' if i < chars.len() => {
+                regex.push_str(&regex::escape(&chars[i].to_string()));
+                i += 1;
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use nu_ansi_term::Color;
+    use std::fs;
+    use std::io;
+    use std::path::Path;
+
+    use super::*;
+    use crate::test::{fixture_repo, FixtureProvider, ModuleRenderer};
+    use crate::utils::create_command;
+
+    /// Writes a dirstate with entries of `(state, mode, size, mtime, path)`
+    fn write_dirstate(repo_dir: &Path, entries: &[(u8, i32, i32, i32, &str)]) -> io::Result<()> {
+        // Starting with the ids of the parents of the working directory
+        let mut data = vec![1; 40];
+        for (state, mode, size, mtime, path) in entries {
+            data.push(*state);
+            for field in [*mode, *size, *mtime, path.len() as i32] {
+                data.extend(field.to_be_bytes());
+            }
+            data.extend(path.as_bytes());
+        }
+        fs::write(repo_dir.join(".hg").join("dirstate"), data)
+    }
+
+    /// Creates a repository tracking `README` and `LICENSE.txt`
+    fn create_repo() -> io::Result<tempfile::TempDir> {
+        let repo_dir = tempfile::tempdir()?;
+        let hg_dir = repo_dir.path().join(".hg");
+        fs::create_dir_all(hg_dir.join("store"))?;
+        fs::write(
+            hg_dir.join("requires"),
+            "dotencode\nfncache\ngeneraldelta\nrevlogv1\nstore\n",
+        )?;
+
+        fs::write(repo_dir.path().join("README"), "Hello\n")?;
+        fs::write(repo_dir.path().join("LICENSE.txt"), "MIT\n")?;
+        Ok(repo_dir)
+    }
+
+    fn render(repo_dir: &Path) -> Option<String> {
+        ModuleRenderer::new("hg_status")
+            .path(repo_dir)
+            .config(toml::toml! {
+                [hg_status]
+                disabled = false
+                format = "$all_status"
+            })
+            .collect()
+    }
+
+    fn render_unknown(repo_dir: &Path) -> Option<String> {
+        ModuleRenderer::new("hg_status")
+            .path(repo_dir)
+            .config(toml::toml! {
+                [hg_status]
+                disabled = false
+                format = "$unknown"
+            })
+            .collect()
+    }
+
+    #[test]
+    fn show_nothing_on_empty_dir() -> io::Result<()> {
+        let repo_dir = tempfile::tempdir()?;
+        assert_eq!(None, render(repo_dir.path()));
+        repo_dir.close()
+    }
+
+    #[test]
+    fn disabled_per_default() -> io::Result<()> {
+        let repo_dir = create_repo()?;
+        fs::write(repo_dir.path().join("new.txt"), "")?;
+
+        let actual = ModuleRenderer::new("hg_status")
+            .path(repo_dir.path())
+            .collect();
+
+        assert_eq!(None, actual);
+        repo_dir.close()
+    }
+
+    /// The modification time of a file as it's recorded in the dirstate
+    fn mtime(path: &Path) -> io::Result<i32> {
+        let mtime = fs::metadata(path)?.modified()?.duration_since(UNIX_EPOCH);
+        Ok(mtime.map_or(-1, |mtime| mtime.as_secs() as i32 & 0x7fff_ffff))
+    }
+
+    #[test]
+    fn counts_touched_files_as_modified() -> io::Result<()> {
+        let repo_dir = create_repo()?;
+        let license_mtime = mtime(&repo_dir.path().join("LICENSE.txt"))?;
+        let readme_mtime = mtime(&repo_dir.path().join("README"))?;
+        write_dirstate(
+            repo_dir.path(),
+            &[
+                (b'n', 0o100_644, 4, license_mtime, "LICENSE.txt"),
+                (b'n', 0o100_644, 6, readme_mtime, "README"),
+            ],
+        )?;
+        assert_eq!(None, render(repo_dir.path()));
+
+        // Unknown modification times would need the content to be compared
+        write_dirstate(
+            repo_dir.path(),
+            &[
+                (b'n', 0o100_644, 4, license_mtime, "LICENSE.txt"),
+                (b'n', 0o100_644, 6, -1, "README"),
+            ],
+        )?;
+        assert_eq!(Some(String::from("!")), render(repo_dir.path()));
+        repo_dir.close()
+    }
+
+    #[test]
+    fn reads_dirstate_v2() -> io::Result<()> {
+        use hg::{V2_HAS_MODE_AND_SIZE, V2_HAS_MTIME, V2_P1_TRACKED, V2_WDIR_TRACKED};
+
+        let repo_dir = create_repo()?;
+        let hg_dir = repo_dir.path().join(".hg");
+        fs::write(hg_dir.join("requires"), "dirstate-v2\nstore\n")?;
+        fs::create_dir(repo_dir.path().join("src"))?;
+        fs::write(repo_dir.path().join("src").join("added.rs"), "")?;
+        let (docket, tree) = hg::encode_dirstate_v2(&[
+            (
+                V2_WDIR_TRACKED | V2_P1_TRACKED | V2_HAS_MODE_AND_SIZE | V2_HAS_MTIME,
+                6,
+                mtime(&repo_dir.path().join("README"))?,
+                "README",
+            ),
+            (V2_P1_TRACKED, 0, 0, "LICENSE.txt"),
+            (V2_WDIR_TRACKED, 0, 0, "src/added.rs"),
+        ]);
+        fs::write(hg_dir.join("dirstate"), docket)?;
+        fs::write(hg_dir.join("dirstate.0123"), tree)?;
+
+        assert_eq!(Some(String::from("✘+")), render(repo_dir.path()));
+        repo_dir.close()
+    }
+
+    #[test]
+    fn shows_all_states() -> io::Result<()> {
+        let repo_dir = create_repo()?;
+        fs::write(repo_dir.path().join("README"), "Hello, world\n")?;
+        fs::write(repo_dir.path().join("added.txt"), "")?;
+        fs::write(repo_dir.path().join("unknown.txt"), "")?;
+        fs::write(repo_dir.path().join("ignored.o"), "")?;
+        fs::create_dir(repo_dir.path().join("build"))?;
+        fs::write(repo_dir.path().join("build").join("output"), "")?;
+        fs::write(
+            repo_dir.path().join(".hgignore"),
+            "syntax: glob\n*.o\nbuild/ # generated\n.hgignore\n",
+        )?;
+        write_dirstate(
+            repo_dir.path(),
+            &[
+                (b'a', 0, -1, -1, "added.txt"),
+                (b'n', 0o100_644, 6, -1, "README"),
+                (b'n', 0o100_644, 4, -1, "missing.txt"),
+                (b'r', 0, 0, 0, "LICENSE.txt"),
+            ],
+        )?;
+
+        let record = b"README\0u\0hash";
+        let mut merge_state = vec![b'F'];
+        merge_state.extend((record.len() as u32).to_be_bytes());
+        merge_state.extend(record);
+        fs::create_dir(repo_dir.path().join(".hg").join("merge"))?;
+        fs::write(
+            repo_dir.path().join(".hg").join("merge").join("state2"),
+            merge_state,
+        )?;
+
+        assert_eq!(Some(String::from("=⊘✘!+?")), render(repo_dir.path()));
+        repo_dir.close()
+    }
+
+    #[test]
+    fn shows_counts() -> io::Result<()> {
+        let repo_dir = create_repo()?;
+        write_dirstate(repo_dir.path(), &[])?;
+        fs::write(repo_dir.path().join("new.txt"), "")?;
+
+        let actual = ModuleRenderer::new("hg_status")
+            .path(repo_dir.path())
+            .config(toml::toml! {
+                [hg_status]
+                disabled = false
+                unknown = "?$count"
+            })
+            .collect();
+
+        let expected = Some(format!("{} ", Color::Red.bold().paint("[?3]")));
+        assert_eq!(expected, actual);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn converts_hgignore_patterns() {
+        let hgignore = parse_hgignore(
+            b"\\.pyc$\n^target/\nsyntax: glob\n**/cache/\n*.{log,tmp}\nrootglob:docs/*.html\n\
+              path:vendor\n\\#notes # a comment\ninclude:common\nsubinclude: docs/.hgignore\n",
+        );
+        assert_eq!(
+            hgignore.includes,
+            [
+                Include::File(String::from("common")),
+                Include::Subdirectory(String::from("docs/.hgignore"))
+            ]
+        );
+        let ignore = Ignore {
+            rules: vec![(BString::default(), hgignore.patterns)],
+        };
+
+        for path in [
+            "module.pyc",
+            "src/module.pyc",
+            "target/debug/starship",
+            "a/b/cache/file",
+            "cache/file",
+            "src/out.log",
+            "tmp.tmp",
+            "docs/index.html",
+            "vendor/crate/lib.rs",
+            "#notes",
+        ] {
+            assert!(ignore.is_match(path.into()), "{path} should be ignored");
+        }
+        for path in [
+            "module.py",
+            "src/target/debug",
+            "docs/api/index.html",
+            "src/vendor/lib.rs",
+            "out.logs",
+        ] {
+            assert!(!ignore.is_match(path.into()), "{path} shouldn't be ignored");
+        }
+    }
+
+    #[test]
+    fn reads_included_and_configured_ignore_files() -> io::Result<()> {
+        let repo_dir = create_repo()?;
+        fs::write(
+            repo_dir.path().join(".hgignore"),
+            "include:ignore/common\nsubinclude:docs/.hgignore\n.hgignore\n",
+        )?;
+        fs::create_dir(repo_dir.path().join("ignore"))?;
+        fs::write(
+            repo_dir.path().join("ignore").join("common"),
+            "syntax: glob\n*.o\nignore\n",
+        )?;
+        fs::create_dir_all(repo_dir.path().join("docs").join("out"))?;
+        fs::write(
+            repo_dir.path().join("docs").join(".hgignore"),
+            "syntax: rootglob\nout\n.hgignore\n",
+        )?;
+        fs::write(
+            repo_dir.path().join("docs").join("out").join("index.html"),
+            "",
+        )?;
+        fs::write(
+            repo_dir.path().join(".hg").join("hgrc"),
+            "[ui]\nusername = someone\nignore.local = .hg/local-ignore\n",
+        )?;
+        fs::write(
+            repo_dir.path().join(".hg").join("local-ignore"),
+            "syntax: glob\n*.log\n",
+        )?;
+        for file in ["main.o", "debug.log", "README", "LICENSE.txt"] {
+            fs::write(repo_dir.path().join(file), "")?;
+        }
+        write_dirstate(
+            repo_dir.path(),
+            &[
+                (b'n', 0o100_644, 0, -1, "LICENSE.txt"),
+                (b'n', 0o100_644, 0, -1, "README"),
+            ],
+        )?;
+        assert_eq!(None, render_unknown(repo_dir.path()));
+
+        // Subincludes only apply to their own directory
+        fs::create_dir(repo_dir.path().join("out"))?;
+        fs::write(repo_dir.path().join("out").join("index.html"), "")?;
+        assert_eq!(Some(String::from("?")), render_unknown(repo_dir.path()));
+        repo_dir.close()
+    }
+
+    #[test]
+    fn reads_system_config_and_includes() -> io::Result<()> {
+        let repo_dir = create_repo()?;
+        let config_dir = tempfile::tempdir()?;
+        fs::write(
+            config_dir.path().join("00-system.rc"),
+            "%include common/hgrc\n[ui]\nignore.unset = unset-ignore\n",
+        )?;
+        fs::create_dir(config_dir.path().join("common"))?;
+        fs::write(
+            config_dir.path().join("common").join("hgrc"),
+            "[ui]\nignore.system = .hg/system-ignore\n",
+        )?;
+        fs::write(
+            repo_dir.path().join(".hg").join("system-ignore"),
+            "syntax: glob\n*.o\n",
+        )?;
+        fs::write(
+            repo_dir.path().join("unset-ignore"),
+            "syntax: glob\n*.log\n",
+        )?;
+        fs::write(
+            repo_dir.path().join(".hg").join("hgrc"),
+            "[ui]\n%unset ignore.unset\n",
+        )?;
+        fs::write(repo_dir.path().join("main.o"), "")?;
+        write_dirstate(
+            repo_dir.path(),
+            &[
+                (b'n', 0o100_644, 0, -1, "LICENSE.txt"),
+                (b'n', 0o100_644, 0, -1, "README"),
+                (b'n', 0o100_644, 0, -1, "unset-ignore"),
+            ],
+        )?;
+        let render = || {
+            ModuleRenderer::new("hg_status")
+                .path(repo_dir.path())
+                .env("HGRCPATH", config_dir.path().to_string_lossy())
+                .config(toml::toml! {
+                    [hg_status]
+                    disabled = false
+                    format = "$unknown"
+                })
+                .collect()
+        };
+        assert_eq!(None, render());
+
+        fs::write(repo_dir.path().join("debug.log"), "")?;
+        assert_eq!(Some(String::from("?")), render());
+        config_dir.close()?;
+        repo_dir.close()
+    }
+
+    #[test]
+    fn stops_finding_unknown_files_after_timeout() -> io::Result<()> {
+        let repo_dir = create_repo()?;
+        write_dirstate(repo_dir.path(), &[])?;
+
+        let actual = ModuleRenderer::new("hg_status")
+            .path(repo_dir.path())
+            .config(toml::toml! {
+                command_timeout = 0
+                [hg_status]
+                disabled = false
+                format = "$unknown$status_partial"
+            })
+            .collect();
+
+        assert_eq!(Some(String::from("…")), actual);
+        repo_dir.close()
+    }
+
+    #[test]
+    #[ignore]
+    fn test_hg_status_with_hg() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Hg)?;
+        let hg = |args: &[&str]| -> io::Result<()> {
+            create_command("hg")?
+                .args(args)
+                .current_dir(repo_dir.path())
+                .output()?;
+            Ok(())
+        };
+        fs::write(repo_dir.path().join("added.txt"), "")?;
+        fs::write(repo_dir.path().join("unknown.txt"), "")?;
+        hg(&["add", "added.txt"])?;
+
+        assert_eq!(Some(String::from("+?")), render(repo_dir.path()));
+        repo_dir.close()
+    }
+}
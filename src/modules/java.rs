@@ -73,7 +73,7 @@ fn get_java_version(context: &Context) -> Option<String> {
         })
         .unwrap_or_else(|| String::from("java"));
 
-    let output = context.exec_cmd(java_command, &["-Xinternalversion"])?;
+    let output = context.exec_version_cmd(java_command, &["-Xinternalversion"])?;
     let java_version_string = get_command_string_output(output);
 
     parse_java_version(&java_version_string)
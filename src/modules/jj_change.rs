@@ -0,0 +1,163 @@
+use super::utils::jj;
+use super::utils::truncate::truncate_text;
+use super::{Context, Module, ModuleConfig};
+
+use crate::configs::jj_change::JjChangeConfig;
+use crate::formatter::StringFormatter;
+
+/// Creates a module with the Jujutsu working-copy change in the current directory
+///
+/// Will display the change id and bookmarks if the current directory is a jj repo
+pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
+    let mut module = context.new_module("jj_change");
+    let config = JjChangeConfig::try_load(module.config);
+
+    // As we default to disabled=true, we have to check here after loading our config module,
+    // before it was only checking against whatever is in the config starship.toml
+    if config.disabled {
+        return None;
+    };
+
+    jj::find_root(context)?;
+    let commit = context.get_jj_working_copy(config.ignore_working_copy)?;
+
+    let description = truncate_text(
+        &commit.description,
+        config.truncation_length as usize,
+        config.truncation_symbol,
+    );
+
+    let parsed = StringFormatter::new(config.format).and_then(|formatter| {
+        formatter
+            .map_meta(|variable, _| match variable {
+                "symbol" => Some(config.symbol),
+                _ => None,
+            })
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(config.style)),
+                _ => None,
+            })
+            .map(|variable| match variable {
+                "change_id" => Some(Ok(commit.change_id(config.change_id_length).to_string())),
+                "commit_id" => Some(Ok(commit.commit_id(config.commit_id_length).to_string())),
+                "description" if !description.is_empty() => Some(Ok(description.clone())),
+                "bookmarks" if !commit.bookmarks.is_empty() => Some(Ok(commit.bookmarks.join(" "))),
+                _ => None,
+            })
+            .parse(None, Some(context))
+    });
+
+    module.set_segments(match parsed {
+        Ok(segments) => segments,
+        Err(error) => {
+            log::warn!("Error in module `jj_change`:\n{}", error);
+            return None;
+        }
+    });
+
+    Some(module)
+}
+
+#[cfg(test)]
+mod tests {
+    use nu_ansi_term::Color;
+    use std::fs;
+    use std::io;
+
+    use crate::modules::utils::jj;
+    use crate::test::ModuleRenderer;
+    use crate::utils::{display_command, CommandOutput};
+
+    const LOG_OUTPUT: &str = "kxqzwmplyvrlsokrtztsoqttlnxoxonu\nk\n\
+        3f7d1c0a9b8e2d4c6f1a0b9e8d7c6b5a4f3e2d1c\n3f\n\
+        Add jj support to the prompt\nmain feature\nempty\n";
+
+    fn log_output() -> Option<CommandOutput> {
+        Some(CommandOutput {
+            stdout: String::from(LOG_OUTPUT),
+            stderr: String::default(),
+        })
+    }
+
+    #[test]
+    fn show_nothing_on_empty_dir() -> io::Result<()> {
+        let repo_dir = tempfile::tempdir()?;
+        let command = display_command("jj", &jj::log_args(true));
+
+        let actual = ModuleRenderer::new("jj_change")
+            .path(repo_dir.path())
+            .cmd(&command, log_output())
+            .config(toml::toml! {
+                [jj_change]
+                disabled = false
+            })
+            .collect();
+
+        assert_eq!(None, actual);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn disabled_per_default() -> io::Result<()> {
+        let repo_dir = tempfile::tempdir()?;
+        fs::create_dir(repo_dir.path().join(".jj"))?;
+        let command = display_command("jj", &jj::log_args(true));
+
+        let actual = ModuleRenderer::new("jj_change")
+            .path(repo_dir.path())
+            .cmd(&command, log_output())
+            .collect();
+
+        assert_eq!(None, actual);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn shows_change_id_and_bookmarks() -> io::Result<()> {
+        let repo_dir = tempfile::tempdir()?;
+        fs::create_dir(repo_dir.path().join(".jj"))?;
+        fs::create_dir(repo_dir.path().join("src"))?;
+        let command = display_command("jj", &jj::log_args(true));
+
+        let actual = ModuleRenderer::new("jj_change")
+            .path(repo_dir.path().join("src"))
+            .cmd(&command, log_output())
+            .config(toml::toml! {
+                [jj_change]
+                disabled = false
+            })
+            .collect();
+
+        let expected = Some(format!(
+            "on {} ",
+            Color::Purple.bold().paint("@ kxqzwmpl main feature")
+        ));
+        assert_eq!(expected, actual);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn shows_commit_id_and_truncated_description() -> io::Result<()> {
+        let repo_dir = tempfile::tempdir()?;
+        fs::create_dir(repo_dir.path().join(".jj"))?;
+        let command = display_command("jj", &jj::log_args(false));
+
+        let actual = ModuleRenderer::new("jj_change")
+            .path(repo_dir.path())
+            .cmd(&command, log_output())
+            .config(toml::toml! {
+                [jj_change]
+                disabled = false
+                format = "$change_id $commit_id $description"
+                change_id_length = 4
+                commit_id_length = 0
+                truncation_length = 14
+                ignore_working_copy = false
+            })
+            .collect();
+
+        let expected = Some(String::from("kxqz 3f Add jj support…"));
+        assert_eq!(expected, actual);
+        repo_dir.close()
+    }
+}
@@ -0,0 +1,102 @@
+use super::utils::jj;
+use super::{Context, Module, ModuleConfig};
+
+use crate::configs::jj_status::JjStatusConfig;
+use crate::formatter::StringFormatter;
+
+/// Creates a module with the status of the Jujutsu working-copy change in the current directory
+///
+/// Will display whether the change is conflicted, divergent or empty
+pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
+    let mut module = context.new_module("jj_status");
+    let config = JjStatusConfig::try_load(module.config);
+
+    // As we default to disabled=true, we have to check here after loading our config module,
+    // before it was only checking against whatever is in the config starship.toml
+    if config.disabled {
+        return None;
+    };
+
+    jj::find_root(context)?;
+    let commit = context.get_jj_working_copy(config.ignore_working_copy)?;
+
+    let parsed = StringFormatter::new(config.format).and_then(|formatter| {
+        formatter
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(config.style)),
+                _ => None,
+            })
+            .map(|variable| match variable {
+                "conflicted" if commit.conflict => Some(Ok(config.conflicted)),
+                "divergent" if commit.divergent => Some(Ok(config.divergent)),
+                "empty" if commit.empty => Some(Ok(config.empty)),
+                _ => None,
+            })
+            .parse(None, Some(context))
+    });
+
+    module.set_segments(match parsed {
+        Ok(segments) => segments,
+        Err(error) => {
+            log::warn!("Error in module `jj_status`:\n{}", error);
+            return None;
+        }
+    });
+
+    Some(module)
+}
+
+#[cfg(test)]
+mod tests {
+    use nu_ansi_term::Color;
+    use std::fs;
+    use std::io;
+
+    use crate::modules::utils::jj;
+    use crate::test::ModuleRenderer;
+    use crate::utils::{display_command, CommandOutput};
+
+    fn render(flags: &str) -> io::Result<Option<String>> {
+        let repo_dir = tempfile::tempdir()?;
+        fs::create_dir(repo_dir.path().join(".jj"))?;
+        let command = display_command("jj", &jj::log_args(true));
+
+        let actual = ModuleRenderer::new("jj_status")
+            .path(repo_dir.path())
+            .cmd(
+                &command,
+                Some(CommandOutput {
+                    stdout: format!("kxqzwmpl\nk\n3f7d1c0a\n3f\nDescription\n\n{flags}\n"),
+                    stderr: String::default(),
+                }),
+            )
+            .config(toml::toml! {
+                [jj_status]
+                disabled = false
+            })
+            .collect();
+
+        repo_dir.close()?;
+        Ok(actual)
+    }
+
+    #[test]
+    fn shows_nothing_for_clean_change() -> io::Result<()> {
+        assert_eq!(None, render("")?);
+        Ok(())
+    }
+
+    #[test]
+    fn shows_empty_change() -> io::Result<()> {
+        let expected = Some(format!("{} ", Color::Red.bold().paint("[∅]")));
+        assert_eq!(expected, render("empty")?);
+        Ok(())
+    }
+
+    #[test]
+    fn shows_conflicted_and_divergent_change() -> io::Result<()> {
+        let expected = Some(format!("{} ", Color::Red.bold().paint("[=⇕]")));
+        assert_eq!(expected, render("conflict divergent")?);
+        Ok(())
+    }
+}
@@ -52,6 +52,57 @@ fn get_kube_ctx_components<T: DataValue>(
         })
 }
 
+/// Returns the host of the API server of the cluster, e.g. `api.example.com` for
+/// `https://api.example.com:6443`
+fn get_kube_server_host<T: DataValue>(document: &T, cluster_name: &str) -> Option<String> {
+    let server = document
+        .get("clusters")?
+        .as_array()?
+        .iter()
+        .find(|cluster| cluster.get("name").and_then(DataValue::as_str) == Some(cluster_name))?
+        .get("cluster")?
+        .get("server")
+        .and_then(DataValue::as_str)?;
+
+    let authority = server.split_once("://").map_or(server, |(_, rest)| rest);
+    let authority = authority.split('/').next().unwrap_or(authority);
+    let authority = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = if let Some(ipv6) = authority.strip_prefix('[') {
+        // Keep the brackets of IPv6 addresses, only strip the port
+        ipv6.split_once(']')
+            .map_or(authority, |(ip, _)| &authority[..ip.len() + 2])
+    } else {
+        authority.split(':').next().unwrap_or(authority)
+    };
+    (!host.is_empty()).then(|| host.to_string())
+}
+
+/// Whether the value matches the anchored regular expression, or there is no pattern at all
+fn matches_pattern(pattern: Option<&str>, value: Option<&str>) -> bool {
+    let Some(pattern) = pattern else {
+        return true;
+    };
+    let Some(value) = value else {
+        return false;
+    };
+    if value == pattern {
+        return true;
+    }
+    match regex::Regex::new(&format!("^(?:{pattern})$")) {
+        Ok(re) => re.is_match(value),
+        Err(error) => {
+            log::warn!(
+                "Could not compile regular expression `{}`:\n{}",
+                &format!("^(?:{pattern})$"),
+                error
+            );
+            false
+        }
+    }
+}
+
 fn get_aliased_name<'a>(
     pattern: Option<&'a str>,
     current_value: Option<&str>,
@@ -67,12 +118,12 @@ fn get_aliased_name<'a>(
     if value == pattern {
         return Some(replacement);
     }
-    let re = match regex::Regex::new(&format!("^{pattern}$")) {
+    let re = match regex::Regex::new(&format!("^(?:{pattern})$")) {
         Ok(re) => re,
         Err(error) => {
             log::warn!(
                 "Could not compile regular expression `{}`:\n{}",
-                &format!("^{pattern}$"),
+                &format!("^(?:{pattern})$"),
                 error
             );
             return None;
@@ -202,14 +253,21 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
             KubeCtxComponents::default()
         });
 
-    // Select the first style that matches the context_pattern and,
-    // if it is defined, the user_pattern
+    // Select the first style that matches the patterns that are defined out of
+    // context_pattern, user_pattern and namespace_pattern
     let (matched_context_config, display_context, display_user) = config
         .contexts
         .iter()
         .find_map(|context_config| {
+            if !matches_pattern(
+                context_config.namespace_pattern,
+                ctx_components.namespace.as_deref(),
+            ) {
+                return None;
+            }
+
             let context_alias = get_aliased_name(
-                Some(context_config.context_pattern),
+                context_config.context_pattern,
                 Some(current_kube_ctx_name),
                 context_config.context_alias,
             )?;
@@ -234,6 +292,15 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     let display_user =
         display_user.and_then(|user| deprecated::get_alias(user, &config.user_aliases, "user"));
 
+    let server_host = || {
+        let cluster = ctx_components.cluster.as_deref()?;
+        // Like contexts, the first file that defines the cluster wins
+        kubeconfigs.iter().find_map(|kubeconfig| match kubeconfig {
+            Document::Json(json) => get_kube_server_host(json, cluster),
+            Document::Yaml(yaml) => get_kube_server_host(yaml, cluster),
+        })
+    };
+
     let display_style = matched_context_config
         .and_then(|ctx_cfg| ctx_cfg.style)
         .unwrap_or(config.style);
@@ -264,6 +331,7 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                 "user" => display_user
                     .as_ref()
                     .map(|kube_user| Ok(Cow::Borrowed(kube_user.as_str()))),
+                "server" => server_host().map(|host| Ok(Cow::Owned(host))),
                 _ => None,
             })
             .parse(None, Some(context))
@@ -321,7 +389,7 @@ mod deprecated {
         } else {
             // regex match
             aliases.iter().find_map(|(k, v)| {
-                let re = regex::Regex::new(&format!("^{k}$")).ok()?;
+                let re = regex::Regex::new(&format!("^(?:{k})$")).ok()?;
                 let replaced = re.replace(current_value.as_str(), *v);
                 match replaced {
                     // We have a match if the replaced string is different from the original
@@ -350,7 +418,7 @@ mod deprecated {
 #[cfg(test)]
 mod tests {
     use crate::modules::kubernetes::parse_kubeconfigs;
-    use crate::modules::kubernetes::Document;
+    use crate::modules::kubernetes::{get_kube_server_host, parse_yaml, Document};
     use crate::test::ModuleRenderer;
     use nu_ansi_term::Color;
     use std::env;
@@ -1280,6 +1348,159 @@ users: []
         dir.close()
     }
 
+    #[test]
+    fn test_kube_server() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        let filename = dir.path().join("config");
+
+        let mut file = File::create(&filename)?;
+        file.write_all(
+            b"
+apiVersion: v1
+clusters:
+  - cluster:
+      server: https://other.example.com
+    name: other_cluster
+  - cluster:
+      certificate-authority-data: Zm9v
+      server: https://api.prod.example.com:6443
+    name: test_cluster
+contexts:
+  - context:
+      cluster: test_cluster
+      user: test_user
+    name: test_context
+current-context: test_context
+kind: Config
+preferences: {}
+users: []
+",
+        )?;
+        file.sync_all()?;
+
+        let actual = ModuleRenderer::new("kubernetes")
+            .path(dir.path())
+            .env("KUBECONFIG", filename.to_string_lossy().as_ref())
+            .config(toml::toml! {
+                [kubernetes]
+                format = "$context@$server"
+                disabled = false
+            })
+            .collect();
+
+        let expected = Some("test_context@api.prod.example.com".to_string());
+        assert_eq!(expected, actual);
+        dir.close()
+    }
+
+    #[test]
+    fn test_kube_server_host() {
+        let host = |server: &str| {
+            let yaml = parse_yaml(&format!(
+                "clusters: [{{name: c, cluster: {{server: '{server}'}}}}]"
+            ));
+            match yaml {
+                Some(Document::Yaml(yaml)) => get_kube_server_host(&yaml, "c"),
+                _ => None,
+            }
+        };
+
+        assert_eq!(host("https://10.0.0.1:6443"), Some("10.0.0.1".to_string()));
+        assert_eq!(host("https://[::1]:6443/path"), Some("[::1]".to_string()));
+        assert_eq!(
+            host("https://user@k8s.example.com/"),
+            Some("k8s.example.com".to_string())
+        );
+        assert_eq!(host("localhost:8080"), Some("localhost".to_string()));
+        assert_eq!(host(""), None);
+    }
+
+    fn base_test_namespace_pattern(namespace: &str, expected: &str) -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let filename = dir.path().join("config");
+        let mut file = File::create(&filename)?;
+        file.write_all(
+            format!(
+                "
+apiVersion: v1
+clusters: []
+contexts:
+  - context:
+      namespace: {namespace}
+    name: test_context
+current-context: test_context
+kind: Config
+preferences: {{}}
+users: []
+"
+            )
+            .as_bytes(),
+        )?;
+        file.sync_all()?;
+
+        let actual = ModuleRenderer::new("kubernetes")
+            .path(dir.path())
+            .env("KUBECONFIG", filename.to_string_lossy().as_ref())
+            .config(toml::toml! {
+                [kubernetes]
+                disabled = false
+                format = "[$symbol$context $namespace]($style)"
+
+                [[kubernetes.contexts]]
+                namespace_pattern = "kube-system|prod-.*"
+                style = "red"
+                symbol = "⚠ "
+
+                [[kubernetes.contexts]]
+                context_pattern = "test_context"
+                namespace_pattern = "dev"
+                context_alias = "dev"
+            })
+            .collect();
+
+        assert_eq!(Some(expected.to_string()), actual);
+        dir.close()
+    }
+
+    #[test]
+    fn test_config_context_namespace_pattern_matches_any_context() -> io::Result<()> {
+        base_test_namespace_pattern(
+            "prod-payments",
+            &Color::Red.paint("⚠ test_context prod-payments").to_string(),
+        )
+    }
+
+    #[test]
+    fn test_config_context_namespace_pattern_with_context_pattern() -> io::Result<()> {
+        base_test_namespace_pattern("dev", &Color::Cyan.bold().paint("☸ dev dev").to_string())
+    }
+
+    #[test]
+    fn test_config_context_namespace_pattern_does_not_match() -> io::Result<()> {
+        base_test_namespace_pattern(
+            "staging",
+            &Color::Cyan
+                .bold()
+                .paint("☸ test_context staging")
+                .to_string(),
+        )
+    }
+
+    #[test]
+    fn test_config_context_namespace_pattern_alternatives_are_anchored() -> io::Result<()> {
+        for namespace in ["preprod-x", "kube-system-x"] {
+            base_test_namespace_pattern(
+                namespace,
+                &Color::Cyan
+                    .bold()
+                    .paint(format!("☸ test_context {namespace}"))
+                    .to_string(),
+            )?;
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_config_context_both_pattern_must_match() -> io::Result<()> {
         let dir = tempfile::tempdir()?;
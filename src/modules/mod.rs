@@ -33,6 +33,7 @@ mod git_commit;
 mod git_metrics;
 mod git_state;
 mod git_status;
+mod git_worktree;
 mod gleam;
 mod golang;
 mod gradle;
@@ -41,8 +42,11 @@ mod haskell;
 mod haxe;
 mod helm;
 mod hg_branch;
+mod hg_status;
 mod hostname;
 mod java;
+mod jj_change;
+mod jj_status;
 mod jobs;
 mod julia;
 mod kotlin;
@@ -86,7 +90,7 @@ mod swift;
 mod terraform;
 mod time;
 mod username;
-mod utils;
+pub(crate) mod utils;
 mod vagrant;
 mod vcsh;
 mod vlang;
@@ -144,6 +148,7 @@ pub fn handle<'a>(module: &str, context: &'a Context) -> Option<Module<'a>> {
             "git_metrics" => git_metrics::module(context),
             "git_state" => git_state::module(context),
             "git_status" => git_status::module(context),
+            "git_worktree" => git_worktree::module(context),
             "gleam" => gleam::module(context),
             "golang" => golang::module(context),
             "gradle" => gradle::module(context),
@@ -152,8 +157,11 @@ pub fn handle<'a>(module: &str, context: &'a Context) -> Option<Module<'a>> {
             "haxe" => haxe::module(context),
             "helm" => helm::module(context),
             "hg_branch" => hg_branch::module(context),
+            "hg_status" => hg_status::module(context),
             "hostname" => hostname::module(context),
             "java" => java::module(context),
+            "jj_change" => jj_change::module(context),
+            "jj_status" => jj_status::module(context),
             "jobs" => jobs::module(context),
             "julia" => julia::module(context),
             "kotlin" => kotlin::module(context),
@@ -266,6 +274,9 @@ pub fn description(module: &str) -> &'static str {
         "git_metrics" => "The currently added/deleted lines in your repo",
         "git_state" => "The current git operation, and it's progress",
         "git_status" => "Symbol representing the state of the repo",
+        "git_worktree" => {
+            "The linked worktree or the superproject of the submodule in your current directory"
+        }
         "gleam" => "The currently installed version of Gleam",
         "golang" => "The currently installed version of Golang",
         "gradle" => "The currently installed version of Gradle",
@@ -274,8 +285,13 @@ pub fn description(module: &str) -> &'static str {
         "haxe" => "The currently installed version of Haxe",
         "helm" => "The currently installed version of Helm",
         "hg_branch" => "The active branch and topic of the repo in your current directory",
+        "hg_status" => "Symbol representing the state of the Mercurial repo",
         "hostname" => "The system hostname",
         "java" => "The currently installed version of Java",
+        "jj_change" => "The working-copy change of the Jujutsu repo in your current directory",
+        "jj_status" => {
+            "Whether the working-copy change of the Jujutsu repo is conflicted, divergent or empty"
+        }
         "jobs" => "The current number of jobs running",
         "julia" => "The currently installed version of Julia",
         "kotlin" => "The currently installed version of Kotlin",
@@ -2,13 +2,16 @@ use super::{Context, Module, ModuleConfig};
 
 use crate::configs::nodejs::NodejsConfig;
 use crate::formatter::{StringFormatter, VersionFormatter};
+use crate::utils;
 
 use once_cell::sync::Lazy;
 use regex::Regex;
 use semver::Version;
 use semver::VersionReq;
 use serde_json as json;
+use std::fs;
 use std::ops::Deref;
+use std::path::{Path, PathBuf};
 
 /// Creates a module with the current Node.js version
 pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
@@ -30,11 +33,17 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
         return None;
     }
 
-    let nodejs_version = Lazy::new(|| {
+    let running_version = Lazy::new(|| {
         context
-            .exec_cmd("node", &["--version"])
+            .exec_version_cmd("node", &["--version"])
             .map(|cmd| cmd.stdout)
     });
+    let resolved_version = Lazy::new(|| get_resolved_version(context));
+    // Avoid spawning `node` when a version manager already pins the version
+    let nodejs_version = Lazy::new(|| match resolved_version.deref() {
+        Some(version) if config.resolve_version => Some(version.clone()),
+        _ => running_version.clone(),
+    });
     let engines_version = Lazy::new(|| get_engines_version(context));
 
     let parsed = StringFormatter::new(config.format).and_then(|formatter| {
@@ -82,6 +91,13 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
 
                     (!in_engines_range).then_some(Ok(eng_ver))
                 }
+                "mismatch" => {
+                    let resolved = resolved_version.as_deref()?;
+                    let running = running_version.as_deref()?;
+
+                    (!is_same_version(running, resolved))
+                        .then(|| Ok(config.mismatch_symbol.to_string()))
+                }
                 _ => None,
             })
             .parse(None, Some(context))
@@ -116,12 +132,11 @@ fn check_engines_version(nodejs_version: Option<&str>, engines_version: Option<&
     };
 
     let re = Regex::new(r"\d+\.\d+\.\d+").unwrap();
-    let version = re
-        .captures(nodejs_version)
-        .unwrap()
-        .get(0)
-        .unwrap()
-        .as_str();
+    // A partially resolved version such as `20` can't be checked against a requirement
+    let Some(version) = re.find(nodejs_version) else {
+        return true;
+    };
+    let version = version.as_str();
 
     let v = match Version::parse(version) {
         Ok(v) => v,
@@ -130,6 +145,191 @@ fn check_engines_version(nodejs_version: Option<&str>, engines_version: Option<&
     r.matches(&v)
 }
 
+/// Finds the version a Node.js version manager would select for the current directory
+fn get_resolved_version(context: &Context) -> Option<String> {
+    let requested = get_requested_version(context)?;
+    resolve_version_spec(context, &requested, 0)
+}
+
+/// Reads the closest version pinned by Volta, `.nvmrc`, `.node-version` or `.tool-versions`
+fn get_requested_version(context: &Context) -> Option<String> {
+    let mut scan = context.begin_ancestor_scan().set_files(&[
+        "package.json",
+        ".nvmrc",
+        ".node-version",
+        ".tool-versions",
+    ]);
+    loop {
+        let dir = scan.scan()?;
+        let version = get_volta_version(dir)
+            .or_else(|| read_version_file(&dir.join(".nvmrc")))
+            .or_else(|| read_version_file(&dir.join(".node-version")))
+            .or_else(|| get_tool_versions_version(dir));
+        if version.is_some() {
+            return version;
+        }
+        // None of the files pin a version, like a `package.json` without `volta`
+        scan = scan.set_path(dir.parent()?);
+    }
+}
+
+fn get_volta_version(dir: &Path) -> Option<String> {
+    let json_str = utils::read_file(dir.join("package.json")).ok()?;
+    let package_json: json::Value = json::from_str(&json_str).ok()?;
+    let version = package_json.get("volta")?.get("node")?.as_str()?;
+
+    Some(version.to_string())
+}
+
+/// Reads the first non-comment line of a version or nvm alias file
+fn read_version_file(path: &Path) -> Option<String> {
+    let contents = utils::read_file(path).ok()?;
+    contents
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+}
+
+/// Reads the `nodejs` (asdf) or `node` (mise) entry of a `.tool-versions` file
+fn get_tool_versions_version(dir: &Path) -> Option<String> {
+    let contents = utils::read_file(dir.join(".tool-versions")).ok()?;
+    contents.lines().find_map(|line| {
+        let line = line.split('#').next()?;
+        let mut parts = line.split_whitespace();
+        // Only the first of multiple versions is the one that gets used
+        match parts.next()? {
+            "nodejs" | "node" => parts.next().map(str::to_string),
+            _ => None,
+        }
+    })
+}
+
+/// Resolves a version spec such as `20`, `lts/iron` or `default` to an installed version,
+/// following nvm and fnm aliases. Partial versions that aren't installed are kept as-is.
+fn resolve_version_spec(context: &Context, spec: &str, depth: u8) -> Option<String> {
+    let version = spec.trim_start_matches('v');
+    if let Some(parts) = parse_version_parts(version) {
+        if parts.len() == 3 {
+            return Some(version.to_string());
+        }
+        return Some(
+            find_installed_version(context, &parts)
+                .map_or_else(|| version.to_string(), |installed| installed.to_string()),
+        );
+    }
+
+    match spec {
+        "system" => None,
+        "node" | "stable" | "latest" | "current" => {
+            find_installed_version(context, &[]).map(|installed| installed.to_string())
+        }
+        // Aliases may point to other aliases, e.g. `default` -> `lts/*` -> `v20.11.0`
+        _ if depth < 5 => {
+            let target = get_alias_target(context, spec)?;
+            resolve_version_spec(context, &target, depth + 1)
+        }
+        _ => None,
+    }
+}
+
+fn parse_version_parts(version: &str) -> Option<Vec<u64>> {
+    let parts = version
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<u64>>>()?;
+
+    (1..=3).contains(&parts.len()).then_some(parts)
+}
+
+/// Finds the highest installed version starting with the given version parts
+fn find_installed_version(context: &Context, prefix: &[u64]) -> Option<Version> {
+    get_install_dirs(context)
+        .into_iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            Version::parse(name.to_str()?.trim_start_matches('v')).ok()
+        })
+        .filter(|installed| {
+            prefix
+                .iter()
+                .zip([installed.major, installed.minor, installed.patch])
+                .all(|(expected, part)| *expected == part)
+        })
+        .max()
+}
+
+/// Directories holding one subdirectory per installed version, for each supported manager
+fn get_install_dirs(context: &Context) -> Vec<PathBuf> {
+    let data_dir = get_dir(context, "XDG_DATA_HOME", ".local/share");
+    [
+        get_dir(context, "NVM_DIR", ".nvm").map(|dir| dir.join("versions").join("node")),
+        get_fnm_dir(context, data_dir.as_deref()).map(|dir| dir.join("node-versions")),
+        get_dir(context, "VOLTA_HOME", ".volta").map(|dir| dir.join("tools/image/node")),
+        get_dir(context, "ASDF_DATA_DIR", ".asdf").map(|dir| dir.join("installs").join("nodejs")),
+        context
+            .get_env("MISE_DATA_DIR")
+            .map(PathBuf::from)
+            .or_else(|| Some(data_dir.as_deref()?.join("mise")))
+            .map(|dir| dir.join("installs").join("node")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Looks up an nvm alias file or an fnm alias symlink
+fn get_alias_target(context: &Context, alias: &str) -> Option<String> {
+    if alias.split('/').any(|part| part.is_empty() || part == "..") {
+        return None;
+    }
+
+    let nvm_alias = get_dir(context, "NVM_DIR", ".nvm").map(|dir| dir.join("alias").join(alias));
+    if let Some(target) = nvm_alias.and_then(|path| read_version_file(&path)) {
+        return Some(target);
+    }
+
+    // fnm aliases link to the installation, and name `lts/iron` as `lts-iron`
+    let data_dir = get_dir(context, "XDG_DATA_HOME", ".local/share");
+    let aliases = get_fnm_dir(context, data_dir.as_deref())?.join("aliases");
+    [alias.to_string(), alias.replace('/', "-")]
+        .iter()
+        .find_map(|name| {
+            let target = fs::read_link(aliases.join(name)).ok()?;
+            target.components().find_map(|component| {
+                let version = component.as_os_str().to_str()?.strip_prefix('v')?;
+                Version::parse(version).ok().map(|_| version.to_string())
+            })
+        })
+}
+
+fn get_fnm_dir(context: &Context, data_dir: Option<&Path>) -> Option<PathBuf> {
+    context
+        .get_env("FNM_DIR")
+        .map(PathBuf::from)
+        .or_else(|| Some(data_dir?.join("fnm")))
+}
+
+/// Reads a directory from an environment variable, or falls back to one in the home directory
+fn get_dir(context: &Context, env_var: &str, home_relative: &str) -> Option<PathBuf> {
+    context
+        .get_env(env_var)
+        .map(PathBuf::from)
+        .or_else(|| Some(context.get_home()?.join(home_relative)))
+}
+
+/// Checks the running version against a possibly partial resolved version
+fn is_same_version(running: &str, resolved: &str) -> bool {
+    let running = running.trim().trim_start_matches('v');
+    resolved
+        .split('.')
+        .zip(running.split('.'))
+        .all(|(expected, part)| expected == part)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::test::ModuleRenderer;
@@ -372,4 +572,145 @@ mod tests {
         assert_eq!(expected, actual);
         dir.close()
     }
+
+    #[test]
+    fn resolve_version_from_nvmrc_without_running_node() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join(".nvmrc"), "v20.11.0\n")?;
+
+        let actual = ModuleRenderer::new("nodejs")
+            .path(dir.path())
+            .config(toml::toml! {
+                [nodejs]
+                resolve_version = true
+            })
+            .cmd("node --version", None)
+            .collect();
+        let expected = Some(format!("via {}", Color::Green.bold().paint(" v20.11.0 ")));
+        assert_eq!(expected, actual);
+        dir.close()
+    }
+
+    #[test]
+    fn resolve_partial_version_from_installed_versions() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let nvm_dir = tempfile::tempdir()?;
+        fs::write(dir.path().join(".node-version"), "20")?;
+        for version in ["v18.19.0", "v20.1.0", "v20.11.0"] {
+            fs::create_dir_all(nvm_dir.path().join("versions/node").join(version))?;
+        }
+
+        let actual = ModuleRenderer::new("nodejs")
+            .path(dir.path())
+            .env("NVM_DIR", nvm_dir.path().to_string_lossy())
+            .config(toml::toml! {
+                [nodejs]
+                resolve_version = true
+            })
+            .collect();
+        let expected = Some(format!("via {}", Color::Green.bold().paint(" v20.11.0 ")));
+        assert_eq!(expected, actual);
+        nvm_dir.close()?;
+        dir.close()
+    }
+
+    #[test]
+    fn resolve_nvm_lts_alias() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let nvm_dir = tempfile::tempdir()?;
+        fs::write(dir.path().join(".nvmrc"), "lts/iron")?;
+        fs::create_dir_all(nvm_dir.path().join("alias/lts"))?;
+        fs::write(nvm_dir.path().join("alias/lts/iron"), "v20.11.0")?;
+
+        let actual = ModuleRenderer::new("nodejs")
+            .path(dir.path())
+            .env("NVM_DIR", nvm_dir.path().to_string_lossy())
+            .config(toml::toml! {
+                [nodejs]
+                resolve_version = true
+            })
+            .collect();
+        let expected = Some(format!("via {}", Color::Green.bold().paint(" v20.11.0 ")));
+        assert_eq!(expected, actual);
+        nvm_dir.close()?;
+        dir.close()
+    }
+
+    #[test]
+    fn resolve_version_from_volta_in_parent_dir() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(
+            dir.path().join("package.json"),
+            r#"{ "volta": { "node": "18.19.0" } }"#,
+        )?;
+        let sub_dir = dir.path().join("packages/app");
+        fs::create_dir_all(&sub_dir)?;
+        File::create(sub_dir.join("package.json"))?.sync_all()?;
+
+        let actual = ModuleRenderer::new("nodejs")
+            .path(&sub_dir)
+            .config(toml::toml! {
+                [nodejs]
+                resolve_version = true
+            })
+            .collect();
+        let expected = Some(format!("via {}", Color::Green.bold().paint(" v18.19.0 ")));
+        assert_eq!(expected, actual);
+        dir.close()
+    }
+
+    #[test]
+    fn resolve_version_from_tool_versions() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        File::create(dir.path().join("index.js"))?.sync_all()?;
+        fs::write(
+            dir.path().join(".tool-versions"),
+            "python 3.12.1\nnodejs 21.6.1 20.11.0 # fallback\n",
+        )?;
+
+        let actual = ModuleRenderer::new("nodejs")
+            .path(dir.path())
+            .config(toml::toml! {
+                [nodejs]
+                resolve_version = true
+            })
+            .collect();
+        let expected = Some(format!("via {}", Color::Green.bold().paint(" v21.6.1 ")));
+        assert_eq!(expected, actual);
+        dir.close()
+    }
+
+    #[test]
+    fn show_mismatch_when_running_node_differs() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join(".nvmrc"), "20")?;
+
+        let actual = ModuleRenderer::new("nodejs")
+            .path(dir.path())
+            .config(toml::toml! {
+                [nodejs]
+                format = "via [$symbol($version )($mismatch )]($style)"
+            })
+            .collect();
+        let expected = Some(format!("via {}", Color::Green.bold().paint(" v12.0.0 ≠ ")));
+        assert_eq!(expected, actual);
+        dir.close()
+    }
+
+    #[test]
+    fn do_not_show_mismatch_when_running_node_matches() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join(".nvmrc"), "v12")?;
+
+        let actual = ModuleRenderer::new("nodejs")
+            .path(dir.path())
+            .config(toml::toml! {
+                [nodejs]
+                format = "via [$symbol($version )($mismatch )]($style)"
+            })
+            .collect();
+        let expected = Some(format!("via {}", Color::Green.bold().paint(" v12.0.0 ")));
+        assert_eq!(expected, actual);
+        dir.close()
+    }
 }
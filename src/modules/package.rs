@@ -1,20 +1,65 @@
 use super::{Context, Module, ModuleConfig};
 use crate::configs::package::PackageConfig;
 use crate::formatter::{StringFormatter, VersionFormatter};
+use crate::utils::read_file;
 
 use ini::Ini;
+use once_cell::sync::OnceCell;
+use path_slash::PathExt as _;
 use quick_xml::events::Event as QXEvent;
 use quick_xml::Reader as QXReader;
 use regex::Regex;
 use serde_json as json;
-use std::fs;
-use std::io::Read;
+use std::path::Path;
+
+/// The files the package name and version are read from, used to find the closest
+/// package when `search_upwards` is enabled
+const MANIFEST_FILES: &[&str] = &[
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "setup.cfg",
+    "composer.json",
+    "gradle.properties",
+    "build.gradle",
+    "Project.toml",
+    "mix.exs",
+    "Chart.yaml",
+    "pom.xml",
+    "meson.build",
+    "shard.yml",
+    "v.mod",
+    "vpkg.json",
+    "build.sbt",
+    "daml.yaml",
+    "pubspec.yaml",
+    "DESCRIPTION",
+    "go.mod",
+];
 
 /// Creates a module with the current package version
 pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     let mut module = context.new_module("package");
     let config: PackageConfig = PackageConfig::try_load(module.config);
-    let module_version = get_version(context, &config)?;
+
+    let dir = if config.search_upwards {
+        context
+            .begin_ancestor_scan()
+            .set_files(MANIFEST_FILES)
+            .scan()
+            .unwrap_or(&context.current_dir)
+    } else {
+        &context.current_dir
+    };
+    let dir = ManifestDir { context, path: dir };
+    let package = get_package(&dir, &config)?;
+
+    let workspace = OnceCell::new();
+    let get_workspace = || {
+        workspace
+            .get_or_init(|| get_workspace_name(dir.path))
+            .as_ref()
+    };
 
     let parsed = StringFormatter::new(config.format).and_then(|formatter| {
         formatter
@@ -27,7 +72,9 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                 _ => None,
             })
             .map(|variable| match variable {
-                "version" => Some(Ok(&module_version)),
+                "version" => package.version.as_ref().map(Ok),
+                "name" => package.name.as_ref().map(Ok),
+                "workspace" => get_workspace().map(Ok),
                 _ => None,
             })
             .parse(None, Some(context))
@@ -44,8 +91,46 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     Some(module)
 }
 
-fn get_node_package_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    let file_contents = context.read_file_from_pwd("package.json")?;
+/// The directory the package manifests are read from
+struct ManifestDir<'a> {
+    context: &'a Context<'a>,
+    path: &'a Path,
+}
+
+impl<'a> ManifestDir<'a> {
+    fn is_current_dir(&self) -> bool {
+        self.path == self.context.current_dir
+    }
+
+    fn read(&self, file_name: &str) -> Option<String> {
+        if self.is_current_dir() {
+            self.context.read_file_from_pwd(file_name)
+        } else {
+            read_file(self.path.join(file_name)).ok()
+        }
+    }
+}
+
+/// The name and formatted version of a package, at least one of which is known
+struct Package {
+    name: Option<String>,
+    version: Option<String>,
+}
+
+impl Package {
+    fn new(name: Option<&str>, version: Option<String>) -> Option<Self> {
+        let name = name.filter(|name| !name.is_empty()).map(String::from);
+        (name.is_some() || version.is_some()).then_some(Self { name, version })
+    }
+
+    /// Packages which can't be shown without a version
+    fn versioned(name: Option<&str>, raw_version: &str, version_format: &str) -> Option<Self> {
+        Self::new(name, Some(format_version(raw_version, version_format)?))
+    }
+}
+
+fn get_node_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    let file_contents = dir.read("package.json")?;
     let package_json: json::Value = json::from_str(&file_contents).ok()?;
 
     if !config.display_private
@@ -54,6 +139,7 @@ fn get_node_package_version(context: &Context, config: &PackageConfig) -> Option
         return None;
     }
 
+    let name = package_json.get("name").and_then(json::Value::as_str);
     let raw_version = package_json.get("version")?.as_str()?;
     if raw_version == "null" {
         return None;
@@ -62,10 +148,10 @@ fn get_node_package_version(context: &Context, config: &PackageConfig) -> Option
     let formatted_version = format_version(raw_version, config.version_format)?;
     if formatted_version == "v0.0.0-development" || formatted_version.starts_with("v0.0.0-semantic")
     {
-        return Some("semantic".to_string());
+        return Package::new(name, Some("semantic".to_string()));
     };
 
-    Some(formatted_version)
+    Package::new(name, Some(formatted_version))
 }
 
 fn get_poetry_version(pyproject: &toml::Table) -> Option<&str> {
@@ -80,100 +166,129 @@ fn get_pep621_version(pyproject: &toml::Table) -> Option<&str> {
     pyproject.get("project")?.get("version")?.as_str()
 }
 
-fn get_pyproject_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    let file_contents = context.read_file_from_pwd("pyproject.toml")?;
+fn get_pyproject_name(pyproject: &toml::Table) -> Option<&str> {
+    pyproject
+        .get("project")
+        .and_then(|project| project.get("name"))
+        .or_else(|| pyproject.get("tool")?.get("poetry")?.get("name"))?
+        .as_str()
+}
+
+fn get_pyproject_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    let file_contents = dir.read("pyproject.toml")?;
     let pyproject_toml: toml::Table = toml::from_str(&file_contents).ok()?;
 
-    get_pep621_version(&pyproject_toml)
-        .or_else(|| get_poetry_version(&pyproject_toml))
-        .and_then(|raw_version| format_version(raw_version, config.version_format))
+    let raw_version =
+        get_pep621_version(&pyproject_toml).or_else(|| get_poetry_version(&pyproject_toml))?;
+    Package::versioned(
+        get_pyproject_name(&pyproject_toml),
+        raw_version,
+        config.version_format,
+    )
 }
 
-fn get_setup_cfg_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    let file_contents = context.read_file_from_pwd("setup.cfg")?;
+fn get_setup_cfg_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    let file_contents = dir.read("setup.cfg")?;
     let ini = Ini::load_from_str(&file_contents).ok()?;
     let raw_version = ini.get_from(Some("metadata"), "version")?;
 
     if raw_version.starts_with("attr:") || raw_version.starts_with("file:") {
         None
     } else {
-        format_version(raw_version, config.version_format)
+        Package::versioned(
+            ini.get_from(Some("metadata"), "name"),
+            raw_version,
+            config.version_format,
+        )
     }
 }
 
-fn get_gradle_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    context
-        .read_file_from_pwd("gradle.properties")
+fn get_gradle_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    dir
+        .read("gradle.properties")
         .and_then(|contents| {
             let re = Regex::new(r"(?m)^\s*version\s*=\s*(?P<version>.*)").unwrap();
             let caps = re.captures(&contents)?;
-            format_version(&caps["version"], config.version_format)
+            Package::versioned(None, &caps["version"], config.version_format)
         }).or_else(|| {
-            let build_file_contents = context.read_file_from_pwd("build.gradle")?;
+            let build_file_contents = dir.read("build.gradle")?;
             let re = Regex::new(r#"(?m)^version( |\s*=\s*)['"](?P<version>[^'"]+)['"]$"#).unwrap(); /*dark magic*/
             let caps = re.captures(&build_file_contents)?;
-            format_version(&caps["version"], config.version_format)
+            Package::versioned(None, &caps["version"], config.version_format)
 
         })
 }
 
-fn get_composer_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    let file_contents = context.read_file_from_pwd("composer.json")?;
+fn get_composer_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    let file_contents = dir.read("composer.json")?;
     let composer_json: json::Value = json::from_str(&file_contents).ok()?;
     let raw_version = composer_json.get("version")?.as_str()?;
+    let name = composer_json.get("name").and_then(json::Value::as_str);
 
-    format_version(raw_version, config.version_format)
+    Package::versioned(name, raw_version, config.version_format)
 }
 
-fn get_julia_project_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    let file_contents = context.read_file_from_pwd("Project.toml")?;
+fn get_julia_project_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    let file_contents = dir.read("Project.toml")?;
     let project_toml: toml::Table = toml::from_str(&file_contents).ok()?;
     let raw_version = project_toml.get("version")?.as_str()?;
+    let name = project_toml.get("name").and_then(toml::Value::as_str);
 
-    format_version(raw_version, config.version_format)
+    Package::versioned(name, raw_version, config.version_format)
 }
 
-fn get_helm_package_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    let file_contents = context.read_file_from_pwd("Chart.yaml")?;
+fn get_helm_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    let file_contents = dir.read("Chart.yaml")?;
     let yaml = yaml_rust2::YamlLoader::load_from_str(&file_contents).ok()?;
-    let version = yaml.first()?["version"].as_str()?;
+    let chart = yaml.first()?;
+    let version = chart["version"].as_str()?;
 
-    format_version(version, config.version_format)
+    Package::versioned(chart["name"].as_str(), version, config.version_format)
 }
 
-fn get_mix_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    let file_contents = context.read_file_from_pwd("mix.exs")?;
+fn get_mix_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    let file_contents = dir.read("mix.exs")?;
     let re = Regex::new(r#"(?m)version: "(?P<version>[^"]+)""#).unwrap();
     let caps = re.captures(&file_contents)?;
+    let name_re = Regex::new(r"(?m)app: :(?P<name>\w+)").unwrap();
+    let name = name_re
+        .captures(&file_contents)
+        .and_then(|caps| caps.name("name"))
+        .map(|name| name.as_str());
 
-    format_version(&caps["version"], config.version_format)
+    Package::versioned(name, &caps["version"], config.version_format)
 }
 
-fn get_maven_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    let file_contents = context.read_file_from_pwd("pom.xml")?;
+fn get_maven_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    let file_contents = dir.read("pom.xml")?;
     let mut reader = QXReader::from_str(&file_contents);
     reader.config_mut().trim_text(true);
 
     let mut buf = vec![];
     let mut in_ver = false;
+    let mut in_artifact_id = false;
+    let mut artifact_id = None;
+    let mut version = None;
     let mut depth = 0;
     loop {
         match reader.read_event_into(&mut buf) {
             Ok(QXEvent::Start(ref e)) => {
                 in_ver = depth == 1 && e.name().as_ref() == b"version";
+                in_artifact_id = depth == 1 && e.name().as_ref() == b"artifactId";
                 depth += 1;
             }
             Ok(QXEvent::End(_)) => {
                 in_ver = false;
+                in_artifact_id = false;
                 depth -= 1;
             }
-            Ok(QXEvent::Text(t)) if in_ver => {
-                let ver = t.unescape().ok().map(std::borrow::Cow::into_owned);
-                return match ver {
-                    // Ignore version which is just a property reference
-                    Some(ref v) if !v.starts_with('$') => format_version(v, config.version_format),
-                    _ => None,
-                };
+            Ok(QXEvent::Text(t)) if in_ver || in_artifact_id => {
+                let text = t.unescape().ok().map(std::borrow::Cow::into_owned);
+                if in_artifact_id {
+                    artifact_id = text;
+                } else {
+                    version = Some(text);
+                }
             }
             Ok(QXEvent::Eof) => break,
             Ok(_) => (),
@@ -185,48 +300,65 @@ fn get_maven_version(context: &Context, config: &PackageConfig) -> Option<String
         }
     }
 
-    None
+    match version? {
+        // Ignore version which is just a property reference
+        Some(ref v) if !v.starts_with('$') => {
+            Package::versioned(artifact_id.as_deref(), v, config.version_format)
+        }
+        _ => None,
+    }
 }
 
-fn get_meson_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    let file_contents = context
-        .read_file_from_pwd("meson.build")?
+fn get_meson_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    let file_contents = dir
+        .read("meson.build")?
         .split_ascii_whitespace()
         .collect::<String>();
 
     let re = Regex::new(r"project\([^())]*,version:'(?P<version>[^']+)'[^())]*\)").unwrap();
     let caps = re.captures(&file_contents)?;
 
-    format_version(&caps["version"], config.version_format)
+    Package::versioned(None, &caps["version"], config.version_format)
 }
 
-fn get_vmod_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    let file_contents = context.read_file_from_pwd("v.mod")?;
+fn get_vmod_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    let file_contents = dir.read("v.mod")?;
     let re = Regex::new(r"(?m)^\s*version\s*:\s*'(?P<version>[^']+)'").unwrap();
     let caps = re.captures(&file_contents)?;
-    format_version(&caps["version"], config.version_format)
+    let name_re = Regex::new(r"(?m)^\s*name\s*:\s*'(?P<name>[^']+)'").unwrap();
+    let name = name_re
+        .captures(&file_contents)
+        .and_then(|caps| caps.name("name"))
+        .map(|name| name.as_str());
+    Package::versioned(name, &caps["version"], config.version_format)
 }
 
-fn get_vpkg_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    let file_contents = context.read_file_from_pwd("vpkg.json")?;
+fn get_vpkg_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    let file_contents = dir.read("vpkg.json")?;
     let vpkg_json: json::Value = json::from_str(&file_contents).ok()?;
     let raw_version = vpkg_json.get("version")?.as_str()?;
+    let name = vpkg_json.get("name").and_then(json::Value::as_str);
 
-    format_version(raw_version, config.version_format)
+    Package::versioned(name, raw_version, config.version_format)
 }
 
-fn get_sbt_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    let file_contents = context.read_file_from_pwd("build.sbt")?;
+fn get_sbt_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    let file_contents = dir.read("build.sbt")?;
     let re = Regex::new(r"(?m)^(.*/)*\s*version\s*:=\s*.(?P<version>[\d\.]+)").unwrap();
     let caps = re.captures(&file_contents)?;
-    format_version(&caps["version"], config.version_format)
+    Package::versioned(None, &caps["version"], config.version_format)
 }
 
-fn get_cargo_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    let mut file_contents = context.read_file_from_pwd("Cargo.toml")?;
+fn get_cargo_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    let file_contents = dir.read("Cargo.toml")?;
 
-    let mut cargo_toml: toml::Table = toml::from_str(&file_contents).ok()?;
+    let cargo_toml: toml::Table = toml::from_str(&file_contents).ok()?;
+    let name = cargo_toml
+        .get("package")
+        .and_then(|p| p.get("name"))
+        .and_then(toml::Value::as_str);
     let cargo_version = cargo_toml.get("package").and_then(|p| p.get("version"));
+    let workspace_toml: toml::Table;
     let raw_version = if let Some(v) = cargo_version.and_then(toml::Value::as_str) {
         // regular version string
         v
@@ -237,23 +369,19 @@ fn get_cargo_version(context: &Context, config: &PackageConfig) -> Option<String
     {
         // workspace version string (`package.version.worspace = true`)
         // need to read the Cargo.toml file from the workspace root
-        let mut version = None;
-        // discover the workspace root
-        for path in context.current_dir.ancestors().skip(1) {
-            // Assume the workspace root is the first ancestor that contains a Cargo.toml file
-            if let Ok(mut file) = fs::File::open(path.join("Cargo.toml")) {
-                file.read_to_string(&mut file_contents).ok()?;
-                cargo_toml = toml::from_str(&file_contents).ok()?;
-                // Read workspace.package.version
-                version = cargo_toml
-                    .get("workspace")?
-                    .get("package")?
-                    .get("version")?
-                    .as_str();
-                break;
-            }
-        }
-        version?
+        // Assume the workspace root is the first ancestor that contains a Cargo.toml file
+        let workspace_contents = dir
+            .path
+            .ancestors()
+            .skip(1)
+            .find_map(|path| read_file(path.join("Cargo.toml")).ok())?;
+        workspace_toml = toml::from_str(&workspace_contents).ok()?;
+        // Read workspace.package.version
+        workspace_toml
+            .get("workspace")?
+            .get("package")?
+            .get("version")?
+            .as_str()?
     } else {
         // This might be a workspace file
         cargo_toml
@@ -263,84 +391,238 @@ fn get_cargo_version(context: &Context, config: &PackageConfig) -> Option<String
             .as_str()?
     };
 
-    format_version(raw_version, config.version_format)
+    Package::versioned(name, raw_version, config.version_format)
 }
 
-fn get_nimble_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    if !context
-        .try_begin_scan()?
-        .set_extensions(&["nimble"])
-        .is_match()
+fn get_nimble_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    // `nimble` reads the package in the directory it's run in
+    if !dir.is_current_dir()
+        || !dir
+            .context
+            .try_begin_scan()?
+            .set_extensions(&["nimble"])
+            .is_match()
     {
         return None;
     };
 
-    let cmd_output = context.exec_cmd("nimble", &["dump", "--json"])?;
+    let cmd_output = dir.context.exec_cmd("nimble", &["dump", "--json"])?;
     let nimble_json: json::Value = json::from_str(&cmd_output.stdout).ok()?;
 
     let raw_version = nimble_json.get("version")?.as_str()?;
 
-    format_version(raw_version, config.version_format)
+    Package::versioned(None, raw_version, config.version_format)
 }
 
-fn get_shard_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    let file_contents = context.read_file_from_pwd("shard.yml")?;
+fn get_shard_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    let file_contents = dir.read("shard.yml")?;
 
     let data = yaml_rust2::YamlLoader::load_from_str(&file_contents).ok()?;
-    let raw_version = data.first()?["version"].as_str()?;
+    let shard = data.first()?;
+    let raw_version = shard["version"].as_str()?;
 
-    format_version(raw_version, config.version_format)
+    Package::versioned(shard["name"].as_str(), raw_version, config.version_format)
 }
 
-fn get_daml_project_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    let file_contents = context.read_file_from_pwd("daml.yaml")?;
+fn get_daml_project_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    let file_contents = dir.read("daml.yaml")?;
 
     let daml_yaml = yaml_rust2::YamlLoader::load_from_str(&file_contents).ok()?;
-    let raw_version = daml_yaml.first()?["version"].as_str()?;
+    let project = daml_yaml.first()?;
+    let raw_version = project["version"].as_str()?;
 
-    format_version(raw_version, config.version_format)
+    Package::versioned(project["name"].as_str(), raw_version, config.version_format)
 }
 
-fn get_dart_pub_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    let file_contents = context.read_file_from_pwd("pubspec.yaml")?;
+fn get_dart_pub_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    let file_contents = dir.read("pubspec.yaml")?;
 
     let data = yaml_rust2::YamlLoader::load_from_str(&file_contents).ok()?;
-    let raw_version = data.first()?["version"].as_str()?;
+    let pubspec = data.first()?;
+    let raw_version = pubspec["version"].as_str()?;
 
-    format_version(raw_version, config.version_format)
+    Package::versioned(pubspec["name"].as_str(), raw_version, config.version_format)
 }
 
-fn get_rlang_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    let file_contents = context.read_file_from_pwd("DESCRIPTION")?;
+fn get_rlang_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    let file_contents = dir.read("DESCRIPTION")?;
     let re = Regex::new(r"(?m)^Version:\s*(?P<version>.*$)").unwrap();
     let caps = re.captures(&file_contents)?;
-    format_version(&caps["version"], config.version_format)
+    let name_re = Regex::new(r"(?m)^Package:\s*(?P<name>\S+)").unwrap();
+    let name = name_re
+        .captures(&file_contents)
+        .and_then(|caps| caps.name("name"))
+        .map(|name| name.as_str());
+    Package::versioned(name, &caps["version"], config.version_format)
+}
+
+/// Go modules are versioned by tags of their repository, so only the module path is known
+fn get_go_module(dir: &ManifestDir, _config: &PackageConfig) -> Option<Package> {
+    let file_contents = dir.read("go.mod")?;
+    let re = Regex::new(r#"(?m)^\s*module\s+"?(?P<path>[^\s"]+)"?"#).unwrap();
+    let caps = re.captures(&file_contents)?;
+    Package::new(Some(&caps["path"]), None)
+}
+
+fn get_package(dir: &ManifestDir, config: &PackageConfig) -> Option<Package> {
+    let package_fn: Vec<fn(&ManifestDir, &PackageConfig) -> Option<Package>> = vec![
+        get_cargo_package,
+        get_nimble_package,
+        get_node_package,
+        get_pyproject_package,
+        get_setup_cfg_package,
+        get_composer_package,
+        get_gradle_package,
+        get_julia_project_package,
+        get_mix_package,
+        get_helm_package,
+        get_maven_package,
+        get_meson_package,
+        get_shard_package,
+        get_vmod_package,
+        get_vpkg_package,
+        get_sbt_package,
+        get_daml_project_package,
+        get_dart_pub_package,
+        get_rlang_package,
+        get_go_module,
+    ];
+
+    package_fn.iter().find_map(|f| f(dir, config))
+}
+
+/// The root of a Cargo, npm/pnpm/yarn or uv workspace
+struct Workspace {
+    name: Option<String>,
+    members: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl Workspace {
+    /// Whether the package at `path` is one of the members of the workspace at `root`
+    fn has_member(&self, root: &Path, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(root) else {
+            return false;
+        };
+        let relative = relative.to_slash_lossy();
+        let matches = |pattern: &String| {
+            let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+            gix::glob::wildmatch(
+                pattern.into(),
+                relative.as_ref().into(),
+                gix::glob::wildmatch::Mode::NO_MATCH_SLASH_LITERAL,
+            )
+        };
+        self.members.iter().any(matches) && !self.exclude.iter().any(matches)
+    }
+}
+
+type WorkspaceFn = fn(&Path) -> Option<Workspace>;
+
+fn toml_strings(value: Option<&toml::Value>) -> Vec<String> {
+    value
+        .and_then(toml::Value::as_array)
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|value| value.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn get_cargo_workspace(root: &Path) -> Option<Workspace> {
+    let cargo_toml: toml::Table = toml::from_str(&read_file(root.join("Cargo.toml")).ok()?).ok()?;
+    let workspace = cargo_toml.get("workspace")?;
+    Some(Workspace {
+        name: cargo_toml
+            .get("package")
+            .and_then(|p| p.get("name"))
+            .and_then(toml::Value::as_str)
+            .map(String::from),
+        members: toml_strings(workspace.get("members")),
+        exclude: toml_strings(workspace.get("exclude")),
+    })
 }
 
-fn get_version(context: &Context, config: &PackageConfig) -> Option<String> {
-    let package_version_fn: Vec<fn(&Context, &PackageConfig) -> Option<String>> = vec![
-        get_cargo_version,
-        get_nimble_version,
-        get_node_package_version,
-        get_pyproject_version,
-        get_setup_cfg_version,
-        get_composer_version,
-        get_gradle_version,
-        get_julia_project_version,
-        get_mix_version,
-        get_helm_package_version,
-        get_maven_version,
-        get_meson_version,
-        get_shard_version,
-        get_vmod_version,
-        get_vpkg_version,
-        get_sbt_version,
-        get_daml_project_version,
-        get_dart_pub_version,
-        get_rlang_version,
+fn get_uv_workspace(root: &Path) -> Option<Workspace> {
+    let pyproject: toml::Table =
+        toml::from_str(&read_file(root.join("pyproject.toml")).ok()?).ok()?;
+    let workspace = pyproject.get("tool")?.get("uv")?.get("workspace")?;
+    Some(Workspace {
+        name: get_pyproject_name(&pyproject).map(String::from),
+        members: toml_strings(workspace.get("members")),
+        exclude: toml_strings(workspace.get("exclude")),
+    })
+}
+
+/// Reads the packages of `pnpm-workspace.yaml` or the `workspaces` of `package.json`,
+/// where patterns starting with `!` exclude packages
+fn get_node_workspace(root: &Path) -> Option<Workspace> {
+    let package_json = read_file(root.join("package.json"))
+        .ok()
+        .and_then(|contents| json::from_str::<json::Value>(&contents).ok());
+    let name = package_json
+        .as_ref()
+        .and_then(|package| package.get("name")?.as_str())
+        .map(String::from);
+
+    let patterns: Vec<String> = if let Ok(contents) = read_file(root.join("pnpm-workspace.yaml")) {
+        let yaml = yaml_rust2::YamlLoader::load_from_str(&contents).ok()?;
+        yaml.first()?["packages"]
+            .as_vec()?
+            .iter()
+            .filter_map(|pattern| pattern.as_str().map(String::from))
+            .collect()
+    } else {
+        let workspaces = package_json.as_ref()?.get("workspaces")?;
+        // Yarn also allows `{ "packages": [...], "nohoist": [...] }`
+        let workspaces = workspaces.get("packages").unwrap_or(workspaces);
+        workspaces
+            .as_array()?
+            .iter()
+            .filter_map(|pattern| pattern.as_str().map(String::from))
+            .collect()
+    };
+
+    let (exclude, members) = patterns
+        .into_iter()
+        .partition::<Vec<_>, _>(|pattern| pattern.starts_with('!'));
+    Some(Workspace {
+        name,
+        members,
+        exclude: exclude
+            .into_iter()
+            .map(|pattern| pattern[1..].to_string())
+            .collect(),
+    })
+}
+
+/// Returns the name of the workspace the package in `path` is a member of, or the name of the
+/// directory of the workspace if it has no package of its own
+fn get_workspace_name(path: &Path) -> Option<String> {
+    // The manifest of members of each kind of workspace, and how to read its root
+    let workspace_fns: [(&str, WorkspaceFn); 3] = [
+        ("Cargo.toml", get_cargo_workspace),
+        ("package.json", get_node_workspace),
+        ("pyproject.toml", get_uv_workspace),
     ];
 
-    package_version_fn.iter().find_map(|f| f(context, config))
+    workspace_fns
+        .iter()
+        .filter(|(manifest, _)| path.join(manifest).is_file())
+        .find_map(|(_, get_workspace)| {
+            path.ancestors().skip(1).find_map(|root| {
+                let workspace = get_workspace(root)?;
+                if !workspace.has_member(root, path) {
+                    return None;
+                }
+                workspace.name.or_else(|| {
+                    root.file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                })
+            })
+        })
 }
 
 fn format_version(version: &str, version_format: &str) -> Option<String> {
@@ -358,7 +640,7 @@ mod tests {
     use super::*;
     use crate::{test::ModuleRenderer, utils::CommandOutput};
     use nu_ansi_term::Color;
-    use std::fs::File;
+    use std::fs::{self, File};
     use std::io;
     use std::io::Write;
     use tempfile::TempDir;
@@ -1442,6 +1724,160 @@ Title: Starship
         expect_output(&project_dir, Some("v1.0.0"), None);
         project_dir.close()
     }
+    #[test]
+    fn test_extract_package_names() -> io::Result<()> {
+        let manifests = [
+            (
+                "package.json",
+                r#"{"name": "@acme/app", "version": "1.0.0"}"#,
+                "@acme/app",
+            ),
+            (
+                "pyproject.toml",
+                "[tool.poetry]\nname = \"acme\"\nversion = \"1.0.0\"\n",
+                "acme",
+            ),
+            (
+                "pom.xml",
+                "<project><parent><artifactId>parent</artifactId></parent>\
+                 <artifactId>acme-core</artifactId><version>1.0.0</version></project>",
+                "acme-core",
+            ),
+            ("DESCRIPTION", "Package: acme\nVersion: 1.0.0\n", "acme"),
+        ];
+        for (file_name, contents, name) in manifests {
+            let project_dir = create_project_dir()?;
+            fill_config(&project_dir, file_name, Some(contents))?;
+            let actual = ModuleRenderer::new("package")
+                .path(project_dir.path())
+                .config(toml::toml! {
+                    [package]
+                    format = "$name@$version"
+                })
+                .collect();
+            assert_eq!(actual, Some(format!("{name}@v1.0.0")), "{file_name}");
+            project_dir.close()?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_go_module_has_no_version() -> io::Result<()> {
+        let project_dir = create_project_dir()?;
+        fill_config(
+            &project_dir,
+            "go.mod",
+            Some("module github.com/starship/starship\n\ngo 1.22\n"),
+        )?;
+
+        // Nothing to show with the default format
+        expect_output(&project_dir, None, None);
+        let actual = ModuleRenderer::new("package")
+            .path(project_dir.path())
+            .config(toml::toml! {
+                [package]
+                format = "$name( $version)"
+            })
+            .collect();
+        assert_eq!(actual.as_deref(), Some("github.com/starship/starship"));
+        project_dir.close()
+    }
+
+    fn render_package(path: &std::path::Path, search_upwards: bool) -> Option<String> {
+        ModuleRenderer::new("package")
+            .path(path)
+            .config(toml::toml! {
+                [package]
+                format = "$name $version( in $workspace)"
+                search_upwards = search_upwards
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cargo_workspace_member_upwards() -> io::Result<()> {
+        let project_dir = create_project_dir()?;
+        fs::create_dir_all(project_dir.path().join("crates/core/src"))?;
+        fill_config(
+            &project_dir,
+            "Cargo.toml",
+            Some("[workspace]\nmembers = [\"crates/*\"]\n"),
+        )?;
+        fill_config(
+            &project_dir,
+            "crates/core/Cargo.toml",
+            Some("[package]\nname = \"acme-core\"\nversion = \"0.2.0\"\n"),
+        )?;
+        let src = project_dir.path().join("crates/core/src");
+        let workspace_name = project_dir.path().file_name().unwrap().to_string_lossy();
+
+        assert_eq!(render_package(&src, false), None);
+        assert_eq!(
+            render_package(&src, true),
+            Some(format!("acme-core v0.2.0 in {workspace_name}"))
+        );
+        project_dir.close()
+    }
+
+    #[test]
+    fn test_pnpm_workspace_members() -> io::Result<()> {
+        let project_dir = create_project_dir()?;
+        for dir in ["packages/app", "packages/internal", "tools/cli"] {
+            fs::create_dir_all(project_dir.path().join(dir))?;
+            fill_config(
+                &project_dir,
+                &format!("{dir}/package.json"),
+                Some(&format!(r#"{{"name": "{dir}", "version": "1.0.0"}}"#)),
+            )?;
+        }
+        fill_config(
+            &project_dir,
+            "package.json",
+            Some(r#"{"name": "monorepo", "private": true}"#),
+        )?;
+        fill_config(
+            &project_dir,
+            "pnpm-workspace.yaml",
+            Some("packages:\n  - 'packages/*'\n  - '!packages/internal'\n"),
+        )?;
+
+        let render = |dir: &str| render_package(&project_dir.path().join(dir), true);
+        assert_eq!(
+            render("packages/app").as_deref(),
+            Some("packages/app v1.0.0 in monorepo")
+        );
+        assert_eq!(
+            render("packages/internal").as_deref(),
+            Some("packages/internal v1.0.0")
+        );
+        assert_eq!(render("tools/cli").as_deref(), Some("tools/cli v1.0.0"));
+        // The private workspace root has no package to show
+        assert_eq!(render(".").as_deref(), None);
+        project_dir.close()
+    }
+
+    #[test]
+    fn test_uv_workspace_member() -> io::Result<()> {
+        let project_dir = create_project_dir()?;
+        fs::create_dir_all(project_dir.path().join("mono/libs/utils"))?;
+        fill_config(
+            &project_dir,
+            "mono/pyproject.toml",
+            Some("[tool.uv.workspace]\nmembers = [\"libs/*\"]\n"),
+        )?;
+        fill_config(
+            &project_dir,
+            "mono/libs/utils/pyproject.toml",
+            Some("[project]\nname = \"utils\"\nversion = \"0.1.0\"\n"),
+        )?;
+
+        assert_eq!(
+            render_package(&project_dir.path().join("mono/libs/utils"), false).as_deref(),
+            Some("utils v0.1.0 in mono")
+        );
+        project_dir.close()
+    }
+
     fn create_project_dir() -> io::Result<TempDir> {
         tempfile::tempdir()
     }
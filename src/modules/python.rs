@@ -1,5 +1,5 @@
 use ini::Ini;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use super::{Context, Module, ModuleConfig};
 use crate::configs::python::PythonConfig;
@@ -7,7 +7,8 @@ use crate::formatter::StringFormatter;
 use crate::formatter::VersionFormatter;
 use crate::utils::get_command_string_output;
 
-/// Creates a module with the current Python version and, if active, virtual environment.
+/// Creates a module with the current Python version and, if active or found in the project,
+/// virtual environment.
 pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     let mut module = context.new_module("python");
     let config: PythonConfig = PythonConfig::try_load(module.config);
@@ -25,6 +26,11 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
         return None;
     };
 
+    let active_venv = context.get_env("VIRTUAL_ENV").map(PathBuf::from);
+    let venv = active_venv
+        .clone()
+        .or_else(|| find_project_venv(context, &config));
+
     let pyenv_prefix = if config.pyenv_version_name {
         config.pyenv_prefix
     } else {
@@ -46,7 +52,18 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                     if config.pyenv_version_name {
                         return get_pyenv_version(context).map(Ok);
                     }
-                    let python_version = get_python_version(context, &config)?;
+                    // Prefer versions that can be read without running Python
+                    let python_version = venv
+                        .as_deref()
+                        .and_then(get_venv_python_version)
+                        .or_else(|| {
+                            // An active venv's Python is run, whatever `.python-version` says
+                            active_venv
+                                .is_none()
+                                .then(|| get_python_version_file(context))
+                                .flatten()
+                        })
+                        .or_else(|| get_python_version(context, &config))?;
                     VersionFormatter::format_module_version(
                         module.get_name(),
                         &python_version,
@@ -55,9 +72,10 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                     .map(Ok)
                 }
                 "virtualenv" => {
-                    let virtual_env = get_python_virtual_env(context);
+                    let virtual_env = venv.as_deref().and_then(get_python_virtual_env);
                     virtual_env.as_ref().map(|e| Ok(e.trim().to_string()))
                 }
+                "tool" => get_python_tool(context).map(|tool| Ok(tool.to_string())),
                 "pyenv_prefix" => Some(Ok(pyenv_prefix.to_string())),
                 _ => None,
             })
@@ -96,7 +114,7 @@ fn get_python_version(context: &Context, config: &PythonConfig) -> Option<String
         .python_binary
         .0
         .iter()
-        .find_map(|binary| context.exec_cmd(binary, &["--version"]))
+        .find_map(|binary| context.exec_version_cmd(binary, &["--version"]))
         .map(get_command_string_output)?;
 
     parse_python_version(&version)
@@ -112,16 +130,97 @@ fn parse_python_version(python_version_string: &str) -> Option<String> {
     Some(version.to_string())
 }
 
-fn get_python_virtual_env(context: &Context) -> Option<String> {
-    context.get_env("VIRTUAL_ENV").and_then(|venv| {
-        get_prompt_from_venv(Path::new(&venv)).or_else(|| {
-            Path::new(&venv)
-                .file_name()
-                .map(|filename| String::from(filename.to_str().unwrap_or("")))
-        })
+fn get_python_virtual_env(venv: &Path) -> Option<String> {
+    get_prompt_from_venv(venv).or_else(|| {
+        venv.file_name()
+            .map(|filename| String::from(filename.to_str().unwrap_or("")))
     })
 }
 
+/// Finds a virtual environment that isn't activated in the project, like the `.venv` created
+/// by uv, in the current directory or one of its parents
+fn find_project_venv(context: &Context, config: &PythonConfig) -> Option<PathBuf> {
+    let home = context.get_home();
+    context
+        .begin_ancestor_scan()
+        .set_folders(&config.venv_folders)
+        .scan()
+        // A venv in the home directory isn't specific to any project
+        .filter(|dir| Some(*dir) != home.as_deref())
+        .and_then(|dir| {
+            config
+                .venv_folders
+                .iter()
+                .map(|folder| dir.join(folder))
+                .find(|venv| venv.join("pyvenv.cfg").is_file())
+        })
+}
+
+/// Reads the version of the venv's Python from its `pyvenv.cfg`, which is `version` for the
+/// `venv` module and `version_info` (e.g. `3.12.1.final.0`) for uv and virtualenv
+fn get_venv_python_version(venv: &Path) -> Option<String> {
+    let cfg = Ini::load_from_file_noescape(venv.join("pyvenv.cfg")).ok()?;
+    let section = cfg.general_section();
+    let version = section
+        .get("version")
+        .or_else(|| section.get("version_info"))?;
+    let version: Vec<&str> = version.trim().split('.').take(3).collect();
+    (!version.is_empty() && version.iter().all(|part| part.parse::<u32>().is_ok()))
+        .then(|| version.join("."))
+}
+
+/// Reads the version requested in the closest `.python-version`, as used by pyenv and uv,
+/// if it's a plain version number rather than e.g. `system` or the name of a pyenv virtualenv
+fn get_python_version_file(context: &Context) -> Option<String> {
+    let dir = context
+        .begin_ancestor_scan()
+        .set_files(&[".python-version"])
+        .scan()?;
+    let contents = crate::utils::read_file(dir.join(".python-version")).ok()?;
+    // Only the first of multiple versions is the one `python` runs
+    let version = contents
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))?;
+    let version = version
+        .strip_prefix("cpython-")
+        .or_else(|| version.strip_prefix("cpython@"))
+        .unwrap_or(version);
+    version
+        .split('.')
+        .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+        .then(|| version.to_string())
+}
+
+/// Detects the tool managing the project from its lockfile or its `pyproject.toml`
+fn get_python_tool(context: &Context) -> Option<&'static str> {
+    const LOCKFILES: [(&str, &str); 4] = [
+        ("uv.lock", "uv"),
+        ("poetry.lock", "poetry"),
+        ("pdm.lock", "pdm"),
+        ("hatch.toml", "hatch"),
+    ];
+    let lockfiles = LOCKFILES.map(|(file, _)| file);
+    if let Some(dir) = context.begin_ancestor_scan().set_files(&lockfiles).scan() {
+        return LOCKFILES
+            .iter()
+            .find(|(file, _)| dir.join(file).is_file())
+            .map(|(_, tool)| *tool);
+    }
+
+    let dir = context
+        .begin_ancestor_scan()
+        .set_files(&["pyproject.toml"])
+        .scan()?;
+    let pyproject: toml::Table =
+        toml::from_str(&crate::utils::read_file(dir.join("pyproject.toml")).ok()?).ok()?;
+    let tools = pyproject.get("tool")?;
+    LOCKFILES
+        .iter()
+        .map(|(_, tool)| *tool)
+        .find(|tool| tools.get(tool).is_some())
+}
+
 fn get_prompt_from_venv(venv_path: &Path) -> Option<String> {
     Ini::load_from_file_noescape(venv_path.join("pyvenv.cfg"))
         .ok()?
@@ -452,6 +551,143 @@ prompt = foo\nbar
         dir.close()
     }
 
+    #[test]
+    fn with_project_venv() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        create_dir_all(dir.path().join(".venv"))?;
+        create_dir_all(dir.path().join("src/pkg"))?;
+        File::create(dir.path().join("src/pkg/__init__.py"))?.sync_all()?;
+        std::fs::write(
+            dir.path().join(".venv/pyvenv.cfg"),
+            "home = /usr/bin\nimplementation = CPython\nuv = 0.4.0\n\
+             version_info = 3.12.4\nprompt = my-project\n",
+        )?;
+
+        // The venv is found from subdirectories, and Python isn't run for its version
+        let actual = ModuleRenderer::new("python")
+            .path(dir.path().join("src/pkg"))
+            .cmd("python --version", None)
+            .collect();
+
+        let expected = Some(format!(
+            "via {}",
+            Color::Yellow.bold().paint("🐍 v3.12.4 (my-project) ")
+        ));
+
+        assert_eq!(actual, expected);
+        dir.close()
+    }
+
+    #[test]
+    fn with_active_venv_version() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        create_dir_all(dir.path().join("env"))?;
+        create_dir_all(dir.path().join(".venv"))?;
+        std::fs::write(
+            dir.path().join("env/pyvenv.cfg"),
+            "home = /usr/bin\nversion_info = 3.11.9.final.0\n",
+        )?;
+        std::fs::write(dir.path().join(".venv/pyvenv.cfg"), "version = 3.12.4\n")?;
+
+        let actual = ModuleRenderer::new("python")
+            .path(dir.path())
+            .env("VIRTUAL_ENV", dir.path().join("env").to_str().unwrap())
+            .collect();
+
+        let expected = Some(format!(
+            "via {}",
+            Color::Yellow.bold().paint("🐍 v3.11.9 (env) ")
+        ));
+
+        assert_eq!(actual, expected);
+        dir.close()
+    }
+
+    #[test]
+    fn with_project_venv_disabled() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        create_dir_all(dir.path().join(".venv"))?;
+        File::create(dir.path().join("main.py"))?.sync_all()?;
+        std::fs::write(dir.path().join(".venv/pyvenv.cfg"), "version = 3.12.4\n")?;
+
+        let actual = ModuleRenderer::new("python")
+            .path(dir.path())
+            .config(toml::toml! {
+                [python]
+                venv_folders = []
+            })
+            .collect();
+
+        let expected = Some(format!("via {}", Color::Yellow.bold().paint("🐍 v3.8.0 ")));
+
+        assert_eq!(actual, expected);
+        dir.close()
+    }
+
+    #[test]
+    fn with_python_version_file() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        create_dir_all(dir.path().join("sub"))?;
+        File::create(dir.path().join("sub/main.py"))?.sync_all()?;
+
+        let render = |contents: &str| -> io::Result<Option<String>> {
+            std::fs::write(dir.path().join(".python-version"), contents)?;
+            Ok(ModuleRenderer::new("python")
+                .path(dir.path().join("sub"))
+                .config(toml::toml! {
+                    [python]
+                    format = "$version"
+                })
+                .collect())
+        };
+
+        assert_eq!(render("3.13\n")?.as_deref(), Some("v3.13"));
+        assert_eq!(
+            render("# pinned\n3.11.2\n3.10.1\n")?.as_deref(),
+            Some("v3.11.2")
+        );
+        assert_eq!(render("cpython@3.12\n")?.as_deref(), Some("v3.12"));
+        // Versions that aren't plain numbers are left to `python --version`
+        assert_eq!(render("my-virtualenv\n")?.as_deref(), Some("v3.8.0"));
+        assert_eq!(render("pypy3.10\n")?.as_deref(), Some("v3.8.0"));
+        dir.close()
+    }
+
+    #[test]
+    fn with_tool() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        create_dir_all(dir.path().join("sub"))?;
+        File::create(dir.path().join("sub/main.py"))?.sync_all()?;
+
+        let render = || {
+            ModuleRenderer::new("python")
+                .path(dir.path().join("sub"))
+                .config(toml::toml! {
+                    [python]
+                    format = "($tool)"
+                })
+                .collect()
+        };
+
+        assert_eq!(render(), None);
+
+        std::fs::write(
+            dir.path().join("pyproject.toml"),
+            "[project]\nname = \"foo\"\n\n[tool.hatch.envs.default]\n",
+        )?;
+        assert_eq!(render().as_deref(), Some("hatch"));
+
+        for (lockfile, tool) in [
+            ("pdm.lock", "pdm"),
+            ("poetry.lock", "poetry"),
+            ("uv.lock", "uv"),
+        ] {
+            File::create(dir.path().join(lockfile))?.sync_all()?;
+            assert_eq!(render().as_deref(), Some(tool));
+        }
+        dir.close()
+    }
+
     fn check_python2_renders(dir: &tempfile::TempDir, starship_config: Option<toml::Table>) {
         let config = starship_config.unwrap_or(toml::toml! {
             [python]
@@ -9,7 +9,6 @@ use super::{Context, Module, ModuleConfig};
 
 use crate::configs::rust::RustConfig;
 use crate::formatter::{StringFormatter, VersionFormatter};
-use crate::utils::create_command;
 use home::rustup_home;
 
 use once_cell::sync::OnceCell;
@@ -104,7 +103,7 @@ impl RustToolingEnvironmentInfo {
                     })
                     .and_then(|rustc| {
                         log::trace!("Running rustc --version directly with {:?}", rustc);
-                        create_command(rustc).map(|mut cmd| {
+                        context.create_command(rustc).map(|mut cmd| {
                             cmd.arg("--version");
                             cmd
                         })
@@ -113,7 +112,7 @@ impl RustToolingEnvironmentInfo {
                         // If that fails, try running rustup rustup run <toolchain> rustc --version
                         // Depending on the source of the toolchain override, it might not have been a full toolchain name ("stable" or "nightly").
                         log::trace!("Running rustup {toolchain} rustc --version");
-                        create_command("rustup").map(|mut cmd| {
+                        context.create_command("rustup").map(|mut cmd| {
                             cmd.args(["run", toolchain, "rustc", "--version"]);
                             cmd
                         })
@@ -136,7 +135,8 @@ impl RustToolingEnvironmentInfo {
 
         self.rustc_verbose_output
             .get_or_init(|| {
-                let Output { status, stdout, .. } = create_command("rustc")
+                let Output { status, stdout, .. } = context
+                    .create_command("rustc")
                     .and_then(|mut cmd| {
                         cmd.args(["-Vv"]).current_dir(&context.current_dir).output()
                     })
@@ -378,7 +378,7 @@ fn extract_toolchain_from_rustup_run_rustc_version(output: Output) -> RustupRunR
 
 fn execute_rustc_version(context: &Context) -> Option<String> {
     context
-        .exec_cmd("rustc", &["--version"])
+        .exec_version_cmd("rustc", &["--version"])
         .map(|o| o.stdout)
         .filter(|s| !s.is_empty())
 }
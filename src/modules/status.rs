@@ -830,7 +830,7 @@ mod tests {
             .pipestatus(pipe_exit_code)
             .width(100);
         let context = crate::modules::Context::from(renderer);
-        let actual = crate::print::get_prompt(context);
+        let actual = crate::print::get_prompt(&context);
 
         let mut escaping = false;
         let mut width = 0;
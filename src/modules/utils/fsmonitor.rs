@@ -0,0 +1,314 @@
+use gix::bstr::{BString, ByteSlice};
+use gix::dir::walk::EmissionMode;
+use sha1::{Digest, Sha1};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::context::{self, Context};
+use crate::utils::{encode_to_hex, exec_timeout};
+
+/// Above this many changed paths, checking every file is about as fast as matching them
+const MAX_CHANGED_PATHS: usize = 1000;
+
+/// The point in time the filesystem monitor was last asked about, stored in the index
+enum Token {
+    V1(u64),
+    V2(String),
+}
+
+/// Asks the filesystem monitor of the repository which files might have changed since
+/// the index was last written.
+///
+/// Both the `core.fsmonitor` hook and watchman (through `$WATCHMAN_SOCK`) are supported.
+/// Returns the changed paths relative to the root of the worktree, or `None` if every file
+/// has to be checked, because no monitor is configured or it can't tell what changed.
+pub fn changed_paths(context: &Context, repo: &context::Repo) -> Option<Vec<BString>> {
+    let workdir = repo.workdir.as_ref()?;
+    let git_repo = repo.open();
+    let index = git_repo.index_or_empty().ok()?;
+    let data = std::fs::read(git_repo.index_path()).ok()?;
+    let Some((token, dirty)) = read_extension(&data, git_repo.object_hash().len_in_bytes()) else {
+        log::debug!("The index has no fsmonitor extension, checking all files");
+        return None;
+    };
+
+    let timeout = Duration::from_millis(context.root_config.command_timeout);
+    let mut paths = match hook_path(&git_repo, workdir) {
+        Some(hook) => query_hook(context, &hook, workdir, &token, timeout)?,
+        None => query_watchman(context, workdir, &token, timeout)?,
+    };
+
+    // Entries the monitor was unsure about when the index was written
+    let entries = index.entries();
+    dirty.for_each_set_bit(|idx| {
+        paths.push(entries.get(idx)?.path(&index).to_owned());
+        Some(())
+    })?;
+
+    if paths.len() > MAX_CHANGED_PATHS {
+        log::debug!("fsmonitor reported {} changed paths", paths.len());
+        return None;
+    }
+    paths.sort();
+    paths.dedup();
+    Some(paths)
+}
+
+/// Identifies everything untracked files depend on besides the worktree: the index, the
+/// exclude files of the repository and how untracked directories are shown.
+///
+/// The monitor reports every path that changed since the index was written, so untracked
+/// files found with the same key only have to be checked again at the reported paths.
+pub fn untracked_key(
+    repo: &gix::Repository,
+    index: &gix::index::File,
+    mode: EmissionMode,
+) -> Option<String> {
+    let mut hasher = Sha1::new();
+    hasher.update(index.checksum()?.as_bytes());
+    hasher.update(format!("{mode:?}"));
+    let excludes_file = repo
+        .config_snapshot()
+        .trusted_path("core.excludesFile")
+        .and_then(Result::ok)
+        .map(|path| path.into_owned());
+    let info_exclude = repo.git_dir().join("info").join("exclude");
+    for path in [Some(info_exclude), excludes_file].into_iter().flatten() {
+        if let Ok(modified) = std::fs::metadata(&path).and_then(|m| m.modified()) {
+            hasher.update(format!("{path:?}{modified:?}"));
+        }
+    }
+    Some(encode_to_hex(&hasher.finalize()))
+}
+
+/// Returns the `core.fsmonitor` hook, unless it's unset or a boolean for the builtin daemon
+fn hook_path(repo: &gix::Repository, workdir: &Path) -> Option<PathBuf> {
+    let value = repo.config_snapshot().string("core.fsmonitor")?;
+    if value.is_empty() || gix::config::Boolean::try_from(value.as_ref()).is_ok() {
+        return None;
+    }
+    // Like git, relative hooks are run from the root of the worktree
+    Some(workdir.join(gix::path::from_bstr(value.as_bstr())))
+}
+
+/// Runs the hook with the `query-fsmonitor` protocol of the token's version
+fn query_hook(
+    context: &Context,
+    hook: &Path,
+    workdir: &Path,
+    token: &Token,
+    timeout: Duration,
+) -> Option<Vec<BString>> {
+    let mut command = context.create_command(hook).ok()?;
+    command.current_dir(workdir);
+    match token {
+        Token::V1(nanos) => command.args(["1", &nanos.to_string()]),
+        Token::V2(token) => command.args(["2", token]),
+    };
+    let output = exec_timeout(&mut command, timeout)?;
+
+    let mut fields = output.stdout.split('\0').filter(|field| !field.is_empty());
+    if let Token::V2(_) = token {
+        // The first field is the token for the next query
+        fields.next()?;
+    }
+    let paths: Vec<BString> = fields.map(BString::from).collect();
+    if paths.iter().any(|path| path == "/") {
+        log::debug!("fsmonitor hook reported that everything changed");
+        return None;
+    }
+    Some(paths)
+}
+
+#[cfg(unix)]
+fn query_watchman(
+    context: &Context,
+    workdir: &Path,
+    token: &Token,
+    timeout: Duration,
+) -> Option<Vec<BString>> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let socket = context.get_env_os("WATCHMAN_SOCK")?;
+    let since = match token {
+        Token::V1(nanos) => serde_json::json!(nanos / 1_000_000_000),
+        Token::V2(clock) => serde_json::json!(clock),
+    };
+    let query = serde_json::json!([
+        "query",
+        workdir,
+        {
+            "since": since,
+            "fields": ["name"],
+            "expression": ["not", ["dirname", ".git"]],
+        }
+    ]);
+
+    let mut stream = UnixStream::connect(socket)
+        .map_err(|e| log::debug!("Unable to connect to watchman: {e}"))
+        .ok()?;
+    stream.set_read_timeout(Some(timeout)).ok()?;
+    writeln!(stream, "{query}").ok()?;
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response).ok()?;
+
+    let response: serde_json::Value = serde_json::from_str(&response).ok()?;
+    if let Some(error) = response.get("error") {
+        log::debug!("watchman query failed: {error}");
+        return None;
+    }
+    if response["is_fresh_instance"].as_bool() != Some(false) {
+        log::debug!("watchman can't tell what changed since {since}");
+        return None;
+    }
+    response["files"]
+        .as_array()?
+        .iter()
+        .map(|name| name.as_str().map(BString::from))
+        .collect()
+}
+
+#[cfg(not(unix))]
+fn query_watchman(
+    _context: &Context,
+    _workdir: &Path,
+    _token: &Token,
+    _timeout: Duration,
+) -> Option<Vec<BString>> {
+    None
+}
+
+/// Reads the token and the entries marked as dirty from the `FSMN` extension of the index
+fn read_extension(data: &[u8], hash_len: usize) -> Option<(Token, gix_bitmap::ewah::Vec)> {
+    let read_u32 = |offset: usize| -> Option<u32> {
+        Some(u32::from_be_bytes(
+            data.get(offset..offset + 4)?.try_into().ok()?,
+        ))
+    };
+    if data.get(..4)? != b"DIRC" {
+        return None;
+    }
+    let version = read_u32(4)?;
+    let entries = read_u32(8)?;
+
+    // Skip the entries, the extensions come after them
+    let mut offset = 12;
+    let mut previous_path_len: usize = 0;
+    for _ in 0..entries {
+        // ctime, mtime, dev, ino, mode, uid, gid and size are followed by the hash and flags
+        let flags_offset = offset + 40 + hash_len;
+        let flags = u16::from_be_bytes(data.get(flags_offset..flags_offset + 2)?.try_into().ok()?);
+        offset = flags_offset + 2;
+        if flags & 0x4000 != 0 {
+            offset += 2;
+        }
+        let path_len = if version == 4 {
+            // The path is stored as the number of bytes to remove from the previous path,
+            // followed by the suffix to append
+            let mut strip = 0usize;
+            loop {
+                let byte = *data.get(offset)?;
+                offset += 1;
+                strip = (strip << 7) | usize::from(byte & 0x7f);
+                if byte & 0x80 == 0 {
+                    break;
+                }
+                strip += 1;
+            }
+            let suffix = data.get(offset..)?.find_byte(0)?;
+            offset += suffix + 1;
+            previous_path_len.checked_sub(strip)? + suffix
+        } else {
+            let entry_start = flags_offset - 40 - hash_len;
+            let path_len = data.get(offset..)?.find_byte(0)?;
+            // Entries are padded with NUL bytes to a multiple of eight bytes
+            offset = entry_start + ((offset - entry_start + path_len + 8) & !7);
+            path_len
+        };
+        previous_path_len = path_len;
+    }
+
+    while offset + 8 <= data.len().checked_sub(hash_len)? {
+        let signature = data.get(offset..offset + 4)?;
+        let size = read_u32(offset + 4)? as usize;
+        let extension = data.get(offset + 8..offset + 8 + size)?;
+        if signature == b"FSMN" {
+            return parse_fsmonitor(extension);
+        }
+        offset += 8 + size;
+    }
+    None
+}
+
+fn parse_fsmonitor(data: &[u8]) -> Option<(Token, gix_bitmap::ewah::Vec)> {
+    let version = u32::from_be_bytes(data.get(..4)?.try_into().ok()?);
+    let (token, data) = match version {
+        1 => {
+            let nanos = u64::from_be_bytes(data.get(4..12)?.try_into().ok()?);
+            (Token::V1(nanos), data.get(12..)?)
+        }
+        2 => {
+            let end = data.get(4..)?.find_byte(0)? + 4;
+            let token = String::from_utf8(data[4..end].to_vec()).ok()?;
+            (Token::V2(token), data.get(end + 1..)?)
+        }
+        _ => return None,
+    };
+    let size = u32::from_be_bytes(data.get(..4)?.try_into().ok()?) as usize;
+    let (dirty, _) = gix_bitmap::ewah::decode(data.get(4..4 + size)?).ok()?;
+    Some((token, dirty))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{fixture_repo, FixtureProvider};
+    use crate::utils::create_command;
+    use std::io;
+
+    fn git(repo_dir: &Path, args: &[&str]) -> io::Result<()> {
+        create_command("git")?
+            .args(args)
+            .current_dir(repo_dir)
+            .output()?;
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn reads_token_from_index() -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+        let hook = repo_dir.path().join(".git").join("fsmonitor-hook");
+        std::fs::write(&hook, "#!/bin/sh\nprintf 'c:123:4\\0readme.md\\0'\n")?;
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o700))?;
+        git(
+            repo_dir.path(),
+            &["config", "core.fsmonitor", &hook.to_string_lossy()],
+        )?;
+
+        for version in ["2", "4"] {
+            git(
+                repo_dir.path(),
+                &["update-index", "--index-version", version],
+            )?;
+            git(repo_dir.path(), &["status"])?;
+
+            let data = std::fs::read(repo_dir.path().join(".git").join("index"))?;
+            let (token, _) = read_extension(&data, 20).expect("fsmonitor extension");
+            assert!(matches!(token, Token::V2(token) if token == "c:123:4"));
+        }
+        repo_dir.close()
+    }
+
+    #[test]
+    fn index_without_extension() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+        let data = std::fs::read(repo_dir.path().join(".git").join("index"))?;
+        assert!(read_extension(&data, 20).is_none());
+        assert!(read_extension(b"not an index", 20).is_none());
+        repo_dir.close()
+    }
+}
@@ -0,0 +1,397 @@
+use gix::bstr::{BString, ByteSlice};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The dirstate starts with the ids of the two parents of the working directory
+const DIRSTATE_PARENTS_SIZE: usize = 40;
+
+/// The docket of version 2 of the dirstate starts with this marker and the ids of the parents
+const DIRSTATE_V2_MARKER: &[u8] = b"dirstate-v2\n";
+const DIRSTATE_V2_PARENTS_SIZE: usize = 64;
+/// The tree metadata in the docket is followed by the size of the data file and its id
+const DIRSTATE_V2_TREE_METADATA_SIZE: usize = 44;
+const DIRSTATE_V2_NODE_SIZE: usize = 44;
+
+// Flags of the nodes in version 2 of the dirstate
+pub const V2_WDIR_TRACKED: u16 = 1 << 0;
+pub const V2_P1_TRACKED: u16 = 1 << 1;
+pub const V2_P2_INFO: u16 = 1 << 2;
+pub const V2_MODE_EXEC_PERM: u16 = 1 << 3;
+pub const V2_MODE_IS_SYMLINK: u16 = 1 << 4;
+pub const V2_HAS_MODE_AND_SIZE: u16 = 1 << 10;
+pub const V2_HAS_MTIME: u16 = 1 << 11;
+pub const V2_MTIME_SECOND_AMBIGUOUS: u16 = 1 << 12;
+
+/// A Mercurial repository, read directly from its `.hg` directory
+pub struct Repository {
+    hg_dir: PathBuf,
+    requirements: Vec<String>,
+}
+
+impl Repository {
+    pub fn open(root: &Path) -> Self {
+        let hg_dir = root.join(".hg");
+        // The format of the working directory is listed in the requirements of the
+        // repository itself, even for shares
+        let requirements = fs::read_to_string(hg_dir.join("requires"))
+            .map(|content| content.lines().map(String::from).collect())
+            .unwrap_or_default();
+
+        Self {
+            hg_dir,
+            requirements,
+        }
+    }
+
+    fn requires(&self, requirement: &str) -> bool {
+        self.requirements.iter().any(|r| r == requirement)
+    }
+
+    /// Reads the state of the tracked files
+    pub fn dirstate(&self) -> Option<Dirstate> {
+        let data = match fs::read(self.hg_dir.join("dirstate")) {
+            Ok(data) => data,
+            // A new repository has no dirstate until something is added
+            Err(_) => {
+                return Some(Dirstate {
+                    entries: BTreeMap::new(),
+                })
+            }
+        };
+        if !self.requires("dirstate-v2") {
+            return Dirstate::parse(&data);
+        }
+
+        // The docket names the data file holding the tree of files
+        let uuid_offset = DIRSTATE_V2_MARKER.len()
+            + DIRSTATE_V2_PARENTS_SIZE
+            + DIRSTATE_V2_TREE_METADATA_SIZE
+            + 5;
+        let uuid_len = usize::from(*data.get(uuid_offset - 1)?);
+        let uuid = data
+            .get(uuid_offset..uuid_offset + uuid_len)?
+            .to_str()
+            .ok()?;
+        let tree = fs::read(self.hg_dir.join(format!("dirstate.{uuid}"))).ok()?;
+        Dirstate::parse_v2(&data, &tree)
+    }
+
+    /// Counts the files with unresolved conflicts of an interrupted merge, update or rebase
+    pub fn unresolved_count(&self) -> usize {
+        let merge_dir = self.hg_dir.join("merge");
+        if let Ok(data) = fs::read(merge_dir.join("state2")) {
+            return count_unresolved_v2(&data);
+        }
+        match fs::read(merge_dir.join("state")) {
+            // The first line is the node of the local revision, then one line per file
+            Ok(data) => data
+                .lines()
+                .skip(1)
+                .filter(|line| line.split_str("\0").nth(1) == Some(b"u"))
+                .count(),
+            Err(_) => 0,
+        }
+    }
+}
+
+/// The state of the tracked files, from `.hg/dirstate`
+pub struct Dirstate {
+    pub entries: BTreeMap<BString, DirstateEntry>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DirstateEntry {
+    /// `n` for normal, `a` for added, `r` for removed or `m` for merged files
+    pub state: u8,
+    pub mode: i32,
+    /// The size of the file, or -1 if it has to be compared with its content and
+    /// -2 if it comes from the second parent of a merge
+    pub size: i32,
+    /// The modification time of the file in seconds, or -1 if it's unknown
+    pub mtime: i32,
+}
+
+impl Dirstate {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let read_i32 = |offset: usize| -> Option<i32> {
+            Some(i32::from_be_bytes(
+                data.get(offset..offset + 4)?.try_into().ok()?,
+            ))
+        };
+        if data.len() < DIRSTATE_PARENTS_SIZE {
+            return None;
+        }
+
+        let mut entries = BTreeMap::new();
+        let mut offset = DIRSTATE_PARENTS_SIZE;
+        while offset < data.len() {
+            let entry = DirstateEntry {
+                state: data[offset],
+                mode: read_i32(offset + 1)?,
+                size: read_i32(offset + 5)?,
+                mtime: read_i32(offset + 9)?,
+            };
+            let len = usize::try_from(read_i32(offset + 13)?).ok()?;
+            let name = data.get(offset + 17..offset + 17 + len)?;
+            // The source of a copy follows the name after a NUL byte
+            let path = name.split_str("\0").next()?;
+            entries.insert(path.into(), entry);
+            offset += 17 + len;
+        }
+        Some(Self { entries })
+    }
+
+    /// Parses version 2 of the dirstate, made of a docket and a data file with a tree of
+    /// nodes for each file and directory. The entries are converted to their version 1 form.
+    pub fn parse_v2(docket: &[u8], tree: &[u8]) -> Option<Self> {
+        let read_u16 = |data: &[u8], offset: usize| -> Option<u16> {
+            Some(u16::from_be_bytes(
+                data.get(offset..offset + 2)?.try_into().ok()?,
+            ))
+        };
+        let read_u32 = |data: &[u8], offset: usize| -> Option<u32> {
+            Some(u32::from_be_bytes(
+                data.get(offset..offset + 4)?.try_into().ok()?,
+            ))
+        };
+        if !docket.starts_with(DIRSTATE_V2_MARKER) {
+            return None;
+        }
+
+        let metadata = DIRSTATE_V2_MARKER.len() + DIRSTATE_V2_PARENTS_SIZE;
+        let mut nodes = vec![(read_u32(docket, metadata)?, read_u32(docket, metadata + 4)?)];
+        let mut entries = BTreeMap::new();
+        // Each node is only visited once, unless the tree is corrupt
+        let mut remaining = tree.len() / DIRSTATE_V2_NODE_SIZE;
+        while let Some((start, count)) = nodes.pop() {
+            remaining = remaining.checked_sub(count as usize)?;
+            for index in 0..count as usize {
+                let node = DIRSTATE_V2_NODE_SIZE
+                    .checked_mul(index)?
+                    .checked_add(start as usize)?;
+                let path_start = read_u32(tree, node)? as usize;
+                let path_len = usize::from(read_u16(tree, node + 4)?);
+                nodes.push((read_u32(tree, node + 14)?, read_u32(tree, node + 18)?));
+
+                let flags = read_u16(tree, node + 30)?;
+                let wdir_tracked = flags & V2_WDIR_TRACKED != 0;
+                let p1_tracked = flags & V2_P1_TRACKED != 0;
+                let p2_info = flags & V2_P2_INFO != 0;
+                if !(wdir_tracked || p1_tracked || p2_info) {
+                    // Directories and files that are only cached for `hg status`
+                    continue;
+                }
+
+                let state = match (wdir_tracked, p1_tracked, p2_info) {
+                    (false, _, _) => b'r',
+                    (true, false, false) => b'a',
+                    (true, true, true) => b'm',
+                    _ => b'n',
+                };
+                let (mode, size) = if flags & V2_HAS_MODE_AND_SIZE == 0 {
+                    (0, -1)
+                } else {
+                    let mode = match (
+                        flags & V2_MODE_IS_SYMLINK != 0,
+                        flags & V2_MODE_EXEC_PERM != 0,
+                    ) {
+                        (true, _) => 0o120_777,
+                        (false, true) => 0o100_755,
+                        (false, false) => 0o100_644,
+                    };
+                    (mode, read_u32(tree, node + 32)? as i32)
+                };
+                let mtime = if flags & V2_HAS_MTIME == 0 || flags & V2_MTIME_SECOND_AMBIGUOUS != 0 {
+                    -1
+                } else {
+                    read_u32(tree, node + 36)? as i32
+                };
+                let entry = DirstateEntry {
+                    state,
+                    mode,
+                    // Files from the second parent of a merge are compared like in version 1
+                    size: if p2_info && state == b'n' { -2 } else { size },
+                    mtime,
+                };
+                let path = tree.get(path_start..path_start + path_len)?;
+                entries.insert(path.into(), entry);
+            }
+        }
+        Some(Self { entries })
+    }
+}
+
+/// Counts the unresolved files in version 2 of the merge state, made of records starting
+/// with their type and size
+fn count_unresolved_v2(data: &[u8]) -> usize {
+    let mut count = 0;
+    let mut offset = 0;
+    while let Some(header) = data.get(offset..offset + 5) {
+        let size = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        let Some(record) = data.get(offset + 5..offset + 5 + size) else {
+            break;
+        };
+        // Merged files, change/delete conflicts and path conflicts have the path and
+        // the state as their first fields
+        if b"FCP".contains(&header[0]) {
+            let state = record.split_str("\0").nth(1);
+            if matches!(state, Some(b"u" | b"pu")) {
+                count += 1;
+            }
+        }
+        offset += 5 + size;
+    }
+    count
+}
+
+/// Encodes the docket and the data file with id `0123` of version 2 of the dirstate, with
+/// entries of `(flags, size, mtime, path)`. Files in directories become children of a
+/// node for their top-level directory.
+#[cfg(test)]
+pub fn encode_dirstate_v2(entries: &[(u16, i32, i32, &str)]) -> (Vec<u8>, Vec<u8>) {
+    type Node<'a> = (&'a str, u16, i32, i32);
+
+    fn push_node(tree: &mut Vec<u8>, paths: &mut Vec<u8>, node: Node, children: (usize, usize)) {
+        let (path, flags, size, mtime) = node;
+        tree.extend((paths.len() as u32).to_be_bytes());
+        tree.extend((path.len() as u16).to_be_bytes());
+        tree.extend([0; 8]);
+        tree.extend((children.0 as u32).to_be_bytes());
+        tree.extend((children.1 as u32).to_be_bytes());
+        tree.extend([0; 8]);
+        tree.extend(flags.to_be_bytes());
+        for field in [size, mtime, 0] {
+            tree.extend(field.to_be_bytes());
+        }
+        paths.extend(path.as_bytes());
+    }
+
+    let mut roots: Vec<(Node, Vec<Node>)> = Vec::new();
+    for &(flags, size, mtime, path) in entries {
+        let node = (path, flags, size, mtime);
+        match path.split_once('/') {
+            Some((dir, _)) => match roots.iter_mut().find(|(root, _)| root.0 == dir) {
+                Some((_, children)) => children.push(node),
+                None => roots.push(((dir, 0, 0, 0), vec![node])),
+            },
+            None => roots.push((node, Vec::new())),
+        }
+    }
+
+    let mut tree = Vec::new();
+    let mut paths = Vec::new();
+    let mut children_start = roots.len();
+    for (root, children) in &roots {
+        let start = children_start * DIRSTATE_V2_NODE_SIZE;
+        push_node(&mut tree, &mut paths, *root, (start, children.len()));
+        children_start += children.len();
+    }
+    for (_, children) in &roots {
+        for child in children {
+            push_node(&mut tree, &mut paths, *child, (0, 0));
+        }
+    }
+    // The paths follow the nodes
+    for node in tree.chunks_mut(DIRSTATE_V2_NODE_SIZE) {
+        let path_start = u32::from_be_bytes(node[..4].try_into().unwrap()) as usize;
+        node[..4].copy_from_slice(
+            &((path_start + children_start * DIRSTATE_V2_NODE_SIZE) as u32).to_be_bytes(),
+        );
+    }
+    tree.extend(paths);
+
+    let mut docket = DIRSTATE_V2_MARKER.to_vec();
+    docket.extend([0; DIRSTATE_V2_PARENTS_SIZE]);
+    docket.extend(0u32.to_be_bytes());
+    docket.extend((roots.len() as u32).to_be_bytes());
+    docket.extend([0; DIRSTATE_V2_TREE_METADATA_SIZE - 8]);
+    docket.extend((tree.len() as u32).to_be_bytes());
+    docket.push(4);
+    docket.extend(b"0123");
+    (docket, tree)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dirstate_v2() {
+        let (docket, tree) = encode_dirstate_v2(&[
+            (
+                V2_WDIR_TRACKED | V2_P1_TRACKED | V2_HAS_MODE_AND_SIZE | V2_HAS_MTIME,
+                8,
+                1_700_000_000,
+                "readme.md",
+            ),
+            (V2_WDIR_TRACKED, 0, 0, "src/new.rs"),
+            (V2_P1_TRACKED, 0, 0, "src/old.rs"),
+            (
+                V2_WDIR_TRACKED | V2_P1_TRACKED | V2_HAS_MODE_AND_SIZE | V2_MODE_EXEC_PERM,
+                3,
+                0,
+                "run.sh",
+            ),
+        ]);
+        let dirstate = Dirstate::parse_v2(&docket, &tree).unwrap();
+
+        assert_eq!(
+            dirstate.entries.keys().collect::<Vec<_>>(),
+            ["readme.md", "run.sh", "src/new.rs", "src/old.rs"]
+        );
+        let entry = |path: &str| dirstate.entries[path.as_bytes().as_bstr()];
+        assert_eq!(
+            entry("readme.md"),
+            DirstateEntry {
+                state: b'n',
+                mode: 0o100_644,
+                size: 8,
+                mtime: 1_700_000_000
+            }
+        );
+        assert_eq!(entry("run.sh").mode, 0o100_755);
+        assert_eq!(entry("run.sh").mtime, -1);
+        assert_eq!(entry("src/new.rs").state, b'a');
+        assert_eq!(entry("src/old.rs").state, b'r');
+        assert!(Dirstate::parse_v2(&docket, &tree[..tree.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn parses_dirstate() {
+        let mut data = vec![1; DIRSTATE_PARENTS_SIZE];
+        for (state, size, name) in [(b'n', 8, "readme.md"), (b'a', 3, "new\0copied")] {
+            data.push(state);
+            for field in [0o100_644, size, 1_700_000_000, name.len() as i32] {
+                data.extend(field.to_be_bytes());
+            }
+            data.extend(name.as_bytes());
+        }
+        let dirstate = Dirstate::parse(&data).unwrap();
+
+        assert_eq!(
+            dirstate.entries.keys().collect::<Vec<_>>(),
+            ["new", "readme.md"]
+        );
+        assert_eq!(dirstate.entries[b"new".as_bstr()].state, b'a');
+        assert_eq!(dirstate.entries[b"readme.md".as_bstr()].size, 8);
+        assert!(Dirstate::parse(&data[..data.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn counts_unresolved_files_in_merge_state() {
+        let mut data = Vec::new();
+        for (kind, record) in [
+            (b'L', "0123456789abcdef0123456789abcdef01234567"),
+            (b'F', "a.txt\0u\0hash"),
+            (b'F', "b.txt\0r\0hash"),
+            (b'P', "c.txt\0pu\0d.txt"),
+        ] {
+            data.push(kind);
+            data.extend((record.len() as u32).to_be_bytes());
+            data.extend(record.as_bytes());
+        }
+
+        assert_eq!(count_unresolved_v2(&data), 2);
+    }
+}
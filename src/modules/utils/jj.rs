@@ -0,0 +1,142 @@
+use std::path::Path;
+
+use crate::context::{self, Context};
+
+/// Prints one field of the working-copy commit per line. The full ids are printed along with
+/// their shortest unique prefix, so they can be shortened to any length without running `jj`
+/// again.
+const LOG_TEMPLATE: &str = concat!(
+    r#"change_id ++ "\n" ++ change_id.shortest() ++ "\n" ++ "#,
+    r#"commit_id ++ "\n" ++ commit_id.shortest() ++ "\n" ++ "#,
+    r#"description.first_line() ++ "\n" ++ "#,
+    r#"bookmarks.join(" ") ++ "\n" ++ "#,
+    r#"separate(" ", if(conflict, "conflict"), if(divergent, "divergent"), if(empty, "empty"))"#,
+);
+
+/// The working-copy commit (`@`) of a Jujutsu repository
+#[derive(Debug, PartialEq, Eq)]
+pub struct WorkingCopy {
+    change_id: String,
+    change_id_unique: usize,
+    commit_id: String,
+    commit_id_unique: usize,
+    pub description: String,
+    pub bookmarks: Vec<String>,
+    pub conflict: bool,
+    pub divergent: bool,
+    pub empty: bool,
+}
+
+impl WorkingCopy {
+    /// The change id, shortened to `len` characters unless more are needed to keep it unique
+    pub fn change_id(&self, len: usize) -> &str {
+        shorten(&self.change_id, self.change_id_unique, len)
+    }
+
+    /// The commit id, shortened to `len` characters unless more are needed to keep it unique
+    pub fn commit_id(&self, len: usize) -> &str {
+        shorten(&self.commit_id, self.commit_id_unique, len)
+    }
+
+    fn parse(output: &str) -> Option<Self> {
+        let mut lines = output.split('\n');
+        let mut next = || lines.next().map(str::trim_end);
+        let change_id = next()?.to_string();
+        let change_id_unique = next()?.len();
+        let commit_id = next()?.to_string();
+        let commit_id_unique = next()?.len();
+        let description = next()?.to_string();
+        let bookmarks = next()?.split_whitespace().map(String::from).collect();
+        let flags: Vec<&str> = next()?.split_whitespace().collect();
+        Some(Self {
+            change_id,
+            change_id_unique,
+            commit_id,
+            commit_id_unique,
+            description,
+            bookmarks,
+            conflict: flags.contains(&"conflict"),
+            divergent: flags.contains(&"divergent"),
+            empty: flags.contains(&"empty"),
+        })
+    }
+}
+
+fn shorten(id: &str, unique: usize, len: usize) -> &str {
+    &id[..len.max(unique).min(id.len())]
+}
+
+/// Finds the root of the Jujutsu repository containing the current directory
+pub fn find_root<'a>(context: &'a Context) -> Option<&'a Path> {
+    context.begin_ancestor_scan().set_folders(&[".jj"]).scan()
+}
+
+/// Whether the git repository is colocated with a Jujutsu repository, which keeps git's
+/// `HEAD` detached
+pub fn is_colocated(repo: &context::Repo) -> bool {
+    repo.workdir
+        .as_ref()
+        .is_some_and(|workdir| workdir.join(".jj").is_dir())
+}
+
+/// The arguments to run `jj` with to print the working-copy commit
+pub fn log_args(ignore_working_copy: bool) -> Vec<&'static str> {
+    let mut args = vec!["--color", "never", "--no-pager"];
+    if ignore_working_copy {
+        // Don't snapshot the working copy, which can be slow and takes a lock on the repo
+        args.push("--ignore-working-copy");
+    }
+    args.extend(["log", "--no-graph", "-r", "@", "-T", LOG_TEMPLATE]);
+    args
+}
+
+/// Reads the working-copy commit with `jj log`.
+///
+/// This requires the `jj` binary, which loads the repo on every call. Unless
+/// `ignore_working_copy` is set, it also snapshots the working copy, which checks every file.
+pub fn get_working_copy(context: &Context, ignore_working_copy: bool) -> Option<WorkingCopy> {
+    let output = context.exec_cmd("jj", &log_args(ignore_working_copy))?;
+    WorkingCopy::parse(&output.stdout).or_else(|| {
+        log::debug!("Unexpected output of jj log: {:?}", output.stdout);
+        None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_log_output() {
+        let output = "kxqzwmplyvrlsokrtztsoqttlnxoxonu\nk\n\
+            3f7d1c0a9b8e2d4c6f1a0b9e8d7c6b5a4f3e2d1c\n3f\n\
+            Add jj support\nmain feature*\nconflict empty\n";
+        let commit = WorkingCopy::parse(output).unwrap();
+
+        assert_eq!(commit.change_id(8), "kxqzwmpl");
+        assert_eq!(commit.change_id(0), "k");
+        assert_eq!(commit.commit_id(1), "3f");
+        assert_eq!(
+            commit.commit_id(100),
+            "3f7d1c0a9b8e2d4c6f1a0b9e8d7c6b5a4f3e2d1c"
+        );
+        assert_eq!(commit.description, "Add jj support");
+        assert_eq!(commit.bookmarks, ["main", "feature*"]);
+        assert!(commit.conflict && commit.empty && !commit.divergent);
+    }
+
+    #[test]
+    fn parses_log_output_without_description_or_flags() {
+        let output = "kxqzwmpl\nkx\n3f7d1c0a\n3f\n\n\n";
+        let commit = WorkingCopy::parse(output).unwrap();
+
+        assert_eq!(commit.description, "");
+        assert!(commit.bookmarks.is_empty());
+        assert!(!commit.conflict && !commit.empty && !commit.divergent);
+    }
+
+    #[test]
+    fn rejects_truncated_output() {
+        assert_eq!(WorkingCopy::parse("kxqzwmpl\nkx\n"), None);
+    }
+}
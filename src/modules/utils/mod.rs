@@ -1,5 +1,11 @@
 pub mod directory;
 
+pub mod fsmonitor;
+
+pub mod hg;
+
+pub mod jj;
+
 #[cfg(target_os = "windows")]
 pub mod directory_win;
 
@@ -8,4 +14,6 @@ pub mod directory_nix;
 
 pub mod path;
 
+pub mod revision;
+
 pub mod truncate;
@@ -0,0 +1,166 @@
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet};
+
+type Error = Box<dyn std::error::Error>;
+
+const OURS: u8 = 1;
+const THEIRS: u8 = 2;
+const BOTH: u8 = OURS | THEIRS;
+
+/// Counts the commits only reachable from `ours` and only reachable from `theirs`.
+/// Like `git rev-list --left-right --count`, both histories are painted newest first
+/// until they only share commits, and the commits painted by one side are counted
+/// once the walk is done.
+pub fn count_ahead_behind(
+    repo: &gix::Repository,
+    ours: gix::ObjectId,
+    theirs: gix::ObjectId,
+) -> Result<(usize, usize), Error> {
+    let mut walk = PaintWalk::new(repo);
+    walk.paint(ours, OURS)?;
+    walk.paint(theirs, THEIRS)?;
+
+    while !walk.is_done() {
+        let Some((_, id)) = walk.queue.pop() else {
+            break;
+        };
+        walk.queued.remove(&id);
+        let flag = walk.flags[&id].1;
+        if flag != BOTH {
+            walk.queued_one_side -= 1;
+        }
+        for parent in repo.find_object(id)?.try_into_commit()?.parent_ids() {
+            walk.paint(parent.detach(), flag)?;
+        }
+    }
+
+    let ahead = walk
+        .one_side
+        .iter()
+        .filter(|(_, id)| walk.flags[id].1 == OURS)
+        .count();
+    Ok((ahead, walk.one_side.len() - ahead))
+}
+
+struct PaintWalk<'r> {
+    repo: &'r gix::Repository,
+    /// The commit time of every visited commit and the sides it is reachable from
+    flags: HashMap<gix::ObjectId, (i64, u8)>,
+    queue: BinaryHeap<(i64, gix::ObjectId)>,
+    queued: HashSet<gix::ObjectId>,
+    /// How many queued commits are only known to be reachable from one side
+    queued_one_side: usize,
+    /// Every visited commit only known to be reachable from one side, oldest first
+    one_side: BTreeSet<(i64, gix::ObjectId)>,
+}
+
+impl<'r> PaintWalk<'r> {
+    fn new(repo: &'r gix::Repository) -> Self {
+        PaintWalk {
+            repo,
+            flags: HashMap::new(),
+            queue: BinaryHeap::new(),
+            queued: HashSet::new(),
+            queued_one_side: 0,
+            one_side: BTreeSet::new(),
+        }
+    }
+
+    /// Marks a commit as reachable from `flag`, queueing it again to pass that on to its
+    /// parents if it wasn't known yet, even if the commit has been walked before
+    fn paint(&mut self, id: gix::ObjectId, flag: u8) -> Result<(), Error> {
+        let (time, old) = match self.flags.get(&id) {
+            Some(&entry) => entry,
+            None => (
+                self.repo
+                    .find_object(id)?
+                    .try_into_commit()?
+                    .time()?
+                    .seconds,
+                0,
+            ),
+        };
+        let new = old | flag;
+        if new == old {
+            return Ok(());
+        }
+        self.flags.insert(id, (time, new));
+
+        if new == BOTH {
+            self.one_side.remove(&(time, id));
+        } else {
+            self.one_side.insert((time, id));
+        }
+        if !self.queued.insert(id) {
+            // A queued commit that changes flags was only reachable from one side before
+            self.queued_one_side -= 1;
+        } else {
+            self.queue.push((time, id));
+        }
+        if new != BOTH {
+            self.queued_one_side += 1;
+        }
+        Ok(())
+    }
+
+    fn is_done(&self) -> bool {
+        if self.queued_one_side > 0 {
+            return false;
+        }
+        // Parents are usually older than their children, so the commits painted by both
+        // sides are walked until they are older than every commit painted by one side.
+        // Commits made in the same second are walked too, as they can be in any order.
+        match (self.queue.peek(), self.one_side.first()) {
+            (Some((time, _)), Some((oldest, _))) => time < oldest,
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{fixture_repo, FixtureProvider};
+    use crate::utils::create_command;
+    use std::io;
+    use std::path::Path;
+
+    fn commit_at_same_time(dir: &Path, message: &str) -> io::Result<()> {
+        create_command("git")?
+            .args(["commit", "--allow-empty", "--quiet", "-m", message])
+            .env("GIT_AUTHOR_DATE", "2022-01-01 00:00:00 +0000")
+            .env("GIT_COMMITTER_DATE", "2022-01-01 00:00:00 +0000")
+            .current_dir(dir)
+            .output()?;
+        Ok(())
+    }
+
+    #[test]
+    fn counts_commits_made_in_the_same_second() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = fixture_repo(FixtureProvider::Git)?;
+        for message in ["a", "b", "c", "d", "e", "f"] {
+            commit_at_same_time(dir.path(), message)?;
+        }
+
+        let repo = gix::open(dir.path())?;
+        let head = repo.rev_parse_single("HEAD")?.detach();
+        for behind in 0..6 {
+            let ancestor = repo.rev_parse_single(format!("HEAD~{behind}").as_str())?;
+            let ancestor = ancestor.detach();
+            assert_eq!(count_ahead_behind(&repo, head, ancestor)?, (behind, 0));
+            assert_eq!(count_ahead_behind(&repo, ancestor, head)?, (0, behind));
+        }
+
+        create_command("git")?
+            .args(["switch", "--quiet", "--create", "other", "HEAD~3"])
+            .current_dir(dir.path())
+            .output()?;
+        for message in ["g", "h"] {
+            commit_at_same_time(dir.path(), message)?;
+        }
+        let other = repo.rev_parse_single("other")?.detach();
+        assert_eq!(count_ahead_behind(&repo, head, other)?, (3, 2));
+        assert_eq!(count_ahead_behind(&repo, other, head)?, (2, 3));
+        dir.close()?;
+        Ok(())
+    }
+}
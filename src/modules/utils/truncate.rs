@@ -1,36 +1,30 @@
 use unicode_segmentation::UnicodeSegmentation;
 
+use crate::formatter::Filter;
+
 /// Truncate a string to only have a set number of characters
 ///
-/// Will truncate a string to only show the last `length` character in the string.
+/// Will truncate a string to only show the first `length` characters in the string,
+/// like the `truncate` filter with the first character of `truncation_symbol`.
 /// If a length of `0` is provided, the string will not be truncated and the original
 /// will be returned.
 pub fn truncate_text(text: &str, length: usize, truncation_symbol: &str) -> String {
     if length == 0 {
         return String::from(text);
     }
-
-    let truncated_graphemes = get_graphemes(text, length);
-    // The truncation symbol should only be added if we truncated
-    let truncated_and_symbol = if length < graphemes_len(text) {
-        let truncation_symbol = get_graphemes(truncation_symbol, 1);
-        truncated_graphemes + truncation_symbol.as_str()
-    } else {
-        truncated_graphemes
-    };
-
-    truncated_and_symbol
+    truncation_filter(length, truncation_symbol).apply(text)
 }
 
-fn get_graphemes(text: &str, length: usize) -> String {
-    UnicodeSegmentation::graphemes(text, true)
-        .take(length)
-        .collect::<Vec<&str>>()
-        .concat()
-}
-
-fn graphemes_len(text: &str) -> usize {
-    UnicodeSegmentation::graphemes(text, true).count()
+/// The `truncate` filter for the `truncation_length` and `truncation_symbol` options of a
+/// module, of which only the first character of the symbol is used
+pub fn truncation_filter(length: usize, truncation_symbol: &str) -> Filter<'_> {
+    let symbol = UnicodeSegmentation::graphemes(truncation_symbol, true)
+        .next()
+        .unwrap_or("");
+    Filter::Truncate {
+        length,
+        symbol: symbol.into(),
+    }
 }
 
 #[cfg(test)]
@@ -5,19 +5,24 @@ use std::collections::BTreeSet;
 use std::fmt::{Debug, Write as FmtWrite};
 use std::io::{self, Write};
 use std::path::PathBuf;
+use std::sync::Mutex;
 use std::time::Duration;
 use terminal_size::terminal_size;
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthChar;
 
+use crate::config::parse_style_string;
 use crate::configs::PROMPT_ORDER;
-use crate::context::{Context, Properties, Shell, Target};
+use crate::context::{Context, OutputFormat, Properties, Shell, Target};
+use crate::deferred;
 use crate::formatter::{StringFormatter, VariableHolder};
+use crate::json_output::{self, JsonModule, JsonPrompt};
 use crate::module::Module;
 use crate::module::ALL_MODULES;
 use crate::modules;
-use crate::segment::Segment;
+use crate::segment::{self, GroupSegment, Segment};
 use crate::shadow;
+use crate::tmux;
 use crate::utils::wrap_colorseq_for_shell;
 
 pub struct Grapheme<'a>(pub &'a str);
@@ -61,14 +66,22 @@ pub fn prompt(args: Properties, target: Target) {
     let context = Context::new(args, target);
     let stdout = io::stdout();
     let mut handle = stdout.lock();
-    write!(handle, "{}", get_prompt(context)).unwrap();
+    write!(handle, "{}", render_prompt(&context)).unwrap();
 }
 
-pub fn get_prompt(context: Context) -> String {
+/// Renders the prompt in the format selected with `--output`
+pub fn render_prompt(context: &Context) -> String {
+    match context.properties.output {
+        OutputFormat::Text => get_prompt(context),
+        OutputFormat::Json => get_prompt_json(context),
+    }
+}
+
+pub fn get_prompt(context: &Context) -> String {
     let config = &context.root_config;
     let mut buf = String::new();
 
-    match std::env::var_os("TERM") {
+    match context.get_env_os("TERM") {
         Some(term) if term == "dumb" => {
             log::error!("Under a 'dumb' terminal (TERM=dumb).");
             buf.push_str("Starship disabled due to TERM=dumb > ");
@@ -83,40 +96,15 @@ pub fn get_prompt(context: Context) -> String {
         buf.push_str("\x1b[J"); // An ASCII control code to clear screen
     }
 
-    let (formatter, modules) = load_formatter_and_modules(&context);
-
-    let formatter = formatter.map_variables_to_segments(|module| {
-        // Make $all display all modules not explicitly referenced
-        if module == "all" {
-            Some(Ok(all_modules_uniq(&modules)
-                .par_iter()
-                .flat_map(|module| {
-                    handle_module(module, &context, &modules)
-                        .into_iter()
-                        .flat_map(|module| module.segments)
-                        .collect::<Vec<Segment>>()
-                })
-                .collect::<Vec<_>>()))
-        } else if context.is_module_disabled_in_config(module) {
-            None
-        } else {
-            // Get segments from module
-            Some(Ok(handle_module(module, &context, &modules)
-                .into_iter()
-                .flat_map(|module| module.segments)
-                .collect::<Vec<Segment>>()))
-        }
-    });
-
     // Creates a root module and prints it.
     let mut root_module = Module::new("Starship Root", "The root module", None);
-    root_module.set_segments(
-        formatter
-            .parse(None, Some(&context))
-            .expect("Unexpected error returned in root format variables"),
-    );
+    root_module.set_segments(prompt_segments(context, |_, _| {}));
 
     let module_strings = root_module.ansi_strings_for_width(Some(context.width));
+    if let Target::Tmux(_) = context.target {
+        // tmux status lines and window titles are a single line
+        return tmux::to_markup(&module_strings).replace('\n', "");
+    }
     if config.add_newline && context.target != Target::Continuation {
         // continuation prompts normally do not include newlines, but they can
         writeln!(buf).unwrap();
@@ -142,12 +130,90 @@ pub fn get_prompt(context: Context) -> String {
     buf
 }
 
+/// Describes the prompt and the modules in it as JSON
+pub fn get_prompt_json(context: &Context) -> String {
+    let modules = Mutex::new(Vec::new());
+    let segments = prompt_segments(context, |position, variable_modules| {
+        let variable_modules: Vec<JsonModule> =
+            variable_modules.iter().map(JsonModule::from).collect();
+        modules.lock().unwrap().push((position, variable_modules));
+    });
+
+    // Variables are rendered in parallel, so put their modules back in prompt order
+    let mut modules = modules.into_inner().unwrap();
+    modules.sort_by_key(|(position, _)| *position);
+    json_output::to_string(&JsonPrompt {
+        segments: json_output::segments(&segments),
+        modules: modules
+            .into_iter()
+            .flat_map(|(_, modules)| modules)
+            .collect(),
+    })
+}
+
+/// Computes the segments of the prompt, passing the modules of each variable in it to
+/// `on_modules` along with the position of the variable in the format string
+fn prompt_segments<F>(context: &Context, on_modules: F) -> Vec<Segment>
+where
+    F: Fn(usize, &[Module]) + Sync,
+{
+    let (formatter, modules) = load_formatter_and_modules(context);
+    let variables = formatter.get_variables_in_order();
+
+    let segments_of = |variable: &str, variable_modules: Vec<Module>| {
+        let position = variables
+            .iter()
+            .position(|name| name == variable)
+            .unwrap_or(variables.len());
+        on_modules(position, &variable_modules);
+        variable_modules
+            .into_iter()
+            .flat_map(|module| module_segments(module, context))
+            .collect::<Vec<Segment>>()
+    };
+    let formatter = formatter.map_variables_to_segments(|module| {
+        // Make $all display all modules not explicitly referenced
+        if module == "all" {
+            let all_modules = all_modules_uniq(&modules)
+                .par_iter()
+                .flat_map(|module| handle_module(module, context, &modules, true))
+                .collect::<Vec<Module>>();
+            Some(Ok(segments_of(module, all_modules)))
+        } else if context.is_module_disabled_in_config(module) {
+            None
+        } else {
+            // Get segments from module
+            Some(Ok(segments_of(
+                module,
+                handle_module(module, context, &modules, true),
+            )))
+        }
+    });
+
+    segment::draw_group_transitions(
+        formatter
+            .parse(None, Some(context))
+            .expect("Unexpected error returned in root format variables"),
+    )
+}
+
 pub fn module(module_name: &str, args: Properties) {
     let context = Context::new(args, Target::Main);
+    if context.properties.output == OutputFormat::Json {
+        let module = modules::handle(module_name, &context).map(|m| JsonModule::from(&m));
+        println!("{}", json_output::to_string(&module));
+        return;
+    }
     let module = get_module(module_name, context).unwrap_or_default();
     print!("{module}");
 }
 
+/// Computes a module with `async = true` in the background of a prompt, see `deferred::refresh`
+pub fn refresh_async_module(module_name: &str, args: Properties) {
+    let context = Context::new(args, Target::Main);
+    deferred::refresh(module_name, &context);
+}
+
 pub fn get_module(module_name: &str, context: Context) -> Option<String> {
     modules::handle(module_name, &context).map(|m| m.to_string())
 }
@@ -163,6 +229,15 @@ pub fn timings(args: Properties) {
         duration_len: usize,
     }
 
+    if context.properties.output == OutputFormat::Json {
+        let mut modules = compute_modules(&context);
+        modules.retain(|module| !module.is_empty() || module.duration.as_millis() > 0);
+        modules.sort_by_key(|module| std::cmp::Reverse(module.duration));
+        let modules: Vec<JsonModule> = modules.iter().map(JsonModule::from).collect();
+        println!("{}", json_output::to_string(&modules));
+        return;
+    }
+
     let mut modules = compute_modules(&context)
         .iter()
         .filter(|module| !module.is_empty() || module.duration.as_millis() > 0)
@@ -210,6 +285,17 @@ pub fn explain(args: Properties) {
 
     static DONT_PRINT: &[&str] = &["line_break"];
 
+    if context.properties.output == OutputFormat::Json {
+        let modules: Vec<JsonModule> = compute_modules(&context)
+            .iter()
+            .filter(|module| !DONT_PRINT.contains(&module.get_name().as_str()))
+            .filter(|module| !module.is_empty())
+            .map(JsonModule::from)
+            .collect();
+        println!("{}", json_output::to_string(&modules));
+        return;
+    }
+
     let modules = compute_modules(&context)
         .into_iter()
         .filter(|module| !DONT_PRINT.contains(&module.get_name().as_str()))
@@ -302,11 +388,11 @@ fn compute_modules<'a>(context: &'a Context) -> Vec<Module<'a>> {
         // Manually add all modules if `$all` is encountered
         if module == "all" {
             for module in all_modules_uniq(&modules) {
-                let modules = handle_module(&module, context, &modules);
+                let modules = handle_module(&module, context, &modules, false);
                 prompt_order.extend(modules);
             }
         } else {
-            let modules = handle_module(module, context, &modules);
+            let modules = handle_module(module, context, &modules, false);
             prompt_order.extend(modules);
         }
     }
@@ -318,21 +404,31 @@ fn handle_module<'a>(
     module: &str,
     context: &'a Context,
     module_list: &BTreeSet<String>,
+    deferred: bool,
 ) -> Vec<Module<'a>> {
     let mut modules: Vec<Module> = Vec::new();
 
+    // Modules with `async = true` are rendered from their last background refresh in prompts
+    let handle = |name: &str| {
+        if deferred && deferred::is_async(context, name) {
+            deferred::module(name, context)
+        } else {
+            modules::handle(name, context)
+        }
+    };
+
     if ALL_MODULES.contains(&module) {
         // Write out a module if it isn't disabled
         if !context.is_module_disabled_in_config(module) {
-            modules.extend(modules::handle(module, context));
+            modules.extend(handle(module));
         }
     } else if module.starts_with("custom.") || module.starts_with("env_var.") {
         // custom.<name> and env_var.<name> are special cases and handle disabled modules themselves
-        modules.extend(modules::handle(module, context));
+        modules.extend(handle(module));
     } else if matches!(module, "custom" | "env_var") {
         // env var is a spacial case and may contain a top-level module definition
         if module == "env_var" {
-            modules.extend(modules::handle(module, context));
+            modules.extend(handle(module));
         }
 
         // Write out all custom modules, except for those that are explicitly set
@@ -347,7 +443,7 @@ fn handle_module<'a>(
             if module == "env_var" && !config.is_table() {
                 continue;
             } else if should_add_implicit_module(module, child, config, module_list) {
-                modules.extend(modules::handle(&format!("{module}.{child}"), context));
+                modules.extend(handle(&format!("{module}.{child}")));
             }
         }
     } else {
@@ -361,6 +457,48 @@ fn handle_module<'a>(
     modules
 }
 
+/// Returns the segments of a module, marked as part of a powerline group if it belongs to one.
+/// `custom.<name>` and `env_var.<name>` modules also belong to a group listing `custom` or `env_var`.
+fn module_segments(module: Module, context: &Context) -> Vec<Segment> {
+    let powerline = &context.root_config.powerline;
+    let name = module.get_name().as_str();
+    let parent = name.split_once('.').map(|(parent, _)| parent);
+    let group = powerline.groups.iter().find(|group| {
+        group
+            .modules
+            .iter()
+            .any(|module| module == name || Some(module.as_str()) == parent)
+    });
+
+    let Some(group) = group else {
+        return module.segments;
+    };
+    if module.is_empty() {
+        return module.segments;
+    }
+    let Some(style) = parse_style_string(&group.style, Some(context)) else {
+        log::warn!(
+            "Invalid style {:?} of the powerline group of {name}",
+            group.style
+        );
+        return module.segments;
+    };
+
+    let mut segments = Vec::with_capacity(module.segments.len() + 2);
+    segments.push(Segment::GroupStart(GroupSegment {
+        style,
+        separator: powerline.separator.clone(),
+        start_symbol: powerline.start_symbol.clone(),
+        end_symbol: powerline.end_symbol.clone(),
+    }));
+    segments.extend(module.segments.into_iter().map(|mut segment| {
+        segment.set_group_style(style);
+        segment
+    }));
+    segments.push(Segment::GroupEnd);
+    segments
+}
+
 fn should_add_implicit_module(
     parent_module: &str,
     child_module: &str,
@@ -427,7 +565,8 @@ fn load_formatter_and_modules<'a>(context: &'a Context) -> (StringFormatter<'a>,
 
     let (left_format_str, right_format_str): (&str, &str) = match context.target {
         Target::Main | Target::Right => (&config.format, &config.right_format),
-        Target::Profile(ref name) => {
+        Target::Tmux(None) => (&config.format, ""),
+        Target::Profile(ref name) | Target::Tmux(Some(ref name)) => {
             if let Some(lf) = config.profiles.get(name) {
                 (lf, "")
             } else {
@@ -442,10 +581,11 @@ fn load_formatter_and_modules<'a>(context: &'a Context) -> (StringFormatter<'a>,
     let rf = StringFormatter::new(right_format_str);
 
     if let Err(ref e) = lf {
-        let name = if let Target::Profile(ref profile_name) = context.target {
-            format!("profile.{profile_name}")
-        } else {
-            "format".to_string()
+        let name = match context.target {
+            Target::Profile(ref profile_name) | Target::Tmux(Some(ref profile_name)) => {
+                format!("profile.{profile_name}")
+            }
+            _ => "format".to_string(),
         };
         log::error!("Error parsing {name:?}: {e}");
     };
@@ -461,7 +601,7 @@ fn load_formatter_and_modules<'a>(context: &'a Context) -> (StringFormatter<'a>,
         .collect();
 
     let main_formatter = match context.target {
-        Target::Main | Target::Profile(_) => lf,
+        Target::Main | Target::Profile(_) | Target::Tmux(_) => lf,
         Target::Right => rf,
         Target::Continuation => unreachable!("Continuation prompt should have been handled above"),
     };
@@ -523,6 +663,7 @@ mod test {
     use super::*;
     use crate::test::default_context;
     use crate::utils;
+    use nu_ansi_term::{Color, Style};
 
     #[test]
     fn main_prompt() {
@@ -535,10 +676,141 @@ mod test {
         context.target = Target::Main;
 
         let expected = String::from(">\n>");
-        let actual = get_prompt(context);
+        let actual = get_prompt(&context);
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn async_module_prompt() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut context = default_context().set_config(toml::toml! {
+                add_newline=false
+                format="$character"
+                async_placeholder="…"
+                [character]
+                async=true
+                format=">"
+        });
+        context
+            .env
+            .insert("STARSHIP_CACHE", dir.path().to_string_lossy().to_string());
+        assert_eq!(get_prompt(&context), "…");
+
+        let mut context = default_context().set_config(toml::toml! {
+                add_newline=false
+                format="$character"
+                [character]
+                async=true
+                format=">"
+        });
+        context
+            .env
+            .insert("STARSHIP_CACHE", dir.path().to_string_lossy().to_string());
+        deferred::refresh("character", &context);
+        assert_eq!(get_prompt(&context), ">");
+        dir.close()
+    }
+
+    #[test]
+    fn powerline_prompt() {
+        let mut context = default_context().set_config(toml::toml! {
+                add_newline=false
+                format="${env_var.FOO}${env_var.EMPTY}${env_var.BAR}>"
+                [powerline]
+                groups=[
+                    { modules=["env_var.FOO"], style="bg:blue" },
+                    { modules=["env_var.EMPTY", "env_var.BAR"], style="bg:red" },
+                ]
+                [env_var.FOO]
+                format="$env_value"
+                style=""
+                [env_var.EMPTY]
+                format="$env_value"
+                [env_var.BAR]
+                format="[$env_value](bold)"
+        });
+        context.env.insert("FOO", "foo".to_string());
+        context.env.insert("BAR", "bar".to_string());
+
+        let expected = AnsiStrings(&[
+            Style::new().on(Color::Blue).paint("foo"),
+            Color::Blue.on(Color::Red).paint("\u{e0b0}"),
+            Style::new().bold().on(Color::Red).paint("bar"),
+            Color::Red.paint("\u{e0b0}"),
+            Style::new().paint(">"),
+        ])
+        .to_string();
+        assert_eq!(get_prompt(&context), expected);
+    }
+
+    #[test]
+    fn prompt_json() {
+        let mut context = default_context().set_config(toml::toml! {
+                format="${env_var.FOO}>"
+                [env_var.FOO]
+                format="[$env_value](bold red)"
+        });
+        context.env.insert("FOO", "foo".to_string());
+
+        let actual: serde_json::Value = serde_json::from_str(&get_prompt_json(&context)).unwrap();
+        let segments = &actual["segments"];
+        assert_eq!(segments[0]["type"], "text");
+        assert_eq!(segments[0]["text"], "foo");
+        assert_eq!(segments[0]["style"]["fg"], "red");
+        assert_eq!(segments[0]["style"]["bold"], true);
+        assert_eq!(segments[1]["text"], ">");
+        assert_eq!(segments.as_array().unwrap().len(), 2);
+
+        let modules = actual["modules"].as_array().unwrap();
+        assert_eq!(modules.len(), 1);
+        assert_eq!(modules[0]["name"], "env_var.FOO");
+        assert_eq!(modules[0]["segments"][0]["text"], "foo");
+        assert!(modules[0]["duration_ms"].is_number());
+    }
+
+    #[test]
+    fn prompt_json_lists_modules_in_prompt_order() {
+        let mut context = default_context().set_config(toml::toml! {
+                format="${env_var.FOO}${env_var.BAR}"
+                [env_var.FOO]
+                format="$env_value"
+                [env_var.BAR]
+                format="$env_value"
+        });
+        context.env.insert("FOO", "foo".to_string());
+        context.env.insert("BAR", "bar".to_string());
+
+        let actual: serde_json::Value = serde_json::from_str(&get_prompt_json(&context)).unwrap();
+        let names: Vec<&str> = actual["modules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|module| module["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["env_var.FOO", "env_var.BAR"]);
+    }
+
+    #[test]
+    fn prompt_json_is_not_escaped_for_the_shell() {
+        // As if run with `STARSHIP_SHELL=zsh` from a zsh session
+        let mut properties = Properties::default();
+        properties.output = OutputFormat::Json;
+        let context = Context::new_with_shell_and_path(
+            properties,
+            Shell::Zsh,
+            Target::Main,
+            PathBuf::new(),
+            PathBuf::new(),
+            Default::default(),
+        )
+        .set_config(toml::toml! {
+            format="100%"
+        });
+
+        let actual: serde_json::Value = serde_json::from_str(&get_prompt_json(&context)).unwrap();
+        assert_eq!(actual["segments"][0]["text"], "100%");
+    }
+
     #[test]
     fn right_prompt() {
         let mut context = default_context().set_config(toml::toml! {
@@ -549,7 +821,7 @@ mod test {
         context.target = Target::Right;
 
         let expected = String::from(">>"); // should strip new lines
-        let actual = get_prompt(context);
+        let actual = get_prompt(&context);
         assert_eq!(expected, actual);
     }
 
@@ -566,7 +838,7 @@ mod test {
         context.current_dir = dir.path().to_path_buf();
 
         let expected = String::from(">");
-        let actual = get_prompt(context);
+        let actual = get_prompt(&context);
         assert_eq!(expected, actual);
         dir.close()
     }
@@ -585,7 +857,7 @@ mod test {
         context.target = Target::Right;
 
         let expected = String::from(">");
-        let actual = get_prompt(context);
+        let actual = get_prompt(&context);
         assert_eq!(expected, actual);
         dir.close()
     }
@@ -602,7 +874,7 @@ mod test {
         context.target = Target::Profile("test".to_string());
 
         let expected = String::from("0_0>>");
-        let actual = get_prompt(context);
+        let actual = get_prompt(&context);
         assert_eq!(expected, actual);
     }
 
@@ -618,7 +890,44 @@ mod test {
         context.target = Target::Profile("wrong_prompt".to_string());
 
         let expected = String::from(">");
-        let actual = get_prompt(context);
+        let actual = get_prompt(&context);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn tmux_prompt() {
+        let mut context = default_context().set_config(toml::toml! {
+                [profiles]
+                tmux="[#$character](bold blue)\n"
+                [character]
+                format="[>>](red)"
+        });
+        context.target = Target::Tmux(Some("tmux".to_string()));
+
+        let expected =
+            String::from("#[fg=blue,bg=default,none,bold]###[fg=red,bg=default,none]>>#[default]");
+        let actual = get_prompt(&context);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn tmux_prompt_is_not_escaped_for_the_shell() {
+        // As if run with `STARSHIP_SHELL=zsh` from a zsh session
+        let context = Context::new_with_shell_and_path(
+            Default::default(),
+            Shell::Zsh,
+            Target::Tmux(None),
+            PathBuf::new(),
+            PathBuf::new(),
+            Default::default(),
+        )
+        .set_config(toml::toml! {
+            format="[100%](red)"
+        });
+
+        assert_eq!(context.shell, Shell::Unknown);
+        let expected = String::from("#[fg=red,bg=default,none]100%#[default]");
+        let actual = get_prompt(&context);
         assert_eq!(expected, actual);
     }
 
@@ -630,7 +939,7 @@ mod test {
         context.target = Target::Continuation;
 
         let expected = String::from("><>");
-        let actual = get_prompt(context);
+        let actual = get_prompt(&context);
         assert_eq!(expected, actual);
     }
 
@@ -681,7 +990,7 @@ mod test {
         context.current_dir = dir.path().to_path_buf();
 
         let expected = String::from("\nab");
-        let actual = get_prompt(context);
+        let actual = get_prompt(&context);
         assert_eq!(expected, actual);
         dir.close()
     }
@@ -703,7 +1012,7 @@ mod test {
         context.env.insert("c", "c".to_string());
 
         let expected = String::from("\nabc");
-        let actual = get_prompt(context);
+        let actual = get_prompt(&context);
         assert_eq!(expected, actual);
     }
 
@@ -725,7 +1034,7 @@ mod test {
         context.current_dir = dir.path().to_path_buf();
 
         let expected = String::from("\ncab");
-        let actual = get_prompt(context);
+        let actual = get_prompt(&context);
         assert_eq!(expected, actual);
         dir.close()
     }
@@ -750,7 +1059,7 @@ mod test {
         context.env.insert("d", "d".to_string());
 
         let expected = String::from("\ncdab");
-        let actual = get_prompt(context);
+        let actual = get_prompt(&context);
         assert_eq!(expected, actual);
     }
 
@@ -769,7 +1078,7 @@ mod test {
         context.current_dir = dir.path().to_path_buf();
 
         let expected = String::from("\nb");
-        let actual = get_prompt(context);
+        let actual = get_prompt(&context);
         assert_eq!(expected, actual);
         dir.close()
     }
@@ -786,7 +1095,7 @@ mod test {
         context.current_dir = dir.path().to_path_buf();
 
         let expected = String::from("\n");
-        let actual = get_prompt(context);
+        let actual = get_prompt(&context);
         assert_eq!(expected, actual);
         dir.close()
     }
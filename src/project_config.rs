@@ -0,0 +1,284 @@
+use sha1::{Digest, Sha1};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::context::Context;
+use crate::utils;
+
+/// The name of the config file a project can overlay the user config with
+pub const PROJECT_CONFIG_FILE: &str = ".starship.toml";
+
+/// Returns the directory starship keeps data in that should not be cleared with its cache.
+fn data_dir(context: &Context) -> Option<PathBuf> {
+    context.get_env_os("STARSHIP_DATA").map_or_else(
+        || {
+            context
+                .get_home()
+                .map(|home| home.join(".local").join("share"))
+                .or_else(dirs::data_dir)
+                .map(|dir| dir.join("starship"))
+        },
+        |dir| Some(PathBuf::from(dir)),
+    )
+}
+
+/// The trusted project config files, mapped to the hash of their trusted content
+const TRUSTED_FILE: &str = "trusted.json";
+
+/// The project config files that were warned about, mapped to a hash of the warning and the
+/// content it was about
+const WARNED_FILE: &str = "warned.json";
+
+fn read_store(context: &Context, name: &str) -> BTreeMap<PathBuf, String> {
+    data_dir(context)
+        .and_then(|dir| utils::read_file(dir.join(name)).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_store(context: &Context, name: &str, store: &BTreeMap<PathBuf, String>) -> io::Result<()> {
+    let file = data_dir(context).map(|dir| dir.join(name)).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "Unable to determine the data directory",
+        )
+    })?;
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(file, serde_json::to_string_pretty(store)?)
+}
+
+/// Project configs are stored by their canonical path, so a file is found no matter
+/// through which symlinks it was reached
+fn store_key(path: &Path) -> PathBuf {
+    dunce::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
+
+/// Logs `message` as a warning the first time it applies to this content of `path`, and only
+/// as a debug message on later prompts. Returns whether it was logged as a warning.
+fn warn_once(context: &Context, path: &Path, hash: &str, message: &str) -> bool {
+    let key = store_key(path);
+    let id = content_hash(&format!("{hash}\0{message}"));
+    let mut warned = read_store(context, WARNED_FILE);
+    if warned.get(&key) == Some(&id) {
+        log::debug!("{message}");
+        return false;
+    }
+    log::warn!("{message}");
+    warned.insert(key, id);
+    if let Err(e) = write_store(context, WARNED_FILE, &warned) {
+        log::debug!("Unable to record the warning about {path:?}: {e}");
+    }
+    true
+}
+
+fn content_hash(content: &str) -> String {
+    utils::encode_to_hex(&Sha1::digest(content.as_bytes()))
+}
+
+/// Finds the project config file that applies to the current directory
+fn find(context: &Context) -> Option<PathBuf> {
+    context
+        .begin_ancestor_scan()
+        .set_files(&[PROJECT_CONFIG_FILE])
+        .scan()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+}
+
+/// Loads the project config file that applies to the current directory.
+///
+/// Project configs can run commands through modules like `custom`, so a file is only
+/// loaded if its current content has been trusted with `starship trust`.
+pub fn load(context: &Context) -> Option<toml::Table> {
+    let path = find(context)?;
+    let content = utils::read_file(&path)
+        .map_err(|e| log::warn!("Unable to read project config {path:?}: {e}"))
+        .ok()?;
+
+    let hash = content_hash(&content);
+    if read_store(context, TRUSTED_FILE).get(&store_key(&path)) != Some(&hash) {
+        let message = format!(
+            "Ignoring project config {path:?}, run `starship trust` in {:?} if you trust its content",
+            path.parent().unwrap_or(&path)
+        );
+        warn_once(context, &path, &hash, &message);
+        return None;
+    }
+
+    let mut table: toml::Table = toml::from_str(&content)
+        .map_err(|e| log::error!("Unable to parse project config {path:?}: {e}"))
+        .ok()?;
+    // Included files are not covered by the trusted hash
+    if table.remove("include").is_some() {
+        let message = format!("Ignoring `include` in project config {path:?}");
+        warn_once(context, &path, &hash, &message);
+    }
+    log::debug!("Project config loaded from {path:?}");
+    Some(table)
+}
+
+/// Trusts the current content of the project config that applies to the current directory
+pub fn trust(context: &Context) -> io::Result<PathBuf> {
+    let path = find_or_error(context)?;
+    let content = utils::read_file(&path)?;
+
+    let mut trusted = read_store(context, TRUSTED_FILE);
+    trusted.insert(store_key(&path), content_hash(&content));
+    write_store(context, TRUSTED_FILE, &trusted)?;
+    Ok(path)
+}
+
+/// Stops trusting the project config that applies to the current directory
+pub fn revoke(context: &Context) -> io::Result<PathBuf> {
+    let path = find_or_error(context)?;
+
+    let mut trusted = read_store(context, TRUSTED_FILE);
+    if trusted.remove(&store_key(&path)).is_some() {
+        write_store(context, TRUSTED_FILE, &trusted)?;
+    }
+    Ok(path)
+}
+
+fn find_or_error(context: &Context) -> io::Result<PathBuf> {
+    find(context).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "No {PROJECT_CONFIG_FILE} found in {} or its parent directories",
+                context.current_dir.display()
+            ),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{Shell, Target};
+    use crate::context_env::Env;
+
+    fn context_in(dir: &std::path::Path, data_dir: &std::path::Path) -> Context<'static> {
+        let mut env = Env::default();
+        env.insert("STARSHIP_DATA", data_dir.to_string_lossy().to_string());
+        env.insert(
+            "STARSHIP_CONFIG",
+            data_dir.join("none.toml").to_string_lossy().to_string(),
+        );
+        Context::new_with_shell_and_path(
+            Default::default(),
+            Shell::Unknown,
+            Target::Main,
+            dir.to_owned(),
+            dir.to_owned(),
+            env,
+        )
+    }
+
+    #[test]
+    fn loads_trusted_config_only() -> io::Result<()> {
+        let project = tempfile::tempdir()?;
+        let data = tempfile::tempdir()?;
+        let nested = project.path().join("infra");
+        fs::create_dir(&nested)?;
+        let config = project.path().join(PROJECT_CONFIG_FILE);
+        utils::write_file(
+            &config,
+            "add_newline = false\n[terraform]\ndisabled = false",
+        )?;
+
+        let context = context_in(&nested, data.path());
+        assert!(load(&context).is_none());
+        assert!(context.root_config.add_newline);
+
+        let trusted = trust(&context)?;
+        assert_eq!(trusted, dunce::canonicalize(&config)?);
+        let context = context_in(&nested, data.path());
+        assert!(!context.root_config.add_newline);
+        assert_eq!(
+            context.config.get_config(&["terraform", "disabled"]),
+            Some(&toml::Value::Boolean(false))
+        );
+
+        // Changing the file requires trusting it again
+        utils::write_file(
+            &config,
+            "add_newline = false\n[custom.deploy]\ncommand = 'evil'",
+        )?;
+        let context = context_in(&nested, data.path());
+        assert!(context.root_config.add_newline);
+        assert!(context.config.get_config(&["custom"]).is_none());
+
+        trust(&context)?;
+        revoke(&context)?;
+        assert!(load(&context).is_none());
+        project.close()?;
+        data.close()
+    }
+
+    #[test]
+    fn ignores_includes() -> io::Result<()> {
+        let project = tempfile::tempdir()?;
+        let data = tempfile::tempdir()?;
+        utils::write_file(
+            project.path().join(PROJECT_CONFIG_FILE),
+            "include = ['other.toml']\nformat = 'project'",
+        )?;
+        utils::write_file(project.path().join("other.toml"), "add_newline = false")?;
+
+        let context = context_in(project.path(), data.path());
+        trust(&context)?;
+        assert_eq!(load(&context), Some(toml::toml! { format = "project" }));
+        project.close()?;
+        data.close()
+    }
+
+    #[test]
+    fn warns_once_per_content() -> io::Result<()> {
+        let project = tempfile::tempdir()?;
+        let data = tempfile::tempdir()?;
+        let config = project.path().join(PROJECT_CONFIG_FILE);
+        let context = context_in(project.path(), data.path());
+
+        assert!(warn_once(&context, &config, "hash", "untrusted"));
+        assert!(!warn_once(&context, &config, "hash", "untrusted"));
+        assert!(warn_once(&context, &config, "hash", "include"));
+        assert!(warn_once(&context, &config, "changed", "include"));
+        assert!(!warn_once(&context, &config, "changed", "include"));
+        project.close()?;
+        data.close()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn trusts_config_through_symlinks() -> io::Result<()> {
+        let project = tempfile::tempdir()?;
+        let data = tempfile::tempdir()?;
+        let link = data.path().join("link");
+        std::os::unix::fs::symlink(project.path(), &link)?;
+        utils::write_file(
+            project.path().join(PROJECT_CONFIG_FILE),
+            "format = 'project'",
+        )?;
+
+        trust(&context_in(&link, data.path()))?;
+        let context = context_in(project.path(), data.path());
+        assert_eq!(load(&context), Some(toml::toml! { format = "project" }));
+
+        revoke(&context)?;
+        assert!(load(&context_in(&link, data.path())).is_none());
+        project.close()?;
+        data.close()
+    }
+
+    #[test]
+    fn trust_without_project_config() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let context = context_in(dir.path(), dir.path());
+        let error = trust(&context).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        dir.close()
+    }
+}
@@ -3,10 +3,11 @@ use crate::{
     print::{Grapheme, UnicodeWidthGraphemes},
 };
 use nu_ansi_term::{AnsiString, Style as AnsiStyle};
+use serde::{Deserialize, Serialize};
 use unicode_segmentation::UnicodeSegmentation;
 
 /// Type that holds text with an associated style
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct TextSegment {
     /// The segment's style. If None, will inherit the style of the module containing it.
     style: Option<Style>,
@@ -26,7 +27,7 @@ impl TextSegment {
 }
 
 /// Type that holds fill text with an associated style
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct FillSegment {
     /// The segment's style. If None, will inherit the style of the module containing it.
     style: Option<Style>,
@@ -90,12 +91,33 @@ mod fill_seg_tests {
     }
 }
 
+/// Type that marks the start of a module in a powerline group, with the glyphs to draw around it
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GroupSegment {
+    /// The style of the group. Its background is used for the transition glyphs.
+    pub style: Style,
+
+    /// Drawn between two different groups
+    pub separator: String,
+
+    /// Drawn before a group that doesn't follow another group
+    pub start_symbol: String,
+
+    /// Drawn after a group that isn't followed by another group
+    pub end_symbol: String,
+}
+
 /// A segment is a styled text chunk ready for printing.
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Segment {
     Text(TextSegment),
     Fill(FillSegment),
     LineTerm,
+    /// Start of a module in a powerline group, rendered as the transition from whatever precedes it
+    GroupStart(GroupSegment),
+    /// End of a module in a powerline group
+    GroupEnd,
 }
 
 impl Segment {
@@ -132,7 +154,7 @@ impl Segment {
         match self {
             Self::Fill(fs) => fs.style.map(|cs| cs.to_ansi_style(None)),
             Self::Text(ts) => ts.style.map(|cs| cs.to_ansi_style(None)),
-            Self::LineTerm => None,
+            Self::LineTerm | Self::GroupStart(_) | Self::GroupEnd => None,
         }
     }
 
@@ -148,7 +170,26 @@ impl Segment {
                     ts.style = style
                 }
             }
-            Self::LineTerm => {}
+            Self::LineTerm | Self::GroupStart(_) | Self::GroupEnd => {}
+        }
+    }
+
+    /// Applies the style of a powerline group, keeping the colors the segment already has
+    pub fn set_group_style(&mut self, group_style: Style) {
+        let style = match self {
+            Self::Fill(FillSegment { style, .. }) | Self::Text(TextSegment { style, .. }) => style,
+            Self::LineTerm | Self::GroupStart(_) | Self::GroupEnd => return,
+        };
+        *style = Some(style.map_or(group_style, |style| style.with_defaults(&group_style)));
+    }
+
+    /// Transforms the value of a text segment
+    pub fn map_text<F>(&mut self, f: F)
+    where
+        F: FnOnce(&str) -> String,
+    {
+        if let Self::Text(ts) = self {
+            ts.value = f(&ts.value);
         }
     }
 
@@ -157,6 +198,7 @@ impl Segment {
             Self::Fill(fs) => &fs.value,
             Self::Text(ts) => &ts.value,
             Self::LineTerm => LINE_TERMINATOR_STRING,
+            Self::GroupStart(_) | Self::GroupEnd => "",
         }
     }
 
@@ -166,6 +208,7 @@ impl Segment {
             Self::Fill(fs) => fs.ansi_string(None, prev),
             Self::Text(ts) => ts.ansi_string(prev),
             Self::LineTerm => AnsiString::from(LINE_TERMINATOR_STRING),
+            Self::GroupStart(_) | Self::GroupEnd => AnsiString::from(""),
         }
     }
 
@@ -173,9 +216,125 @@ impl Segment {
         match self {
             Self::Fill(fs) => fs.value.width_graphemes(),
             Self::Text(ts) => ts.value.width_graphemes(),
-            Self::LineTerm => 0,
+            Self::LineTerm | Self::GroupStart(_) | Self::GroupEnd => 0,
+        }
+    }
+}
+
+/// Replaces the powerline group markers with the glyphs drawn between groups
+pub fn draw_group_transitions(segments: Vec<Segment>) -> Vec<Segment> {
+    fn push_glyph(segments: &mut Vec<Segment>, symbol: &str, style: Style) {
+        if !symbol.is_empty() {
+            segments.extend(Segment::from_text(Some(style), symbol));
+        }
+    }
+
+    let mut result = Vec::with_capacity(segments.len());
+    // The group that is currently drawn, and whether its last module has ended
+    let mut group: Option<GroupSegment> = None;
+    let mut group_ended = false;
+
+    for segment in segments {
+        match segment {
+            Segment::GroupStart(next) => {
+                match &group {
+                    // Modules of the same group aren't separated
+                    Some(current) if current.style == next.style => {}
+                    Some(_) => push_glyph(
+                        &mut result,
+                        &next.separator,
+                        Style::transition_to(Some(&next.style)),
+                    ),
+                    None => push_glyph(
+                        &mut result,
+                        &next.start_symbol,
+                        next.style.background_as_foreground(),
+                    ),
+                }
+                group = Some(next);
+                group_ended = false;
+            }
+            Segment::GroupEnd => group_ended = true,
+            segment => {
+                let is_visible =
+                    !matches!(segment, Segment::Text(_)) || !segment.value().is_empty();
+                if group_ended && is_visible {
+                    if let Some(current) = group.take() {
+                        push_glyph(&mut result, &current.end_symbol, Style::transition_to(None));
+                    }
+                    group_ended = false;
+                }
+                result.push(segment);
+            }
         }
     }
+    if let Some(current) = group {
+        push_glyph(&mut result, &current.end_symbol, Style::transition_to(None));
+    }
+    result
+}
+
+#[cfg(test)]
+mod group_tests {
+    use super::*;
+    use nu_ansi_term::Color;
+
+    fn group(color: Color) -> Segment {
+        Segment::GroupStart(GroupSegment {
+            style: Color::Black.on(color).into(),
+            separator: ">".to_string(),
+            start_symbol: "[".to_string(),
+            end_symbol: "]".to_string(),
+        })
+    }
+
+    fn values(segments: Vec<Segment>) -> Vec<String> {
+        draw_group_transitions(segments)
+            .iter()
+            .map(|segment| segment.value().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn draws_transitions_between_groups() {
+        let mut segments = vec![group(Color::Blue)];
+        segments.extend(Segment::from_text(None, "a"));
+        segments.extend([Segment::GroupEnd, group(Color::Blue)]);
+        segments.extend(Segment::from_text(None, "b"));
+        segments.extend([Segment::GroupEnd, group(Color::Red)]);
+        segments.extend(Segment::from_text(None, "c"));
+        segments.push(Segment::GroupEnd);
+        segments.extend(Segment::from_text(None, ""));
+        segments.extend(Segment::from_text(None, " $"));
+
+        assert_eq!(values(segments), ["[", "a", "b", ">", "c", "", "]", " $"]);
+    }
+
+    #[test]
+    fn closes_groups_at_line_ends() {
+        let mut segments = vec![group(Color::Blue)];
+        segments.extend(Segment::from_text(None, "a"));
+        segments.extend([Segment::GroupEnd, Segment::LineTerm, group(Color::Red)]);
+        segments.extend(Segment::from_text(None, "b"));
+        segments.push(Segment::GroupEnd);
+
+        assert_eq!(values(segments), ["[", "a", "]", "\n", "[", "b", "]"]);
+    }
+
+    #[test]
+    fn transition_styles() {
+        let mut segments = vec![group(Color::Blue)];
+        segments.extend(Segment::from_text(None, "a"));
+        segments.extend([Segment::GroupEnd, group(Color::Red)]);
+        segments.push(Segment::GroupEnd);
+
+        let segments = draw_group_transitions(segments);
+        let prev = Color::Black.on(Color::Blue);
+        let separator = segments[2].ansi_string(Some(&prev));
+        assert_eq!(separator.style_ref(), &Color::Blue.on(Color::Red));
+        let end = segments[3].ansi_string(Some(&Color::Black.on(Color::Red)));
+        assert_eq!(end.style_ref(), &Color::Red.normal());
+    }
 }
 
 const LINE_TERMINATOR: char = '\n';
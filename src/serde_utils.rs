@@ -183,6 +183,15 @@ impl<'de> Deserializer<'de> for ValueDeserializer<'de> {
             return visitor.visit_none();
         }
 
+        // `async` is read by the prompt renderer for every module
+        if self.current_key == Some("async")
+            && self
+                .info
+                .is_some_and(|StructInfo { name, .. }| name != "StarshipRootConfig")
+        {
+            return visitor.visit_none();
+        }
+
         if !self.error_on_ignored {
             return visitor.visit_none();
         }
@@ -373,6 +382,34 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_deserialize_async_module_key() {
+        let value = toml::toml! {
+            foo = "bar"
+            async = true
+        };
+        let deserializer = ValueDeserializer::new(&value);
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct SampleConfig {
+            foo: String,
+        }
+
+        let result = SampleConfig::deserialize(deserializer);
+        assert!(result.is_ok());
+
+        let value = toml::toml! {
+            async = true
+        };
+        let deserializer = ValueDeserializer::new(&value);
+        let result = StarshipRootConfig::deserialize(deserializer).unwrap_err();
+        assert_eq!(
+            format!("{result}"),
+            "Error in 'StarshipRoot' at 'async': Unknown key (Did you mean 'async_placeholder'?)"
+        );
+    }
+
     #[test]
     fn test_deserialize_unknown_root_config() {
         let value = toml::toml! {
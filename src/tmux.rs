@@ -0,0 +1,108 @@
+use nu_ansi_term::{AnsiString, Color, Style};
+use std::fmt::Write;
+
+/// Renders styled strings with tmux `#[fg=...,bg=...]` markup instead of ANSI escape sequences,
+/// for use in `status-left`, `status-right` or `set-titles-string`
+pub fn to_markup(strings: &[AnsiString]) -> String {
+    let mut markup = String::new();
+    let mut current = Style::new();
+    for string in strings {
+        let style = *string.style_ref();
+        if style != current {
+            write_style(&mut markup, &style);
+            current = style;
+        }
+        // tmux expands `#` as the start of a format, `##` is a literal `#`
+        markup.push_str(&string.as_str().replace('#', "##"));
+    }
+    if current != Style::new() {
+        markup.push_str("#[default]");
+    }
+    markup
+}
+
+fn write_style(markup: &mut String, style: &Style) {
+    if *style == Style::new() {
+        markup.push_str("#[default]");
+        return;
+    }
+
+    write!(
+        markup,
+        "#[fg={},bg={},none",
+        style.foreground.map_or_else(|| "default".into(), color),
+        style.background.map_or_else(|| "default".into(), color),
+    )
+    .unwrap();
+    let attributes = [
+        (style.is_bold, "bold"),
+        (style.is_dimmed, "dim"),
+        (style.is_italic, "italics"),
+        (style.is_underline, "underscore"),
+        (style.is_blink, "blink"),
+        (style.is_reverse, "reverse"),
+        (style.is_hidden, "hidden"),
+        (style.is_strikethrough, "strikethrough"),
+    ];
+    for (_, name) in attributes.iter().filter(|(enabled, _)| *enabled) {
+        write!(markup, ",{name}").unwrap();
+    }
+    markup.push(']');
+}
+
+/// Returns the name tmux uses for a color
+fn color(color: Color) -> String {
+    match color {
+        Color::Black => "black".into(),
+        Color::Red => "red".into(),
+        Color::Green => "green".into(),
+        Color::Yellow => "yellow".into(),
+        Color::Blue => "blue".into(),
+        Color::Purple | Color::Magenta => "magenta".into(),
+        Color::Cyan => "cyan".into(),
+        Color::White => "white".into(),
+        Color::DarkGray => "brightblack".into(),
+        Color::LightRed => "brightred".into(),
+        Color::LightGreen => "brightgreen".into(),
+        Color::LightYellow => "brightyellow".into(),
+        Color::LightBlue => "brightblue".into(),
+        Color::LightPurple | Color::LightMagenta => "brightmagenta".into(),
+        Color::LightCyan => "brightcyan".into(),
+        Color::LightGray => "brightwhite".into(),
+        Color::Fixed(n) => format!("colour{n}"),
+        Color::Rgb(r, g, b) => format!("#{r:02x}{g:02x}{b:02x}"),
+        Color::Default => "default".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_text() {
+        let strings = [AnsiString::from("~/src "), AnsiString::from("#1")];
+        assert_eq!(to_markup(&strings), "~/src ##1");
+    }
+
+    #[test]
+    fn styled_text() {
+        let strings = [
+            Color::Red.bold().paint("a"),
+            Color::Red.bold().paint("b"),
+            Color::Fixed(208)
+                .on(Color::Rgb(0, 128, 255))
+                .italic()
+                .paint("c"),
+            AnsiString::from(" "),
+            Color::LightGray.underline().paint("d"),
+        ];
+        assert_eq!(
+            to_markup(&strings),
+            "#[fg=red,bg=default,none,bold]ab\
+             #[fg=colour208,bg=#0080ff,none,italics]c\
+             #[default] \
+             #[fg=brightwhite,bg=default,none,underscore]d#[default]"
+        );
+    }
+}
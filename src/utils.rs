@@ -1,4 +1,5 @@
 use process_control::{ChildExt, Control};
+use serde::{Deserialize, Serialize};
 use std::ffi::OsStr;
 use std::fmt::Debug;
 use std::fs::read_to_string;
@@ -97,8 +98,27 @@ pub fn get_command_string_output(command: CommandOutput) -> String {
 pub fn create_command<T: AsRef<OsStr>>(binary_name: T) -> Result<Command> {
     let binary_name = binary_name.as_ref();
     log::trace!("Creating Command for binary {:?}", binary_name);
+    command_for(binary_name, which::which(binary_name))
+}
+
+/// Like `create_command`, but resolves `binary_name` in the given `PATH` instead of the `PATH`
+/// of the process
+pub fn create_command_in<T: AsRef<OsStr>>(
+    binary_name: T,
+    paths: Option<&OsStr>,
+    cwd: &Path,
+) -> Result<Command> {
+    let binary_name = binary_name.as_ref();
+    log::trace!(
+        "Creating Command for binary {:?} in {:?}",
+        binary_name,
+        paths
+    );
+    command_for(binary_name, which::which_in(binary_name, paths, cwd))
+}
 
-    let full_path = match which::which(binary_name) {
+fn command_for(binary_name: &OsStr, resolved: which::Result<PathBuf>) -> Result<Command> {
+    let full_path = match resolved {
         Ok(full_path) => {
             log::trace!("Using {:?} as {:?}", full_path, binary_name);
             full_path
@@ -118,7 +138,7 @@ pub fn create_command<T: AsRef<OsStr>>(binary_name: T) -> Result<Command> {
     Ok(cmd)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandOutput {
     pub stdout: String,
     pub stderr: String,